@@ -39,8 +39,13 @@
 //! based on the [`Optimizer`] configuration. For large files,
 //! this can provide significant speed improvements.
 
+use crate::checksum::{self, ChecksumAlgorithm};
 use crate::config::ProxyConfig;
+use crate::delta::{self, DeltaControl, DeltaReport};
+use crate::error::KgetError;
+use crate::metalink::{self, HttpMetalink};
 use crate::optimization::Optimizer;
+use crate::store::{self, OutputMode};
 use hex;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
@@ -63,6 +68,8 @@ use std::os::windows::fs::FileExt;
 const MIN_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
 /// Maximum retry attempts per chunk
 const MAX_RETRIES: usize = 3;
+/// Redirects followed while probing, as many as reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
 
 /// Controls how [`AdvancedDownloader`] behaves when user interaction would otherwise be required.
 ///
@@ -177,7 +184,6 @@ pub struct AdvancedDownloader {
     url: String,
     output_path: String,
     quiet_mode: bool,
-    #[allow(dead_code)]
     proxy: ProxyConfig,
     optimizer: Optimizer,
     progress_callback: Option<Arc<dyn Fn(f32) + Send + Sync>>,
@@ -186,6 +192,10 @@ pub struct AdvancedDownloader {
     expected_sha256: Option<String>,
     extra_headers: Vec<(String, String)>,
     resume_policy: ResumePolicy,
+    /// Same settings as `client`, but redirects are left to us so the
+    /// `Link` headers on a mirror redirector's `302` are not lost.
+    probe_client: Client,
+    follow_mirror_links: bool,
 }
 
 impl AdvancedDownloader {
@@ -221,33 +231,40 @@ impl AdvancedDownloader {
     ) -> Result<Self, Box<dyn Error + Send + Sync>> {
         let _is_iso = url.to_lowercase().ends_with(".iso");
 
-        let mut client_builder = Client::builder()
-            .timeout(std::time::Duration::from_secs(300))
-            .connect_timeout(std::time::Duration::from_secs(20))
-            .user_agent(concat!("KGet/", env!("CARGO_PKG_VERSION")))
-            .no_gzip()
-            .no_deflate();
-
-        if proxy_config.enabled {
-            if let Some(proxy_url) = &proxy_config.url {
-                let proxy = match proxy_config.proxy_type {
-                    crate::config::ProxyType::Http => reqwest::Proxy::http(proxy_url),
-                    crate::config::ProxyType::Https => reqwest::Proxy::https(proxy_url),
-                    crate::config::ProxyType::Socks5 => reqwest::Proxy::all(proxy_url),
-                };
-
-                if let Ok(mut proxy) = proxy {
-                    if let (Some(username), Some(password)) =
-                        (&proxy_config.username, &proxy_config.password)
-                    {
-                        proxy = proxy.basic_auth(username, password);
+        let client_builder = || {
+            let mut client_builder = Client::builder()
+                .timeout(std::time::Duration::from_secs(300))
+                .connect_timeout(std::time::Duration::from_secs(20))
+                .user_agent(concat!("KGet/", env!("CARGO_PKG_VERSION")))
+                .no_gzip()
+                .no_deflate();
+
+            if proxy_config.enabled {
+                if let Some(proxy_url) = &proxy_config.url {
+                    let proxy = match proxy_config.proxy_type {
+                        crate::config::ProxyType::Http => reqwest::Proxy::http(proxy_url),
+                        crate::config::ProxyType::Https => reqwest::Proxy::https(proxy_url),
+                        crate::config::ProxyType::Socks5 => reqwest::Proxy::all(proxy_url),
+                    };
+
+                    if let Ok(mut proxy) = proxy {
+                        if let (Some(username), Some(password)) =
+                            (&proxy_config.username, &proxy_config.password)
+                        {
+                            proxy = proxy.basic_auth(username, password);
+                        }
+                        client_builder = client_builder.proxy(proxy);
                     }
-                    client_builder = client_builder.proxy(proxy);
                 }
             }
-        }
+            client_builder
+        };
 
-        let client = client_builder
+        let client = client_builder()
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let probe_client = client_builder()
+            .redirect(reqwest::redirect::Policy::none())
             .build()
             .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
@@ -264,6 +281,8 @@ impl AdvancedDownloader {
             expected_sha256: None,
             extra_headers: Vec::new(),
             resume_policy: ResumePolicy::default(),
+            probe_client,
+            follow_mirror_links: true,
         })
     }
 
@@ -349,6 +368,15 @@ impl AdvancedDownloader {
         self.resume_policy = policy;
     }
 
+    /// Switch to multi-mirror mode when the server lists mirrors with RFC 6249
+    /// `Link: <…>; rel=duplicate` headers (on by default).
+    ///
+    /// The mirrors, the original URL as the last resort and any `Digest`
+    /// header are handed to [`crate::metalink::download_file`].
+    pub fn set_follow_mirror_links(&mut self, enabled: bool) {
+        self.follow_mirror_links = enabled;
+    }
+
     /// Apply `self.extra_headers` to a request builder.
     fn apply_headers(&self, mut req: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
         for (name, value) in &self.extra_headers {
@@ -390,6 +418,10 @@ impl AdvancedDownloader {
     /// - Cancellation via cancel token
     /// - Disk I/O errors
     pub fn download(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.reuse_from_store()? {
+            return Ok(());
+        }
+
         let is_iso = self.url.to_lowercase().ends_with(".iso");
         if !self.quiet_mode {
             println!("Starting advanced download for: {}", self.url);
@@ -418,11 +450,15 @@ impl AdvancedDownloader {
         if !self.quiet_mode {
             println!("Querying server for file size and range support...");
         }
-        let (total_size, supports_range) = self.get_file_size_and_range()?;
+        let RemoteFile { size: total_size, supports_range, links } = self.get_file_size_and_range()?;
         if !self.quiet_mode {
             println!("Total file size: {} bytes", total_size);
             println!("Server supports range requests: {}", supports_range);
         }
+        if self.follow_mirror_links && links.has_mirrors() {
+            return self.download_from_mirrors(links, total_size);
+        }
+        let digests = links.digests;
 
         if let Some(size) = existing_size {
             if size > total_size {
@@ -441,35 +477,14 @@ impl AdvancedDownloader {
         };
 
         // Create a progress bar if not quiet or if we have a callback
-        let progress = if !self.quiet_mode || self.progress_callback.is_some() {
-            let bar = ProgressBar::new(total_size);
-            if let Some(size) = existing_size {
-                bar.set_position(size);
-            }
-            if self.quiet_mode {
-                bar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
-            } else {
-                bar.set_style(ProgressStyle::with_template(
-                    "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})"
-                ).unwrap().progress_chars("#>-"));
-            }
-            Some(Arc::new(Mutex::new(bar)))
-        } else {
-            None
-        };
+        let progress = self.new_progress_bar(total_size, existing_size.unwrap_or(0));
 
         // Create or open the output file and preallocate
         if !self.quiet_mode {
             println!("Preparing output file: {}", self.output_path);
         }
-        let file = if existing_size.is_some() {
-            File::options()
-                .read(true)
-                .write(true)
-                .open(&self.output_path)?
-        } else {
-            File::create(&self.output_path)?
-        };
+        let mode = if existing_size.is_some() { OutputMode::Update } else { OutputMode::Create };
+        let file = store::open_output(Path::new(&self.output_path), mode)?;
 
         // If range not supported, do a single download (no preallocation required)
         if !supports_range {
@@ -485,7 +500,7 @@ impl AdvancedDownloader {
             if !self.quiet_mode {
                 println!("Single-threaded download completed");
             }
-            return Ok(());
+            return self.verify_header_digests(&digests);
         }
 
         // Range supported: preallocate file so parallel chunk writes land at the right offsets.
@@ -527,8 +542,11 @@ impl AdvancedDownloader {
                 .finish_with_message("Download completed");
         }
 
-        // Verify download integrity
-        if !self.quiet_mode || self.status_callback.is_some() {
+        self.verify_header_digests(&digests)?;
+
+        // Verify download integrity. An expected SHA-256 is always checked,
+        // even in quiet mode, so the file can be added to the content store.
+        if !self.quiet_mode || self.status_callback.is_some() || self.expected_sha256.is_some() {
             if is_iso || self.expected_sha256.is_some() {
                 let should_verify = if self.status_callback.is_some()
                     || self.expected_sha256.is_some()
@@ -551,6 +569,7 @@ impl AdvancedDownloader {
 
                 if should_verify {
                     self.verify_integrity(total_size)?;
+                    self.add_to_store();
                 }
             } else {
                 let metadata = std::fs::metadata(&self.output_path)?;
@@ -569,9 +588,116 @@ impl AdvancedDownloader {
         Ok(())
     }
 
-    fn get_file_size_and_range(&self) -> Result<(u64, bool), Box<dyn Error + Send + Sync>> {
-        let head_response = self.apply_headers(self.client.head(&self.url)).send();
-        let Ok(response) = head_response else {
+    /// Reconstruct the file from an older local copy plus ranged requests.
+    ///
+    /// Blocks described by `control` (from a `.zsync` file or Metalink piece
+    /// hashes, see [`crate::delta`]) are looked up in `seed` and copied into
+    /// the output file; only the blocks the seed lacks are fetched, through
+    /// the same parallel chunk engine as [`download`](Self::download). The
+    /// result is checked against the control's checksums and, when set, the
+    /// expected SHA-256.
+    ///
+    /// # Errors
+    ///
+    /// - `seed` and the output path are the same file
+    /// - The server ignores range requests
+    /// - The reconstructed file fails verification
+    pub fn download_delta(
+        &self,
+        control: &DeltaControl,
+        seed: &Path,
+    ) -> Result<DeltaReport, Box<dyn Error + Send + Sync>> {
+        let output = Path::new(&self.output_path);
+        if output.exists() && seed.canonicalize()? == output.canonicalize()? {
+            return Err("Seed and output must be different files".into());
+        }
+
+        self.send_status(&format!(
+            "Matching {} blocks of {} bytes against seed {}",
+            control.block_count(),
+            control.block_size,
+            seed.display()
+        ));
+        let file = store::open_output(output, OutputMode::Create)?;
+        file.set_len(control.length)?;
+
+        let have = delta::fill_from_seed(control, seed, &file)?;
+        let ranges = delta::missing_ranges(control, &have);
+        let missing: u64 = ranges.iter().map(|(start, end)| end - start).sum();
+        let report = DeltaReport {
+            total_bytes: control.length,
+            reused_bytes: control.length - missing,
+            downloaded_bytes: missing,
+            blocks_total: control.block_count(),
+            blocks_reused: have.iter().filter(|h| **h).count(),
+        };
+        self.send_status(&format!(
+            "Seed provides {}/{} blocks; fetching {} bytes",
+            report.blocks_reused, report.blocks_total, missing
+        ));
+
+        if !ranges.is_empty() {
+            if self.is_cancelled() {
+                return Err("Download cancelled".into());
+            }
+            // Split long gaps so they spread across connections like a normal download.
+            let chunk_size = self.calculate_chunks(missing, None)?.first().map_or(missing, |c| c.1);
+            let chunks: Vec<(u64, u64)> = ranges
+                .iter()
+                .flat_map(|&(start, end)| {
+                    (start..end)
+                        .step_by(chunk_size.max(1) as usize)
+                        .map(move |s| (s, (s + chunk_size).min(end)))
+                })
+                .collect();
+
+            let progress = self.new_progress_bar(missing, 0);
+            let throttle_bucket = self.optimizer.speed_limit.map(|limit| {
+                Arc::new(Mutex::new(TokenBucket::new(limit)))
+            });
+            self.download_chunks_parallel(chunks, &file, progress.clone(), missing, 0, throttle_bucket)?;
+            if let Some(ref bar) = progress {
+                bar.lock()
+                    .expect("Progress bar mutex was poisoned")
+                    .finish_with_message("Download completed");
+            }
+        }
+        drop(file);
+
+        delta::verify(control, output)?;
+        if self.expected_sha256.is_some() {
+            self.verify_integrity(control.length)?;
+        }
+
+        self.send_status(&format!(
+            "Delta download complete: reused {} of {} bytes ({:.1}% saved), downloaded {} bytes",
+            report.reused_bytes,
+            report.total_bytes,
+            report.saved_percent(),
+            report.downloaded_bytes
+        ));
+        Ok(report)
+    }
+
+    fn new_progress_bar(&self, total: u64, position: u64) -> Option<Arc<Mutex<ProgressBar>>> {
+        if self.quiet_mode && self.progress_callback.is_none() {
+            return None;
+        }
+        let bar = ProgressBar::new(total);
+        bar.set_position(position);
+        if self.quiet_mode {
+            bar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+        } else {
+            bar.set_style(ProgressStyle::with_template(
+                "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})"
+            ).unwrap().progress_chars("#>-"));
+        }
+        Some(Arc::new(Mutex::new(bar)))
+    }
+
+    fn get_file_size_and_range(&self) -> Result<RemoteFile, Box<dyn Error + Send + Sync>> {
+        let head_response = self.head_following_redirects();
+        let Ok((response, links)) = head_response else {
             return self.get_file_size_with_range_probe();
         };
 
@@ -593,17 +719,22 @@ impl AdvancedDownloader {
             .unwrap_or(false);
 
         if let Some(content_length) = content_length {
-            Ok((content_length, accepts_range))
+            Ok(RemoteFile {
+                size: content_length,
+                supports_range: accepts_range,
+                links,
+            })
         } else {
             self.get_file_size_with_range_probe()
         }
     }
 
-    fn get_file_size_with_range_probe(&self) -> Result<(u64, bool), Box<dyn Error + Send + Sync>> {
+    fn get_file_size_with_range_probe(&self) -> Result<RemoteFile, Box<dyn Error + Send + Sync>> {
         let response = self
             .apply_headers(self.client.get(&self.url))
             .header(reqwest::header::RANGE, "bytes=0-0")
             .send()?;
+        let links = HttpMetalink::from_headers(response.headers(), response.url().as_str());
 
         if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
             if let Some(total) = response
@@ -612,7 +743,7 @@ impl AdvancedDownloader {
                 .and_then(|v| v.to_str().ok())
                 .and_then(parse_content_range_total)
             {
-                return Ok((total, true));
+                return Ok(RemoteFile { size: total, supports_range: true, links });
             }
         }
 
@@ -623,13 +754,72 @@ impl AdvancedDownloader {
                 .and_then(|v| v.to_str().ok())
                 .and_then(|s| s.parse::<u64>().ok())
             {
-                return Ok((total, false));
+                return Ok(RemoteFile { size: total, supports_range: false, links });
             }
         }
 
         Err("Could not determine file size".into())
     }
 
+    /// `HEAD` the URL, following redirects by hand and collecting the mirrors
+    /// and digests every hop announces. Extra headers (credentials) are only
+    /// sent to the original host.
+    fn head_following_redirects(
+        &self,
+    ) -> Result<(reqwest::blocking::Response, HttpMetalink), Box<dyn Error + Send + Sync>> {
+        let origin = reqwest::Url::parse(&self.url)?;
+        let mut url = origin.clone();
+        let mut links = HttpMetalink::default();
+        for _ in 0..=MAX_REDIRECTS {
+            let request = self.probe_client.head(url.as_str());
+            let request = if url.origin() == origin.origin() { self.apply_headers(request) } else { request };
+            let response = request.send()?;
+            links.merge(HttpMetalink::from_headers(response.headers(), url.as_str()));
+            let next = response
+                .status()
+                .is_redirection()
+                .then(|| response.headers().get(reqwest::header::LOCATION))
+                .flatten()
+                .and_then(|l| l.to_str().ok())
+                .and_then(|l| url.join(l).ok());
+            match next {
+                Some(next) => url = next,
+                None => return Ok((response, links)),
+            }
+        }
+        Err(format!("Too many redirects for {}", self.url).into())
+    }
+
+    /// Hand the download to the Metalink engine, which tries the mirrors the
+    /// server listed and verifies the announced digests.
+    fn download_from_mirrors(&self, links: HttpMetalink, size: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mirrors = links.mirrors.len();
+        if !self.quiet_mode {
+            println!("Server lists {} mirror(s) (RFC 6249), switching to multi-mirror mode", mirrors);
+        }
+        self.send_status(&format!("Using {} mirror(s) from Link headers", mirrors));
+
+        let file = links.into_file(&self.url, Some(size));
+        metalink::download_file(&file, Path::new(&self.output_path), self.quiet_mode, &self.proxy, &self.optimizer, None)?;
+        if self.expected_sha256.is_some() {
+            self.verify_integrity(size)?;
+            self.add_to_store();
+        }
+        self.send_status("Advanced download completed successfully!");
+        Ok(())
+    }
+
+    /// Check the finished file against the digests the server announced.
+    fn verify_header_digests(&self, digests: &[(ChecksumAlgorithm, String)]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if digests.is_empty() {
+            return Ok(());
+        }
+        checksum::verify_digests(Path::new(&self.output_path), digests)?;
+        let names: Vec<&str> = digests.iter().map(|(a, _)| a.name()).collect();
+        self.send_status(&format!("Digest header verified ({})", names.join(", ")));
+        Ok(())
+    }
+
     fn calculate_chunks(
         &self,
         total_size: u64,
@@ -866,6 +1056,37 @@ impl AdvancedDownloader {
         Ok(())
     }
 
+    /// Materialize the output from the content store when the expected
+    /// SHA-256 is already there. Returns `true` if nothing needs fetching.
+    fn reuse_from_store(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let (Some(expected), Some(store)) = (&self.expected_sha256, self.optimizer.content_store()) else {
+            return Ok(false);
+        };
+        let dest = Path::new(&self.output_path);
+        match store.materialize(&ChecksumAlgorithm::Sha256, expected, dest)? {
+            Some(kind) => {
+                if !self.quiet_mode {
+                    println!("Reused {} from content store ({})", self.output_path, kind);
+                }
+                self.send_status(&format!("Reused from content store ({})", kind));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Add the verified output to the content store, if one is enabled.
+    fn add_to_store(&self) {
+        let (Some(expected), Some(store)) = (&self.expected_sha256, self.optimizer.content_store()) else {
+            return;
+        };
+        if let Err(e) = store.insert(Path::new(&self.output_path), &ChecksumAlgorithm::Sha256, expected)
+            && !self.quiet_mode
+        {
+            eprintln!("Warning: could not add to content store: {}", e);
+        }
+    }
+
     fn verify_integrity(&self, expected_size: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
         let metadata = std::fs::metadata(&self.output_path)?;
         let actual_size = metadata.len();
@@ -900,11 +1121,11 @@ impl AdvancedDownloader {
         if let Some(expected_sha256) = &self.expected_sha256 {
             let expected_sha256 = expected_sha256.trim().to_ascii_lowercase();
             if hash_hex != expected_sha256 {
-                return Err(format!(
-                    "SHA256 mismatch: expected {}, got {}",
-                    expected_sha256, hash_hex
-                )
-                .into());
+                return Err(Box::new(KgetError::ChecksumMismatch {
+                    algorithm: "sha256".to_string(),
+                    expected: expected_sha256,
+                    got: hash_hex,
+                }));
             }
             self.send_status("SHA256 matches expected hash.");
         }
@@ -914,6 +1135,13 @@ impl AdvancedDownloader {
     }
 }
 
+/// Size, range support, announced digests and mirrors of the remote file.
+struct RemoteFile {
+    size: u64,
+    supports_range: bool,
+    links: HttpMetalink,
+}
+
 fn parse_content_range_total(value: &str) -> Option<u64> {
     let (_, total) = value.rsplit_once('/')?;
     if total == "*" {
@@ -35,16 +35,23 @@
 
 use crate::DownloadOptions;
 use crate::advanced_download::AdvancedDownloader;
-use crate::checksum::{ChecksumAlgorithm, compute_checksum, parse_sidecar};
-use crate::config::{Config, ProxyConfig, ProxyType};
+use crate::checksum::{
+    ChecksumAlgorithm, compute_checksums, parse_digest_header, parse_sidecar, parse_sri, sidecar_candidates,
+};
+use crate::config::{Config, MetalinkConfig, ProxyConfig, ProxyType};
 use crate::download::download as http_download;
 use crate::error::KgetError;
-use crate::events::DownloadEvent;
+use crate::events::{DownloadEvent, FileEvent};
+use crate::local;
+use crate::metalink;
 use crate::optimization::Optimizer;
+use crate::signature::{PendingSignature, SignatureCheck, SignatureInfo};
+use crate::store::{self, ContentStore, OutputMode};
 use crate::utils;
-use std::io::{Cursor, Read};
-use std::path::Path;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -109,10 +116,17 @@ impl Default for RetryConfig {
 #[derive(Debug, Clone, Default)]
 pub struct ComputedChecksums {
     pub sha256: Option<String>,
+    pub sha384: Option<String>,
     pub sha512: Option<String>,
     pub sha1: Option<String>,
     pub md5: Option<String>,
+    pub sha3_256: Option<String>,
+    pub sha3_512: Option<String>,
+    pub blake2b: Option<String>,
     pub blake3: Option<String>,
+    pub crc32: Option<String>,
+    pub crc32c: Option<String>,
+    pub xxh3: Option<String>,
 }
 
 /// Metrics and metadata returned after a successful download.
@@ -130,28 +144,68 @@ pub struct DownloadResult {
     pub connections_used: usize,
     /// Checksums that were computed during or after the download.
     pub checksums: ComputedChecksums,
+    /// `true` if the file was linked from the content store instead of fetched.
+    pub from_store: bool,
+    /// The verified signature, when one was requested.
+    pub signature: Option<SignatureInfo>,
+    /// Where the expected checksum came from when it was looked up rather
+    /// than given: the sidecar URL, or the response header name.
+    pub checksum_source: Option<String>,
 }
 
 // ════════════════════════════════════════════════════════════════════════════
 // DownloadBuilder internals
 // ════════════════════════════════════════════════════════════════════════════
 
-#[derive(Debug, Clone, Default)]
-struct ChecksumExpectations {
-    sha256: Option<String>,
-    sha512: Option<String>,
-    sha1: Option<String>,
-    md5: Option<String>,
-    blake3: Option<String>,
-}
+/// Expected digests share the layout of the computed ones.
+type ChecksumExpectations = ComputedChecksums;
 
-impl ChecksumExpectations {
+impl ComputedChecksums {
     fn any_set(&self) -> bool {
-        self.sha256.is_some()
-            || self.sha512.is_some()
-            || self.sha1.is_some()
-            || self.md5.is_some()
-            || self.blake3.is_some()
+        !self.digests().is_empty()
+    }
+
+    /// Every digest that is set, with its algorithm.
+    fn digests(&self) -> Vec<(ChecksumAlgorithm, &str)> {
+        ChecksumAlgorithm::ALL
+            .into_iter()
+            .filter_map(|algo| self.get(&algo).map(|h| (algo, h)))
+            .collect()
+    }
+
+    fn get(&self, algo: &ChecksumAlgorithm) -> Option<&str> {
+        match algo {
+            ChecksumAlgorithm::Sha256   => &self.sha256,
+            ChecksumAlgorithm::Sha384   => &self.sha384,
+            ChecksumAlgorithm::Sha512   => &self.sha512,
+            ChecksumAlgorithm::Sha1     => &self.sha1,
+            ChecksumAlgorithm::Md5      => &self.md5,
+            ChecksumAlgorithm::Sha3_256 => &self.sha3_256,
+            ChecksumAlgorithm::Sha3_512 => &self.sha3_512,
+            ChecksumAlgorithm::Blake2b  => &self.blake2b,
+            ChecksumAlgorithm::Blake3   => &self.blake3,
+            ChecksumAlgorithm::Crc32    => &self.crc32,
+            ChecksumAlgorithm::Crc32c   => &self.crc32c,
+            ChecksumAlgorithm::Xxh3     => &self.xxh3,
+        }
+        .as_deref()
+    }
+
+    pub(crate) fn slot(&mut self, algo: &ChecksumAlgorithm) -> &mut Option<String> {
+        match algo {
+            ChecksumAlgorithm::Sha256   => &mut self.sha256,
+            ChecksumAlgorithm::Sha384   => &mut self.sha384,
+            ChecksumAlgorithm::Sha512   => &mut self.sha512,
+            ChecksumAlgorithm::Sha1     => &mut self.sha1,
+            ChecksumAlgorithm::Md5      => &mut self.md5,
+            ChecksumAlgorithm::Sha3_256 => &mut self.sha3_256,
+            ChecksumAlgorithm::Sha3_512 => &mut self.sha3_512,
+            ChecksumAlgorithm::Blake2b  => &mut self.blake2b,
+            ChecksumAlgorithm::Blake3   => &mut self.blake3,
+            ChecksumAlgorithm::Crc32    => &mut self.crc32,
+            ChecksumAlgorithm::Crc32c   => &mut self.crc32c,
+            ChecksumAlgorithm::Xxh3     => &mut self.xxh3,
+        }
     }
 }
 
@@ -171,11 +225,18 @@ pub struct DownloadBuilder {
     proxy_user: Option<String>,
     proxy_pass: Option<String>,
     checksums: ChecksumExpectations,
+    integrity: Option<String>,
     verify_from: Option<String>,
+    verify_auto: bool,
+    checksum_source: Option<String>,
     headers: Vec<(String, String)>,
     retry: RetryConfig,
     range: Option<(u64, u64)>,
     quiet: bool,
+    store: Option<ContentStore>,
+    cache: Option<bool>,
+    cache_dir: Option<String>,
+    signature: Option<SignatureCheck>,
 }
 
 impl DownloadBuilder {
@@ -190,11 +251,18 @@ impl DownloadBuilder {
             proxy_user: None,
             proxy_pass: None,
             checksums: ChecksumExpectations::default(),
+            integrity: None,
             verify_from: None,
+            verify_auto: false,
+            checksum_source: None,
             headers: Vec::new(),
             retry: RetryConfig::default(),
             range: None,
             quiet: false,
+            store: None,
+            cache: None,
+            cache_dir: None,
+            signature: None,
         }
     }
 
@@ -263,6 +331,23 @@ impl DownloadBuilder {
         self
     }
 
+    /// Expect a digest for any [`ChecksumAlgorithm`], e.g. SHA3-256 or
+    /// CRC32C.  Can be combined with the other checksum methods.
+    pub fn checksum(mut self, algorithm: ChecksumAlgorithm, hash: impl Into<String>) -> Self {
+        *self.checksums.slot(&algorithm) = Some(hash.into().to_lowercase());
+        self
+    }
+
+    /// Expect a Subresource Integrity string such as `sha512-<base64>`, as
+    /// found in npm lockfiles and HTML `integrity=` attributes.
+    ///
+    /// When several hashes are listed the strongest is used. An invalid
+    /// string makes the download fail before anything is fetched.
+    pub fn integrity(mut self, sri: impl Into<String>) -> Self {
+        self.integrity = Some(sri.into());
+        self
+    }
+
     /// Download a checksum sidecar file from `url` and use it to populate the
     /// expected digest automatically.
     ///
@@ -272,6 +357,40 @@ impl DownloadBuilder {
         self
     }
 
+    /// Look for the expected checksum next to the file when none is given.
+    ///
+    /// Tries `<file>.sha256`, `<file>.sha512`, then `SHA256SUMS`, `CHECKSUMS`
+    /// and `sha256sum.txt` in the same directory, then the `Repr-Digest` and
+    /// `Digest` response headers. The first match is used and reported in
+    /// [`DownloadResult::checksum_source`]; if nothing is found the download
+    /// goes ahead unverified. Only applies to HTTP(S) URLs.
+    pub fn verify_auto(mut self) -> Self {
+        self.verify_auto = true;
+        self
+    }
+
+    /// Verify a detached signature from `sig_url` with the trusted keys in
+    /// `keyring` (see [`crate::signature`]).
+    ///
+    /// `.minisig` signatures need a `minisign.pub` key file; `.asc`/`.sig`/
+    /// `.gpg` signatures need an exported OpenPGP key and `gpg` on `PATH`.
+    /// The signature may cover the file itself or a `SHA256SUMS`-style list
+    /// that has an entry for it. The download fails with
+    /// [`KgetError::SignatureError`] if it does not verify.
+    pub fn verify_signature(mut self, sig_url: impl Into<String>, keyring: impl Into<PathBuf>) -> Self {
+        self.signature = Some(SignatureCheck::new(Some(sig_url.into()), keyring));
+        self
+    }
+
+    /// Like [`verify_signature`](Self::verify_signature), but find the
+    /// signature automatically: `<url>.minisig`, `<url>.asc` and `<url>.sig`
+    /// are tried first, then signed `SHA256SUMS`/`SHA512SUMS` lists in the
+    /// same directory.
+    pub fn verify_signature_auto(mut self, keyring: impl Into<PathBuf>) -> Self {
+        self.signature = Some(SignatureCheck::new(None, keyring));
+        self
+    }
+
     /// Add a custom HTTP request header.  Can be called multiple times.
     pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
         self.headers.push((name.into(), value.into()));
@@ -299,27 +418,57 @@ impl DownloadBuilder {
         self
     }
 
+    /// Enable or disable the [HTTP cache](crate::cache).  Enabled by default,
+    /// like `cache_enabled` in [`Config`].
+    pub fn cache(mut self, enabled: bool) -> Self {
+        self.cache = Some(enabled);
+        self
+    }
+
+    /// Keep the HTTP cache in `dir` instead of `~/.cache/kget`.
+    pub fn cache_dir(mut self, dir: impl Into<String>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Use a content-addressed store at `dir` (see [`crate::store`]).
+    ///
+    /// If an expected checksum is already in the store the file is hardlinked
+    /// into place instead of downloaded, and every verified download is added
+    /// to it.  Has no effect without an expected checksum.
+    pub fn store(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.store = Some(ContentStore::new(dir));
+        self
+    }
+
     // ── Terminal methods ─────────────────────────────────────────────────────
 
     /// Execute the download synchronously and return metrics on success.
     pub fn download(mut self) -> Result<DownloadResult, KgetError> {
         // 1. Resolve sidecar before the main download so the hash is ready.
-        if let Some(sidecar_url) = self.verify_from.take() {
-            self.apply_sidecar(&sidecar_url)?;
-        }
+        self.resolve_checksums()?;
+        let pending_signature = self.prepare_signature()?;
 
         let output_path = self.resolve_output();
         let proxy = self.make_proxy();
         let optimizer = self.make_optimizer();
         let start = Instant::now();
 
-        // 2. Execute the download (with retry).
-        self.run_with_retry(&output_path, proxy.clone(), optimizer.clone())?;
+        // 2. Reuse a stored copy, or execute the download (with retry).
+        let from_store = self.reuse_from_store(&output_path)?;
+        if !from_store {
+            self.run_with_retry(&output_path, proxy.clone(), optimizer.clone())?;
+        }
 
         let duration = start.elapsed();
 
-        // 3. Verify checksums and collect digests.
+        // 3. Verify checksums and signature, collect digests.
         let checksums = self.verify_and_collect(Path::new(&output_path))?;
+        let signature = match &pending_signature {
+            Some(pending) => Some(pending.verify_with_digests(Path::new(&output_path), &checksums.digests())?),
+            None => None,
+        };
+        self.add_to_store(Path::new(&output_path), &checksums);
 
         // 4. Build result metrics.
         let bytes_downloaded = std::fs::metadata(&output_path)
@@ -338,6 +487,9 @@ impl DownloadBuilder {
             duration,
             connections_used: self.connections,
             checksums,
+            from_store,
+            signature,
+            checksum_source: self.checksum_source.clone(),
         })
     }
 
@@ -389,12 +541,20 @@ impl DownloadBuilder {
         let (tx, rx) = mpsc::channel::<DownloadEvent>();
         let handle = thread::spawn(move || {
             // Resolve sidecar
-            if let Some(sidecar_url) = self.verify_from.take() {
-                if let Err(e) = self.apply_sidecar(&sidecar_url) {
+            if let Err(e) = self.resolve_checksums() {
+                let _ = tx.send(DownloadEvent::Error(e.to_string()));
+                return Err(e);
+            }
+            if let Some(source) = &self.checksum_source {
+                let _ = tx.send(DownloadEvent::Status(format!("Expected checksum from {source}")));
+            }
+            let pending_signature = match self.prepare_signature() {
+                Ok(pending) => pending,
+                Err(e) => {
                     let _ = tx.send(DownloadEvent::Error(e.to_string()));
                     return Err(e);
                 }
-            }
+            };
 
             let output_path = self.resolve_output();
             let proxy = self.make_proxy();
@@ -404,8 +564,19 @@ impl DownloadBuilder {
             let tx_progress = tx.clone();
             let tx_status = tx.clone();
 
-            let result =
-                self.run_with_events(&output_path, proxy, optimizer, tx_progress, tx_status);
+            let from_store = match self.reuse_from_store(&output_path) {
+                Ok(reused) => reused,
+                Err(e) => {
+                    let _ = tx.send(DownloadEvent::Error(e.to_string()));
+                    return Err(e);
+                }
+            };
+            let result = if from_store {
+                let _ = tx.send(DownloadEvent::Status("Reused from content store".to_string()));
+                Ok(())
+            } else {
+                self.run_with_events(&output_path, proxy, optimizer, tx_progress, tx_status)
+            };
 
             match result {
                 Ok(()) => {
@@ -417,6 +588,21 @@ impl DownloadBuilder {
                             return Err(e);
                         }
                     };
+                    let signature = match pending_signature
+                        .as_ref()
+                        .map(|p| p.verify_with_digests(Path::new(&output_path), &checksums.digests()))
+                        .transpose()
+                    {
+                        Ok(info) => info,
+                        Err(e) => {
+                            let _ = tx.send(DownloadEvent::Error(e.to_string()));
+                            return Err(e);
+                        }
+                    };
+                    if let Some(info) = &signature {
+                        let _ = tx.send(DownloadEvent::Status(format!("Good {} signature from {}", info.kind, info.signer)));
+                    }
+                    self.add_to_store(Path::new(&output_path), &checksums);
                     let bytes_downloaded = std::fs::metadata(&output_path)
                         .map(|m| m.len())
                         .unwrap_or(0);
@@ -436,6 +622,9 @@ impl DownloadBuilder {
                         duration,
                         connections_used: self.connections,
                         checksums,
+                        from_store,
+                        signature,
+                        checksum_source: self.checksum_source.clone(),
                     })
                 }
                 Err(e) => {
@@ -460,31 +649,113 @@ impl DownloadBuilder {
 
     // ── Private helpers ──────────────────────────────────────────────────────
 
+    /// Fill in the expected checksum from `integrity` and `verify_from`, or
+    /// discover one when `verify_auto` is set and none was given.
+    fn resolve_checksums(&mut self) -> Result<(), KgetError> {
+        if let Some(sri) = self.integrity.take() {
+            let (algo, hex) = parse_sri(&sri)
+                .ok_or_else(|| KgetError::Other(format!("Invalid integrity string: {sri}")))?;
+            *self.checksums.slot(&algo) = Some(hex);
+        }
+        if let Some(sidecar_url) = self.verify_from.take() {
+            return self.apply_sidecar(&sidecar_url);
+        }
+        if self.verify_auto && !self.checksums.any_set() && self.url.starts_with("http") {
+            self.discover_checksum()?;
+        }
+        Ok(())
+    }
+
     /// Fetch the sidecar file and, if a matching hash is found, update
     /// `self.checksums` so the post-download verification uses it.
+    ///
+    /// A sidecar holding nothing but a hash (`<file>.sha256` often does) is
+    /// accepted as well.
     fn apply_sidecar(&mut self, sidecar_url: &str) -> Result<(), KgetError> {
         let client = self.make_blocking_client()?;
         let text = client
             .get(sidecar_url)
             .send()
-            .map_err(KgetError::from)?
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| KgetError::SidecarError(format!("{sidecar_url}: {e}")))?
             .text()
             .map_err(KgetError::from)?;
 
         let filename = utils::get_filename_from_url_or_default(&self.url, "file");
-        match parse_sidecar(&text, &filename) {
-            Some((ChecksumAlgorithm::Sha256, h)) => self.checksums.sha256 = Some(h),
-            Some((ChecksumAlgorithm::Sha512, h)) => self.checksums.sha512 = Some(h),
-            Some((ChecksumAlgorithm::Sha1, h))   => self.checksums.sha1   = Some(h),
-            Some((ChecksumAlgorithm::Md5, h))     => self.checksums.md5    = Some(h),
-            Some((ChecksumAlgorithm::Blake3, h))  => self.checksums.blake3 = Some(h),
+        let lone_hash = || {
+            let hash = text.trim();
+            let sidecar_name = sidecar_url.split(['?', '#']).next().unwrap_or(sidecar_url);
+            let algo = ChecksumAlgorithm::from_manifest_name(sidecar_name)
+                .filter(|a| a.hex_len() == hash.len())
+                .or_else(|| ChecksumAlgorithm::from_hex_len(hash))?;
+            hash.chars().all(|c| c.is_ascii_hexdigit()).then(|| (algo, hash.to_lowercase()))
+        };
+        match parse_sidecar(&text, &filename).or_else(lone_hash) {
+            Some((algo, h)) => *self.checksums.slot(&algo) = Some(h),
             None => return Err(KgetError::SidecarError(format!(
                 "No entry for '{}' found in sidecar file", filename
             ))),
         }
+        self.checksum_source = Some(sidecar_url.to_string());
         Ok(())
     }
 
+    /// Probe the usual sidecar locations, then the digest headers of the
+    /// file itself. Finding nothing is not an error.
+    fn discover_checksum(&mut self) -> Result<(), KgetError> {
+        for candidate in sidecar_candidates(&self.url) {
+            if self.apply_sidecar(&candidate).is_ok() {
+                return Ok(());
+            }
+        }
+
+        let client = self.make_blocking_client()?;
+        let Ok(resp) = apply_headers(client.head(&self.url), &self.headers).send() else {
+            return Ok(());
+        };
+        // Header digests cover the bytes as sent; skip them if those are
+        // content-encoded and would be decoded before reaching the disk.
+        let encoded = resp
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .is_some_and(|v| v.as_bytes() != b"identity");
+        if !resp.status().is_success() || encoded {
+            return Ok(());
+        }
+        for name in ["Repr-Digest", "Digest"] {
+            let digests = resp
+                .headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(parse_digest_header)
+                .collect::<Vec<_>>();
+            if !digests.is_empty() {
+                for (algo, hex) in digests {
+                    *self.checksums.slot(&algo) = Some(hex);
+                }
+                self.checksum_source = Some(format!("{name} header"));
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch the requested signature. A signed checksum list is verified
+    /// now and its digest becomes an expected checksum (unless one is
+    /// already set for that algorithm), so the content store can use it.
+    fn prepare_signature(&mut self) -> Result<Option<PendingSignature>, KgetError> {
+        let Some(check) = &self.signature else { return Ok(None) };
+        let pending = check.prepare(&self.make_blocking_client()?, &self.url)?;
+        if let Some((algo, hex)) = pending.expected_digest() {
+            let slot = self.checksums.slot(&algo);
+            if slot.is_none() {
+                *slot = Some(hex.to_string());
+            }
+        }
+        Ok(Some(pending))
+    }
+
     /// Run the download, retrying on transient failures per `self.retry`.
     fn run_with_retry(
         &self,
@@ -531,7 +802,7 @@ impl DownloadBuilder {
             return self.download_range(output_path, range_start, range_end);
         }
 
-        if self.connections > 1 {
+        if self.use_parallel(&optimizer) {
             let mut dl = AdvancedDownloader::new(
                 self.url.clone(),
                 output_path.to_string(),
@@ -570,7 +841,7 @@ impl DownloadBuilder {
             return self.download_range(output_path, range_start, range_end);
         }
 
-        if self.connections > 1 {
+        if self.use_parallel(&optimizer) {
             let mut dl = AdvancedDownloader::new(
                 self.url.clone(),
                 output_path.to_string(),
@@ -619,6 +890,19 @@ impl DownloadBuilder {
         }
     }
 
+    /// Whether to use `AdvancedDownloader` rather than the single-stream path.
+    ///
+    /// Local file:// and data: sources are always a single-stream copy, and
+    /// cached URLs go through the single-stream path that serves and
+    /// revalidates cache entries.
+    fn use_parallel(&self, optimizer: &Optimizer) -> bool {
+        self.connections > 1
+            && !local::is_local_url(&self.url)
+            && optimizer
+                .http_cache()
+                .is_none_or(|c| c.lookup(&self.url).is_none())
+    }
+
     /// Raw range download via reqwest, written to `output_path`.
     fn download_range(&self, output_path: &str, start: u64, end: u64) -> Result<(), KgetError> {
         let client = self.make_blocking_client()?;
@@ -641,7 +925,7 @@ impl DownloadBuilder {
         if let Some(parent) = Path::new(output_path).parent() {
             std::fs::create_dir_all(parent)?;
         }
-        std::fs::write(output_path, &bytes)?;
+        store::open_output(Path::new(output_path), OutputMode::Create)?.write_all(&bytes)?;
         Ok(())
     }
 
@@ -652,31 +936,65 @@ impl DownloadBuilder {
             return Ok(ComputedChecksums::default());
         }
 
+        // Every expected digest comes out of one read of the file.
+        let expected = self.checksums.digests();
+        let algorithms: Vec<ChecksumAlgorithm> = expected.iter().map(|(algo, _)| algo.clone()).collect();
+        let digests = compute_checksums(path, &algorithms)?;
+
         let mut computed = ComputedChecksums::default();
+        for ((algo, expected), (_, got)) in expected.into_iter().zip(digests) {
+            if got != expected {
+                return Err(KgetError::ChecksumMismatch {
+                    algorithm: algo.name().to_string(),
+                    expected: expected.to_string(),
+                    got,
+                });
+            }
+            *computed.slot(&algo) = Some(got);
+        }
 
-        macro_rules! check {
-            ($field:ident, $algo:expr) => {
-                if let Some(expected) = &self.checksums.$field {
-                    let got = compute_checksum(path, &$algo)?;
-                    if got != *expected {
-                        return Err(KgetError::ChecksumMismatch {
-                            algorithm: $algo.name().to_string(),
-                            expected: expected.clone(),
-                            got,
-                        });
-                    }
-                    computed.$field = Some(got);
+        Ok(computed)
+    }
+
+    /// Link the file from the content store if any expected cryptographic
+    /// digest is there. A CRC or XXH3 match proves nothing about content.
+    fn reuse_from_store(&self, output_path: &str) -> Result<bool, KgetError> {
+        let Some(store) = &self.store else { return Ok(false) };
+        if self.range.is_some() {
+            return Ok(false);
+        }
+        for (algo, hex) in self.checksums.digests() {
+            if !algo.is_cryptographic() {
+                continue;
+            }
+            if let Some(kind) = store.materialize(&algo, hex, Path::new(output_path))? {
+                if !self.quiet {
+                    println!("Reused {} from content store ({})", output_path, kind);
                 }
-            };
+                return Ok(true);
+            }
         }
+        Ok(false)
+    }
 
-        check!(sha256, ChecksumAlgorithm::Sha256);
-        check!(sha512, ChecksumAlgorithm::Sha512);
-        check!(sha1,   ChecksumAlgorithm::Sha1);
-        check!(md5,    ChecksumAlgorithm::Md5);
-        check!(blake3, ChecksumAlgorithm::Blake3);
-
-        Ok(computed)
+    /// Add a verified download to the content store under every
+    /// cryptographic digest. Failures only warn: the download itself already
+    /// succeeded.
+    fn add_to_store(&self, path: &Path, checksums: &ComputedChecksums) {
+        let Some(store) = &self.store else { return };
+        if self.range.is_some() {
+            return;
+        }
+        for (algo, hex) in checksums.digests() {
+            if !algo.is_cryptographic() {
+                continue;
+            }
+            if let Err(e) = store.insert(path, &algo, hex)
+                && !self.quiet
+            {
+                eprintln!("Warning: could not add to content store: {e}");
+            }
+        }
     }
 
     fn resolve_output(&self) -> String {
@@ -684,31 +1002,19 @@ impl DownloadBuilder {
     }
 
     fn make_proxy(&self) -> ProxyConfig {
-        match &self.proxy_url {
-            None => ProxyConfig::default(),
-            Some(url) => {
-                let proxy_type = if url.starts_with("socks5://") {
-                    ProxyType::Socks5
-                } else if url.starts_with("https://") {
-                    ProxyType::Https
-                } else {
-                    ProxyType::Http
-                };
-                ProxyConfig {
-                    enabled: true,
-                    url: Some(url.clone()),
-                    username: self.proxy_user.clone(),
-                    password: self.proxy_pass.clone(),
-                    proxy_type,
-                }
-            }
-        }
+        proxy_config(&self.proxy_url, &self.proxy_user, &self.proxy_pass)
     }
 
     fn make_optimizer(&self) -> Optimizer {
         let mut cfg = Config::default().optimization;
         cfg.speed_limit = self.speed_limit;
         cfg.max_connections = self.connections;
+        if let Some(enabled) = self.cache {
+            cfg.cache_enabled = enabled;
+        }
+        if let Some(dir) = &self.cache_dir {
+            cfg.cache_dir = dir.clone();
+        }
         Optimizer::from_config(cfg)
     }
 
@@ -933,10 +1239,224 @@ impl BatchBuilder {
     }
 }
 
+// ════════════════════════════════════════════════════════════════════════════
+// MetalinkBuilder
+// ════════════════════════════════════════════════════════════════════════════
+
+/// Result of one file in a Metalink download.
+#[derive(Debug)]
+pub struct MetalinkResult {
+    /// File name as listed in the manifest.
+    pub name: String,
+    /// Mirror the file was fetched from; `None` if it came from the content
+    /// store or failed.
+    pub mirror: Option<String>,
+    /// Success or failure for that file.
+    pub result: Result<DownloadResult, KgetError>,
+}
+
+/// Builder for downloading the files of a Metalink manifest concurrently.
+///
+/// Each file tries its mirrors in priority order and is verified against the
+/// strongest hash the manifest lists.
+///
+/// ```rust,no_run
+/// use kget::{DownloadEvent, MetalinkBuilder};
+///
+/// let (handle, events) = MetalinkBuilder::new("https://example.com/release.meta4")
+///     .output_dir("./downloads/")
+///     .concurrency(2)
+///     .os("linux")
+///     .spawn();
+///
+/// for e in events {
+///     if let DownloadEvent::Progress { percent, .. } = e.event {
+///         println!("{}: {:.1}%", e.file, percent);
+///     }
+/// }
+/// for r in handle.join().unwrap()? {
+///     println!("{}: {}", r.name, if r.result.is_ok() { "OK" } else { "FAILED" });
+/// }
+/// # Ok::<(), kget::KgetError>(())
+/// ```
+pub struct MetalinkBuilder {
+    source: String,
+    output_dir: String,
+    concurrency: usize,
+    connections: usize,
+    speed_limit: Option<u64>,
+    proxy_url: Option<String>,
+    proxy_user: Option<String>,
+    proxy_pass: Option<String>,
+    prefs: MetalinkConfig,
+    quiet: bool,
+    cancel: Arc<AtomicBool>,
+}
+
+impl MetalinkBuilder {
+    /// Start building a download of the manifest at `source`, a local
+    /// `.meta4`/`.metalink` path or an HTTP(S) URL.
+    pub fn new(source: impl Into<String>) -> Self {
+        MetalinkBuilder {
+            source: source.into(),
+            output_dir: ".".to_string(),
+            concurrency: metalink::DEFAULT_CONCURRENT_FILES,
+            connections: Config::default().optimization.max_connections,
+            speed_limit: None,
+            proxy_url: None,
+            proxy_user: None,
+            proxy_pass: None,
+            prefs: MetalinkConfig::default(),
+            quiet: false,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Directory where the files are saved.  Default: current directory.
+    pub fn output_dir(mut self, dir: impl Into<String>) -> Self {
+        self.output_dir = dir.into();
+        self
+    }
+
+    /// Maximum number of files downloaded at once.  Default: 3.
+    pub fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Parallel connections per file.
+    pub fn connections(mut self, n: usize) -> Self {
+        self.connections = n.max(1);
+        self
+    }
+
+    /// Speed limit applied to each file (bytes/s).
+    pub fn speed_limit(mut self, bps: u64) -> Self {
+        self.speed_limit = Some(bps);
+        self
+    }
+
+    /// HTTP proxy URL shared by all downloads.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy_url = Some(url.into());
+        self
+    }
+
+    /// Proxy credentials.
+    pub fn proxy_auth(mut self, user: impl Into<String>, pass: impl Into<String>) -> Self {
+        self.proxy_user = Some(user.into());
+        self.proxy_pass = Some(pass.into());
+        self
+    }
+
+    /// Only download files meant for this OS (e.g. `linux`).
+    pub fn os(mut self, os: impl Into<String>) -> Self {
+        self.prefs.os = Some(os.into());
+        self
+    }
+
+    /// Only download files in this language (e.g. `en`).
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.prefs.language = Some(language.into());
+        self
+    }
+
+    /// Try mirrors in this country first (ISO 3166-1 alpha-2, e.g. `de`).
+    pub fn country(mut self, country: impl Into<String>) -> Self {
+        self.prefs.country = Some(country.into());
+        self
+    }
+
+    /// Suppress console output.
+    pub fn quiet(mut self, q: bool) -> Self {
+        self.quiet = q;
+        self
+    }
+
+    /// Token that cancels the remaining downloads when set to `true`.
+    /// Files not finished by then report [`KgetError::Cancelled`].
+    pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Fetch the manifest and download every matching file, returning one
+    /// result per file in manifest order.
+    ///
+    /// Fails only if the manifest cannot be read or no file matches the
+    /// OS/language filters; per-file failures are in the results.
+    pub fn download_all(self) -> Result<Vec<MetalinkResult>, KgetError> {
+        self.run(None)
+    }
+
+    /// Like [`download_all`](Self::download_all), on a background thread,
+    /// with a [`FileEvent`] stream for every file.
+    pub fn spawn(
+        self,
+    ) -> (
+        thread::JoinHandle<Result<Vec<MetalinkResult>, KgetError>>,
+        mpsc::Receiver<FileEvent>,
+    ) {
+        let (tx, rx) = mpsc::channel::<FileEvent>();
+        let handle = thread::spawn(move || self.run(Some(tx)));
+        (handle, rx)
+    }
+
+    fn run(self, events: Option<mpsc::Sender<FileEvent>>) -> Result<Vec<MetalinkResult>, KgetError> {
+        let proxy = proxy_config(&self.proxy_url, &self.proxy_user, &self.proxy_pass);
+        let xml = metalink::fetch_manifest(&self.source, self.quiet, &proxy)?;
+        let doc = metalink::parse(&xml)?;
+        let files = doc.select(&self.prefs);
+        if files.is_empty() {
+            return Err(KgetError::NotFound(format!(
+                "no file in {} for os={} language={}",
+                self.source,
+                self.prefs.os.as_deref().unwrap_or("any"),
+                self.prefs.language.as_deref().unwrap_or("any")
+            )));
+        }
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        let mut cfg = Config::default().optimization;
+        cfg.speed_limit = self.speed_limit;
+        cfg.max_connections = self.connections;
+        let mut session = metalink::Session::new(&self.output_dir, self.quiet, proxy, Optimizer::from_config(cfg));
+        session.country = self.prefs.country.clone();
+        session.concurrency = self.concurrency;
+        session.cancel = self.cancel;
+        session.events = events;
+        session.source = Some(self.source);
+        Ok(session.run(&files))
+    }
+}
+
 // ════════════════════════════════════════════════════════════════════════════
 // Free-function helpers
 // ════════════════════════════════════════════════════════════════════════════
 
+/// Proxy settings from a builder's proxy URL and credentials.
+fn proxy_config(url: &Option<String>, user: &Option<String>, pass: &Option<String>) -> ProxyConfig {
+    match url {
+        None => ProxyConfig::default(),
+        Some(url) => {
+            let proxy_type = if url.starts_with("socks5://") {
+                ProxyType::Socks5
+            } else if url.starts_with("https://") {
+                ProxyType::Https
+            } else {
+                ProxyType::Http
+            };
+            ProxyConfig {
+                enabled: true,
+                url: Some(url.clone()),
+                username: user.clone(),
+                password: pass.clone(),
+                proxy_type,
+            }
+        }
+    }
+}
+
 fn apply_headers(
     mut req: reqwest::blocking::RequestBuilder,
     headers: &[(String, String)],
@@ -0,0 +1,968 @@
+//! HTTP response cache for downloads.
+//!
+//! Responses are stored under `<cache_dir>/http/` as a compressed body plus a
+//! JSON metadata file, keyed by the SHA-256 of the URL. Freshness and
+//! revalidation follow the parts of RFC 9111 that matter for a private,
+//! single-user cache:
+//!
+//! - `Cache-Control: no-store` (and `Vary: *`) responses are never stored
+//! - the request header values a response's `Vary` names are stored with it,
+//!   and the entry is only served to requests that send the same values
+//!   (RFC 9111 §4.1)
+//! - `max-age` (minus `Age`) or `Expires` make an entry fresh; fresh entries
+//!   are served without contacting the server
+//! - entries that are stale, have no explicit lifetime, or were sent with
+//!   `no-cache` are revalidated with `If-None-Match` / `If-Modified-Since`;
+//!   a `304 Not Modified` serves the cached body
+//! - responses with neither a lifetime nor a validator are not stored
+//!
+//! The total size on disk is capped; the least recently used entries are
+//! evicted after each insert.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use kget::Optimizer;
+//!
+//! if let Some(cache) = Optimizer::new().http_cache() {
+//!     for entry in cache.entries() {
+//!         println!("{}  {} bytes", entry.url, entry.size);
+//!     }
+//! }
+//! ```
+
+use crate::config::OptimizationConfig;
+use crate::store::expand_home;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use reqwest::header::{
+    AGE, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, DATE, ETAG, EXPIRES, HeaderMap,
+    LAST_MODIFIED, PRAGMA, VARY,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default cap on the total size of the cache (1 GiB).
+pub const DEFAULT_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Hit/miss counters, stored next to the entries.
+const STATS_FILE: &str = "stats.json";
+
+/// Compression applied to a stored body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    None,
+    Gzip,
+    Lz4,
+    Brotli,
+}
+
+impl Codec {
+    /// Codec for a `compression_level`, using the same mapping as
+    /// [`Optimizer::compress`](crate::Optimizer::compress).
+    pub fn for_level(level: u8) -> Self {
+        match level {
+            1..=3 => Codec::Gzip,
+            4..=6 => Codec::Lz4,
+            7..=9 => Codec::Brotli,
+            _ => Codec::None,
+        }
+    }
+}
+
+/// Metadata of one cached response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Request URL the response was stored for.
+    pub url: String,
+    /// Size of the (uncompressed) body in bytes.
+    pub size: u64,
+    /// Size of the body file on disk.
+    pub stored_size: u64,
+    /// Hex SHA-256 of the uncompressed body.
+    pub sha256: String,
+    /// How the body file is compressed.
+    pub codec: Codec,
+    /// `ETag` validator, if the server sent one.
+    pub etag: Option<String>,
+    /// `Last-Modified` validator, if the server sent one.
+    pub last_modified: Option<String>,
+    /// `Content-Type` of the response.
+    pub content_type: Option<String>,
+    /// `Content-Disposition` header of the response.
+    pub content_disposition: Option<String>,
+    /// Unix time the entry was stored or last revalidated.
+    pub stored_at: u64,
+    /// Unix time the entry stops being fresh; `None` means always revalidate.
+    pub expires_at: Option<u64>,
+    /// Unix time the entry was last served.
+    pub last_access: u64,
+    /// Number of times the entry was served.
+    pub hits: u64,
+    /// Request headers named by the response's `Vary` (lowercase), with the
+    /// values sent by the request that stored it (`None`: not sent).
+    #[serde(default)]
+    pub vary: Vec<(String, Option<String>)>,
+}
+
+impl CacheEntry {
+    /// Whether the entry can be served without revalidation at `now`.
+    pub fn is_fresh(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|e| now < e)
+    }
+
+    /// Whether a conditional request can revalidate the entry.
+    pub fn has_validator(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+
+    /// Whether the entry may answer a request sending `request_headers`:
+    /// every header named by `Vary` must have the value it had when the
+    /// entry was stored.
+    pub fn matches_request(&self, request_headers: &[(String, String)]) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_header(request_headers, name) == *value)
+    }
+}
+
+/// Cumulative cache effectiveness counters.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Downloads served from a fresh entry without a request.
+    pub hits: u64,
+    /// Downloads served after a `304 Not Modified` revalidation.
+    pub revalidated: u64,
+    /// Cache-enabled downloads that had to fetch the body.
+    pub misses: u64,
+    /// Bytes served from the cache instead of the network.
+    pub bytes_served: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups (hits and revalidations) that avoided a body download.
+    pub fn hit_ratio(&self) -> f64 {
+        let served = self.hits + self.revalidated;
+        let total = served + self.misses;
+        if total == 0 { 0.0 } else { served as f64 / total as f64 }
+    }
+}
+
+/// A cache file that failed verification.
+#[derive(Debug, Clone)]
+pub struct CorruptEntry {
+    /// URL of the entry, if its metadata could be read.
+    pub url: Option<String>,
+    /// The offending file.
+    pub path: PathBuf,
+    /// What is wrong with it.
+    pub problem: String,
+}
+
+/// Outcome of [`HttpCache::verify`].
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Entries whose body decompressed to the recorded size and SHA-256.
+    pub ok: usize,
+    /// Entries and stray files that failed verification.
+    pub corrupted: Vec<CorruptEntry>,
+    /// Corrupted entries that were deleted.
+    pub removed: usize,
+}
+
+/// Outcome of [`HttpCache::prune`], [`HttpCache::evict`] and [`HttpCache::clear`].
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// Entries removed.
+    pub removed: usize,
+    /// Bytes of stored bodies freed.
+    pub bytes_freed: u64,
+    /// Entries left in the cache.
+    pub kept: usize,
+}
+
+/// Caching-relevant parts of a response's headers.
+#[derive(Debug, Clone, Default)]
+struct Policy {
+    expires_at: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Policy {
+    /// Parse `headers`; `None` if the response must not be stored.
+    fn from_headers(headers: &HeaderMap, now: u64) -> Option<Self> {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+        if header(VARY).is_some_and(|v| v.split(',').any(|f| f.trim() == "*")) {
+            return None;
+        }
+
+        let mut no_cache = false;
+        let mut max_age: Option<u64> = None;
+        for value in headers.get_all(CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else { continue };
+            for directive in value.split(',') {
+                let (name, arg) = match directive.split_once('=') {
+                    Some((n, a)) => (n.trim(), Some(a.trim().trim_matches('"'))),
+                    None => (directive.trim(), None),
+                };
+                match name.to_ascii_lowercase().as_str() {
+                    "no-store" => return None,
+                    "no-cache" => no_cache = true,
+                    "max-age" => max_age = arg.and_then(|a| a.parse().ok()),
+                    _ => {}
+                }
+            }
+        }
+        if headers.get(CACHE_CONTROL).is_none()
+            && header(PRAGMA).is_some_and(|p| p.eq_ignore_ascii_case("no-cache"))
+        {
+            no_cache = true;
+        }
+
+        let lifetime = if no_cache {
+            None
+        } else if let Some(max_age) = max_age {
+            let age: u64 = header(AGE).and_then(|a| a.parse().ok()).unwrap_or(0);
+            Some(max_age.saturating_sub(age))
+        } else {
+            header(EXPIRES).map(|e| {
+                // An invalid Expires (e.g. "0") means already expired.
+                let expires = http_date(e).unwrap_or(0);
+                let date = header(DATE).and_then(http_date).unwrap_or(now);
+                expires.saturating_sub(date)
+            })
+        };
+
+        let policy = Policy {
+            expires_at: lifetime.filter(|&l| l > 0).map(|l| now + l),
+            etag: header(ETAG).map(str::to_string),
+            last_modified: header(LAST_MODIFIED).map(str::to_string),
+        };
+        if policy.expires_at.is_none() && policy.etag.is_none() && policy.last_modified.is_none() {
+            return None;
+        }
+        Some(policy)
+    }
+}
+
+/// A size-bounded HTTP cache in a directory.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+    max_size: u64,
+    codec: Codec,
+    level: u8,
+}
+
+impl HttpCache {
+    /// A cache in `dir` holding at most `max_size` bytes, compressing bodies
+    /// at `compression_level` (0 stores them uncompressed).
+    pub fn new(dir: impl Into<PathBuf>, max_size: u64, compression_level: u8) -> Self {
+        Self {
+            dir: dir.into(),
+            max_size,
+            codec: Codec::for_level(compression_level),
+            level: compression_level,
+        }
+    }
+
+    /// The cache described by `config` (whether or not `cache_enabled` is set).
+    pub fn from_config(config: &OptimizationConfig) -> Self {
+        let root = expand_home(if config.cache_dir.is_empty() {
+            "~/.cache/kget"
+        } else {
+            &config.cache_dir
+        });
+        let level = if config.compression { config.compression_level } else { 0 };
+        Self::new(root.join("http"), config.cache_max_size, level)
+    }
+
+    /// Directory holding the entries.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Maximum total size of the stored bodies in bytes.
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// The entry stored for `url`, if any.
+    pub fn lookup(&self, url: &str) -> Option<CacheEntry> {
+        let (meta, body) = self.paths(url);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(meta).ok()?).ok()?;
+        (entry.url == url && body.is_file()).then_some(entry)
+    }
+
+    /// All readable entries, in no particular order.
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        dir.flatten()
+            .filter(|e| is_meta_file(&e.path()))
+            .filter_map(|e| serde_json::from_slice(&fs::read(e.path()).ok()?).ok())
+            .collect()
+    }
+
+    /// Cumulative hit/miss counters.
+    pub fn stats(&self) -> CacheStats {
+        fs::read(self.dir.join(STATS_FILE))
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .unwrap_or_default()
+    }
+
+    /// Update the counters (best effort; concurrent downloads may race).
+    pub(crate) fn record(&self, update: impl FnOnce(&mut CacheStats)) {
+        let mut stats = self.stats();
+        update(&mut stats);
+        if fs::create_dir_all(&self.dir).is_ok()
+            && let Ok(json) = serde_json::to_vec_pretty(&stats)
+        {
+            let tmp = self.dir.join(format!("{}.tmp{}", STATS_FILE, std::process::id()));
+            if fs::write(&tmp, json).is_ok() {
+                let _ = fs::rename(tmp, self.dir.join(STATS_FILE));
+            }
+        }
+    }
+
+    /// Decompress and hash every entry, reporting entries whose body is
+    /// missing, unreadable or does not match its recorded size and SHA-256,
+    /// plus stray files. With `remove`, corrupted files are deleted.
+    pub fn verify(&self, remove: bool) -> VerifyReport {
+        let mut report = VerifyReport::default();
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return report;
+        };
+        let mut bodies = std::collections::HashSet::new();
+        let mut stray = Vec::new();
+
+        for file in dir.flatten() {
+            let path = file.path();
+            let name = file.file_name().to_string_lossy().into_owned();
+            if name.starts_with(STATS_FILE) && !name.contains(".tmp") {
+                continue;
+            }
+            if name.ends_with(".body") {
+                bodies.insert(path);
+                continue;
+            }
+            if !is_meta_file(&path) {
+                stray.push(path);
+                continue;
+            }
+
+            let entry = match fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|b| serde_json::from_slice::<CacheEntry>(&b).map_err(|e| e.to_string()))
+            {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report.corrupted.push(CorruptEntry {
+                        url: None,
+                        path: path.clone(),
+                        problem: format!("unreadable metadata: {}", e),
+                    });
+                    continue;
+                }
+            };
+            let (meta, body) = self.paths(&entry.url);
+            if meta != path {
+                report.corrupted.push(CorruptEntry {
+                    url: Some(entry.url),
+                    path,
+                    problem: "metadata stored under the wrong key".to_string(),
+                });
+                continue;
+            }
+            bodies.remove(&body);
+            match self.check_body(&entry) {
+                Ok(()) => report.ok += 1,
+                Err(problem) => report.corrupted.push(CorruptEntry {
+                    url: Some(entry.url),
+                    path,
+                    problem,
+                }),
+            }
+        }
+
+        for path in bodies.into_iter().chain(stray) {
+            report.corrupted.push(CorruptEntry {
+                url: None,
+                path,
+                problem: "orphaned file".to_string(),
+            });
+        }
+
+        if remove {
+            for c in &report.corrupted {
+                match &c.url {
+                    Some(url) => {
+                        self.remove(url);
+                    }
+                    None => {
+                        let _ = fs::remove_file(&c.path);
+                    }
+                }
+                report.removed += 1;
+            }
+        }
+        report
+    }
+
+    /// Remove entries not served for longer than `older_than`, then evict
+    /// least recently used entries until the cache fits in `max_size`.
+    pub fn prune(&self, older_than: Option<std::time::Duration>, max_size: Option<u64>) -> PruneReport {
+        let mut report = PruneReport::default();
+        if let Some(age) = older_than {
+            let cutoff = now().saturating_sub(age.as_secs());
+            for entry in self.entries() {
+                if entry.last_access < cutoff && self.remove(&entry.url) {
+                    report.removed += 1;
+                    report.bytes_freed += entry.stored_size;
+                }
+            }
+        }
+        let evicted = self.evict_to(max_size.unwrap_or(self.max_size));
+        report.removed += evicted.removed;
+        report.bytes_freed += evicted.bytes_freed;
+        report.kept = evicted.kept;
+        report
+    }
+
+    /// Remove every entry. The hit/miss counters are kept.
+    pub fn clear(&self) -> PruneReport {
+        self.evict_to(0)
+    }
+
+    /// Open the decompressed body of `entry`.
+    pub fn open(&self, entry: &CacheEntry) -> io::Result<Box<dyn Read + Send>> {
+        let file = BufReader::new(File::open(self.paths(&entry.url).1)?);
+        Ok(match entry.codec {
+            Codec::None => Box::new(file),
+            Codec::Gzip => Box::new(GzDecoder::new(file)),
+            Codec::Lz4 => Box::new(lz4::Decoder::new(file)?),
+            Codec::Brotli => Box::new(brotli::Decompressor::new(file, 64 * 1024)),
+        })
+    }
+
+    /// Headers for a conditional request revalidating `entry`.
+    pub fn conditional_headers(entry: &CacheEntry) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = &entry.etag {
+            headers.push(("If-None-Match".to_string(), etag.clone()));
+        }
+        if let Some(lm) = &entry.last_modified {
+            headers.push(("If-Modified-Since".to_string(), lm.clone()));
+        }
+        headers
+    }
+
+    /// Record that `entry` is about to be served, updating the LRU order.
+    pub fn touch(&self, entry: &mut CacheEntry) {
+        entry.last_access = now();
+        entry.hits += 1;
+        let _ = self.write_meta(entry);
+    }
+
+    /// Refresh `entry` from the headers of a `304 Not Modified` response.
+    pub fn revalidated(&self, entry: &mut CacheEntry, headers: &HeaderMap) {
+        let now = now();
+        let policy = Policy::from_headers(headers, now).unwrap_or_default();
+        entry.stored_at = now;
+        entry.expires_at = policy.expires_at;
+        if policy.etag.is_some() {
+            entry.etag = policy.etag;
+        }
+        if policy.last_modified.is_some() {
+            entry.last_modified = policy.last_modified;
+        }
+        let _ = self.write_meta(entry);
+    }
+
+    /// Start storing a `200 OK` response for `url`, requested with
+    /// `request_headers` (needed to honor the response's `Vary`).
+    ///
+    /// Returns `None` if the response is not cacheable or is known to be
+    /// larger than the cache.
+    pub fn writer(
+        &self,
+        url: &str,
+        headers: &HeaderMap,
+        content_length: Option<u64>,
+        request_headers: &[(String, String)],
+    ) -> Option<CacheWriter> {
+        if content_length.is_some_and(|l| l > self.max_size) {
+            return None;
+        }
+        let now = now();
+        let policy = Policy::from_headers(headers, now)?;
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let content_type = header(CONTENT_TYPE);
+        let codec = if content_type.as_deref().is_some_and(already_compressed) {
+            Codec::None
+        } else {
+            self.codec
+        };
+        let mut vary: Vec<(String, Option<String>)> = Vec::new();
+        for value in headers.get_all(VARY) {
+            let Ok(value) = value.to_str() else { continue };
+            for name in value.split(',').map(|n| n.trim().to_ascii_lowercase()) {
+                if !name.is_empty() && !vary.iter().any(|(n, _)| *n == name) {
+                    let sent = request_header(request_headers, &name);
+                    vary.push((name, sent));
+                }
+            }
+        }
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            size: 0,
+            stored_size: 0,
+            sha256: String::new(),
+            codec,
+            etag: policy.etag,
+            last_modified: policy.last_modified,
+            content_type,
+            content_disposition: header(CONTENT_DISPOSITION),
+            stored_at: now,
+            expires_at: policy.expires_at,
+            last_access: now,
+            hits: 0,
+            vary,
+        };
+        self.start(entry).ok()
+    }
+
+    /// Store `data` for `url` with no HTTP metadata.
+    ///
+    /// The entry has no lifetime or validators, so downloads never serve it;
+    /// it is only returned by [`read`](Self::read).
+    pub fn put(&self, url: &str, data: &[u8]) -> io::Result<()> {
+        let now = now();
+        let mut writer = self.start(CacheEntry {
+            url: url.to_string(),
+            size: 0,
+            stored_size: 0,
+            sha256: String::new(),
+            codec: self.codec,
+            etag: None,
+            last_modified: None,
+            content_type: None,
+            content_disposition: None,
+            stored_at: now,
+            expires_at: None,
+            last_access: now,
+            hits: 0,
+            vary: Vec::new(),
+        })?;
+        writer.write_all(data)?;
+        writer.commit()
+    }
+
+    /// Read the whole body stored for `url`, regardless of freshness.
+    pub fn read(&self, url: &str) -> io::Result<Option<Vec<u8>>> {
+        let Some(mut entry) = self.lookup(url) else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(entry.size as usize);
+        self.open(&entry)?.read_to_end(&mut data)?;
+        self.touch(&mut entry);
+        Ok(Some(data))
+    }
+
+    /// Delete the entry for `url`. Returns `true` if there was one.
+    pub fn remove(&self, url: &str) -> bool {
+        let (meta, body) = self.paths(url);
+        let existed = meta.exists();
+        let _ = fs::remove_file(body);
+        let _ = fs::remove_file(meta);
+        existed
+    }
+
+    /// Evict least recently used entries until the cache fits in `max_size`.
+    pub fn evict(&self) -> PruneReport {
+        self.evict_to(self.max_size)
+    }
+
+    fn evict_to(&self, limit: u64) -> PruneReport {
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|e| e.stored_size).sum();
+        entries.sort_by_key(|e| e.last_access);
+        let mut report = PruneReport::default();
+        for entry in entries {
+            if total > limit && self.remove(&entry.url) {
+                total = total.saturating_sub(entry.stored_size);
+                report.removed += 1;
+                report.bytes_freed += entry.stored_size;
+            } else {
+                report.kept += 1;
+            }
+        }
+        report
+    }
+
+    /// Decompress and hash one entry's body.
+    fn check_body(&self, entry: &CacheEntry) -> Result<(), String> {
+        let mut reader = self.open(entry).map_err(|e| format!("body unreadable: {}", e))?;
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf).map_err(|e| format!("decompression failed: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            size += n as u64;
+        }
+        if size != entry.size {
+            return Err(format!("size mismatch: expected {} bytes, got {}", entry.size, size));
+        }
+        let got = hex::encode(hasher.finalize());
+        if got != entry.sha256 {
+            return Err(format!("SHA-256 mismatch: expected {}, got {}", entry.sha256, got));
+        }
+        Ok(())
+    }
+
+    fn start(&self, entry: CacheEntry) -> io::Result<CacheWriter> {
+        fs::create_dir_all(&self.dir)?;
+        let (meta, body) = self.paths(&entry.url);
+        let tmp = body.with_extension(format!("tmp{}", std::process::id()));
+        let file = File::create(&tmp)?;
+        let encoder = match entry.codec {
+            Codec::None => Encoder::None(file),
+            Codec::Gzip => Encoder::Gzip(GzEncoder::new(file, flate2::Compression::new(self.level as u32))),
+            Codec::Lz4 => Encoder::Lz4(lz4::EncoderBuilder::new().level(self.level as u32).build(file)?),
+            Codec::Brotli => Encoder::Brotli(Box::new(brotli::CompressorWriter::new(
+                file,
+                64 * 1024,
+                self.level as u32,
+                22,
+            ))),
+        };
+        Ok(CacheWriter {
+            cache: self.clone(),
+            entry,
+            encoder: Some(encoder),
+            hasher: Sha256::new(),
+            tmp,
+            body,
+            meta,
+        })
+    }
+
+    fn write_meta(&self, entry: &CacheEntry) -> io::Result<()> {
+        let meta = self.paths(&entry.url).0;
+        let tmp = meta.with_extension(format!("json.tmp{}", std::process::id()));
+        fs::write(&tmp, serde_json::to_vec_pretty(entry)?)?;
+        fs::rename(tmp, meta)
+    }
+
+    /// `(metadata, body)` paths for `url`.
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = hex::encode(Sha256::digest(url.as_bytes()));
+        (self.dir.join(format!("{}.json", key)), self.dir.join(format!("{}.body", key)))
+    }
+}
+
+/// An in-progress cache entry. Dropping it without [`commit`](Self::commit)
+/// discards the partial body.
+pub struct CacheWriter {
+    cache: HttpCache,
+    entry: CacheEntry,
+    encoder: Option<Encoder>,
+    hasher: Sha256,
+    tmp: PathBuf,
+    body: PathBuf,
+    meta: PathBuf,
+}
+
+impl Write for CacheWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.entry.size + buf.len() as u64 > self.cache.max_size {
+            return Err(io::Error::other("response exceeds the cache size limit"));
+        }
+        let encoder = self.encoder.as_mut().ok_or_else(|| io::Error::other("cache entry already committed"))?;
+        encoder.write_all(buf)?;
+        self.hasher.update(buf);
+        self.entry.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CacheWriter {
+    /// Finish the body, publish the entry and evict old entries if needed.
+    pub fn commit(mut self) -> io::Result<()> {
+        let encoder = self.encoder.take().ok_or_else(|| io::Error::other("cache entry already committed"))?;
+        encoder.finish()?;
+        self.entry.sha256 = hex::encode(std::mem::take(&mut self.hasher).finalize());
+        self.entry.stored_size = fs::metadata(&self.tmp)?.len();
+        // Replace the body first: a reader that sees the new metadata must
+        // also see the new body.
+        let _ = fs::remove_file(&self.meta);
+        fs::rename(&self.tmp, &self.body)?;
+        self.cache.write_meta(&self.entry)?;
+        self.cache.evict();
+        Ok(())
+    }
+}
+
+impl Drop for CacheWriter {
+    fn drop(&mut self) {
+        if self.encoder.is_some() {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// Reader adapter that copies everything read into a [`CacheWriter`].
+///
+/// Caching errors (full disk, size cap) silently stop caching; they never
+/// fail the download itself.
+pub(crate) struct TeeReader<R> {
+    inner: R,
+    writer: Option<CacheWriter>,
+}
+
+impl<R: Read> TeeReader<R> {
+    pub(crate) fn new(inner: R, writer: Option<CacheWriter>) -> Self {
+        Self { inner, writer }
+    }
+
+    /// Publish the cache entry once the whole body has been read.
+    pub(crate) fn commit(self) {
+        if let Some(writer) = self.writer {
+            let _ = writer.commit();
+        }
+    }
+}
+
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(writer) = self.writer.as_mut()
+            && writer.write_all(&buf[..n]).is_err()
+        {
+            self.writer = None;
+        }
+        Ok(n)
+    }
+}
+
+enum Encoder {
+    None(File),
+    Gzip(GzEncoder<File>),
+    Lz4(lz4::Encoder<File>),
+    Brotli(Box<brotli::CompressorWriter<File>>),
+}
+
+impl Encoder {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Encoder::None(w) => w.write_all(buf),
+            Encoder::Gzip(w) => w.write_all(buf),
+            Encoder::Lz4(w) => w.write_all(buf),
+            Encoder::Brotli(w) => w.write_all(buf),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        let file = match self {
+            Encoder::None(f) => f,
+            Encoder::Gzip(w) => w.finish()?,
+            Encoder::Lz4(w) => {
+                let (f, result) = w.finish();
+                result?;
+                f
+            }
+            Encoder::Brotli(mut w) => {
+                w.flush()?;
+                w.into_inner()
+            }
+        };
+        file.sync_all()
+    }
+}
+
+/// Whether `path` is an entry's metadata file (not the counters).
+/// Value of the request header `name`; repeated headers are joined with `, `.
+fn request_header(headers: &[(String, String)], name: &str) -> Option<String> {
+    let values: Vec<&str> = headers
+        .iter()
+        .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.trim())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+fn is_meta_file(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "json")
+        && path.file_name().is_some_and(|n| n != STATS_FILE)
+}
+
+/// Content types whose bodies are already compressed and would not shrink.
+fn already_compressed(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    essence.starts_with("video/")
+        || essence.starts_with("audio/")
+        || matches!(
+            essence.as_str(),
+            "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/x-xz"
+                | "application/x-bzip2"
+                | "application/zstd"
+                | "application/x-7z-compressed"
+                | "application/x-rar-compressed"
+                | "application/vnd.rar"
+                | "image/jpeg"
+                | "image/png"
+                | "image/webp"
+        )
+}
+
+fn http_date(value: &str) -> Option<u64> {
+    httpdate::parse_http_date(value)
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.append(*k, HeaderValue::from_static(v));
+        }
+        map
+    }
+
+    #[test]
+    fn policy_freshness_and_storability() {
+        let now = 1_000_000;
+        let p = Policy::from_headers(&headers(&[("cache-control", "public, max-age=60"), ("age", "10")]), now).unwrap();
+        assert_eq!(p.expires_at, Some(now + 50));
+
+        assert!(Policy::from_headers(&headers(&[("cache-control", "no-store, max-age=60")]), now).is_none());
+        assert!(Policy::from_headers(&headers(&[]), now).is_none());
+        assert!(Policy::from_headers(&headers(&[("etag", "\"x\""), ("vary", "*")]), now).is_none());
+
+        let p = Policy::from_headers(&headers(&[("cache-control", "no-cache, max-age=60"), ("etag", "\"v1\"")]), now).unwrap();
+        assert_eq!(p.expires_at, None);
+        assert_eq!(p.etag.as_deref(), Some("\"v1\""));
+
+        let p = Policy::from_headers(
+            &headers(&[("date", "Sun, 06 Nov 1994 08:49:37 GMT"), ("expires", "Sun, 06 Nov 1994 09:49:37 GMT")]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(p.expires_at, Some(now + 3600));
+    }
+
+    #[test]
+    fn vary_headers_must_match_the_storing_request() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = HttpCache::new(dir.path(), DEFAULT_MAX_SIZE, 0);
+        let response = headers(&[("cache-control", "max-age=60"), ("vary", "Accept-Language, Accept"), ("vary", "accept")]);
+        let sent = [("accept-language".to_string(), "de".to_string())];
+        let mut writer = cache.writer("https://example.com/v", &response, None, &sent).unwrap();
+        writer.write_all(b"hallo").unwrap();
+        writer.commit().unwrap();
+
+        let entry = cache.lookup("https://example.com/v").unwrap();
+        assert_eq!(
+            entry.vary,
+            [("accept-language".to_string(), Some("de".to_string())), ("accept".to_string(), None)]
+        );
+        assert!(entry.matches_request(&[("Accept-Language".to_string(), " de ".to_string())]));
+        assert!(!entry.matches_request(&[("Accept-Language".to_string(), "en".to_string())]));
+        assert!(!entry.matches_request(&[]));
+        assert!(!entry.matches_request(&[
+            ("Accept-Language".to_string(), "de".to_string()),
+            ("Accept".to_string(), "text/html".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn roundtrips_every_codec_and_evicts_lru() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let data: Vec<u8> = (0..50_000u32).map(|i| (i % 7) as u8).collect();
+        for level in [0, 2, 5, 8] {
+            let cache = HttpCache::new(dir.path().join(level.to_string()), DEFAULT_MAX_SIZE, level);
+            cache.put("https://example.com/a", &data).unwrap();
+            assert_eq!(cache.read("https://example.com/a").unwrap().unwrap(), data);
+            let entry = cache.lookup("https://example.com/a").unwrap();
+            assert_eq!(entry.sha256, hex::encode(Sha256::digest(&data)));
+            if level > 0 {
+                assert!(entry.stored_size < entry.size);
+            }
+        }
+
+        let cache = HttpCache::new(dir.path().join("lru"), 250, 0);
+        cache.put("u1", &[1; 100]).unwrap();
+        cache.put("u2", &[2; 100]).unwrap();
+        for (url, last_access) in [("u1", u64::MAX), ("u2", 1)] {
+            let mut entry = cache.lookup(url).unwrap();
+            entry.last_access = last_access;
+            cache.write_meta(&entry).unwrap();
+        }
+        cache.put("u3", &[3; 100]).unwrap();
+        assert!(cache.lookup("u1").is_some());
+        assert!(cache.lookup("u2").is_none());
+        assert!(cache.lookup("u3").is_some());
+        // Larger than the whole cache: never stored.
+        assert!(cache.put("u4", &[4; 300]).is_err());
+        assert!(cache.lookup("u4").is_none());
+    }
+
+    #[test]
+    fn verify_flags_tampered_entries_and_prune_shrinks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = HttpCache::new(dir.path(), DEFAULT_MAX_SIZE, 0);
+        cache.put("good", b"intact body").unwrap();
+        cache.put("bad", b"original body").unwrap();
+        fs::write(cache.paths("bad").1, b"tampered body").unwrap();
+        fs::write(dir.path().join("deadbeef.body"), b"stray").unwrap();
+
+        let report = cache.verify(false);
+        assert_eq!(report.ok, 1);
+        assert_eq!(report.corrupted.len(), 2);
+        assert!(report.corrupted.iter().any(|c| c.url.as_deref() == Some("bad")));
+
+        let report = cache.verify(true);
+        assert_eq!(report.removed, 2);
+        assert!(cache.lookup("bad").is_none());
+        assert!(cache.verify(false).corrupted.is_empty());
+
+        cache.record(|s| s.misses += 1);
+        cache.put("other", &[0; 100]).unwrap();
+        let pruned = cache.prune(None, Some(5));
+        assert_eq!(pruned.removed, 2);
+        assert_eq!(pruned.kept, 0);
+        assert_eq!(cache.stats().misses, 1);
+    }
+}
@@ -1,8 +1,15 @@
 //! Multi-algorithm file checksum computation and sidecar-file parsing.
 //!
-//! Supports SHA-256, SHA-512, SHA-1, MD5, and BLAKE3.
+//! Supports SHA-256, SHA-384, SHA-512, SHA-1, MD5, SHA3-256, SHA3-512,
+//! BLAKE2b and BLAKE3, plus the non-cryptographic CRC32, CRC32C and XXH3 (64-bit) for
+//! quick integrity checks. [`compute_checksums`] computes any combination of
+//! them in a single read.
 //! The [`parse_sidecar`] function understands both the GNU `<hash>  <file>`
-//! format and the BSD `ALG (file) = hash` format.
+//! format (optionally with an `alg:` prefix on the hash) and the BSD
+//! `ALG (file) = hash` format, and [`verify_manifest`] checks a whole
+//! directory against a `SHA256SUMS`-style manifest. [`parse_digest_header`]
+//! reads the digests servers send in `Digest` and `Repr-Digest` headers, and
+//! [`parse_sri`] the `sha384-<base64>` strings of Subresource Integrity.
 
 use crate::error::KgetError;
 use sha2::Digest as _;
@@ -16,97 +23,316 @@ use std::path::Path;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChecksumAlgorithm {
     Sha256,
+    Sha384,
     Sha512,
     Sha1,
     Md5,
+    Sha3_256,
+    Sha3_512,
+    /// BLAKE2b-512, as printed by `b2sum`.
+    Blake2b,
     Blake3,
+    /// CRC-32 (IEEE), as printed by `crc32` and used by zip/gzip.
+    Crc32,
+    /// CRC-32C (Castagnoli), as used by iSCSI, ext4 and cloud object stores.
+    Crc32c,
+    /// XXH3 64-bit, as printed by `xxhsum -H3`.
+    Xxh3,
 }
 
 impl ChecksumAlgorithm {
+    /// Every supported algorithm.
+    pub const ALL: [ChecksumAlgorithm; 12] = [
+        ChecksumAlgorithm::Sha256,
+        ChecksumAlgorithm::Sha384,
+        ChecksumAlgorithm::Sha512,
+        ChecksumAlgorithm::Sha1,
+        ChecksumAlgorithm::Md5,
+        ChecksumAlgorithm::Sha3_256,
+        ChecksumAlgorithm::Sha3_512,
+        ChecksumAlgorithm::Blake2b,
+        ChecksumAlgorithm::Blake3,
+        ChecksumAlgorithm::Crc32,
+        ChecksumAlgorithm::Crc32c,
+        ChecksumAlgorithm::Xxh3,
+    ];
+
     /// Guess the algorithm from the hex-string length.
     ///
+    /// - 8 chars → CRC32  (CRC32C has the same length; CRC32 is assumed)
+    /// - 16 chars → XXH3
     /// - 32 chars → MD5
     /// - 40 chars → SHA-1
-    /// - 64 chars → SHA-256  (BLAKE3 also produces 64 chars; SHA-256 is assumed)
-    /// - 128 chars → SHA-512
+    /// - 64 chars → SHA-256  (SHA3-256 and BLAKE3 also produce 64 chars; SHA-256 is assumed)
+    /// - 96 chars → SHA-384
+    /// - 128 chars → SHA-512  (SHA3-512 and BLAKE2b also produce 128 chars; SHA-512 is assumed)
+    ///
+    /// Use a tagged line (`SHA3-256 (file) = …` or `sha3-256:…`) for the
+    /// ambiguous algorithms.
     pub fn from_hex_len(hex: &str) -> Option<Self> {
         match hex.trim().len() {
+            8   => Some(ChecksumAlgorithm::Crc32),
+            16  => Some(ChecksumAlgorithm::Xxh3),
             32  => Some(ChecksumAlgorithm::Md5),
             40  => Some(ChecksumAlgorithm::Sha1),
             64  => Some(ChecksumAlgorithm::Sha256),
+            96  => Some(ChecksumAlgorithm::Sha384),
             128 => Some(ChecksumAlgorithm::Sha512),
             _   => None,
         }
     }
 
+    /// Parse an algorithm name such as `sha256`, `SHA-256`, `SHA3_256` or
+    /// `BLAKE3`. Metalink `<hash type>` values use the same names.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "sha256" | "sha-256"     => Some(ChecksumAlgorithm::Sha256),
+            "sha384" | "sha-384"     => Some(ChecksumAlgorithm::Sha384),
+            "sha512" | "sha-512"     => Some(ChecksumAlgorithm::Sha512),
+            "sha1"   | "sha-1"       => Some(ChecksumAlgorithm::Sha1),
+            "md5"                    => Some(ChecksumAlgorithm::Md5),
+            "sha3-256"               => Some(ChecksumAlgorithm::Sha3_256),
+            "sha3-512"               => Some(ChecksumAlgorithm::Sha3_512),
+            "blake2b" | "blake2b-512" | "b2" => Some(ChecksumAlgorithm::Blake2b),
+            "blake3"                 => Some(ChecksumAlgorithm::Blake3),
+            "crc32"                  => Some(ChecksumAlgorithm::Crc32),
+            "crc32c"                 => Some(ChecksumAlgorithm::Crc32c),
+            "xxh3" | "xxh3-64" | "xxhash3" => Some(ChecksumAlgorithm::Xxh3),
+            _                        => None,
+        }
+    }
+
+    /// Guess the algorithm of a manifest from its file name: `SHA256SUMS`,
+    /// `B2SUMS`, `sha512sums.txt`, `MD5SUMS`, or a `<file>.sha256` sidecar.
+    pub fn from_manifest_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let stem = name.strip_suffix(".txt").unwrap_or(&name);
+        if let Some(alg) = stem.strip_suffix("sums").or_else(|| stem.strip_suffix("sum")) {
+            return match alg.trim_end_matches(['-', '_']) {
+                "b2" => Some(ChecksumAlgorithm::Blake2b),
+                alg => Self::from_name(alg),
+            };
+        }
+        Self::from_name(Path::new(stem).extension()?.to_str()?)
+    }
+
+    /// Length of the hex digest.
+    pub fn hex_len(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::Crc32 | ChecksumAlgorithm::Crc32c => 8,
+            ChecksumAlgorithm::Xxh3     => 16,
+            ChecksumAlgorithm::Md5      => 32,
+            ChecksumAlgorithm::Sha1     => 40,
+            ChecksumAlgorithm::Sha256 | ChecksumAlgorithm::Sha3_256 | ChecksumAlgorithm::Blake3 => 64,
+            ChecksumAlgorithm::Sha384   => 96,
+            ChecksumAlgorithm::Sha512 | ChecksumAlgorithm::Sha3_512 | ChecksumAlgorithm::Blake2b => 128,
+        }
+    }
+
     /// Human-readable algorithm name.
     pub fn name(&self) -> &'static str {
         match self {
-            ChecksumAlgorithm::Sha256 => "sha256",
-            ChecksumAlgorithm::Sha512 => "sha512",
-            ChecksumAlgorithm::Sha1   => "sha1",
-            ChecksumAlgorithm::Md5    => "md5",
-            ChecksumAlgorithm::Blake3 => "blake3",
+            ChecksumAlgorithm::Sha256   => "sha256",
+            ChecksumAlgorithm::Sha384   => "sha384",
+            ChecksumAlgorithm::Sha512   => "sha512",
+            ChecksumAlgorithm::Sha1     => "sha1",
+            ChecksumAlgorithm::Md5      => "md5",
+            ChecksumAlgorithm::Sha3_256 => "sha3-256",
+            ChecksumAlgorithm::Sha3_512 => "sha3-512",
+            ChecksumAlgorithm::Blake2b  => "blake2b",
+            ChecksumAlgorithm::Blake3   => "blake3",
+            ChecksumAlgorithm::Crc32    => "crc32",
+            ChecksumAlgorithm::Crc32c   => "crc32c",
+            ChecksumAlgorithm::Xxh3     => "xxh3",
         }
     }
+
+    /// Whether the algorithm resists deliberate tampering. CRCs and XXH3
+    /// only detect accidental corruption.
+    pub fn is_cryptographic(&self) -> bool {
+        !matches!(self, ChecksumAlgorithm::Crc32 | ChecksumAlgorithm::Crc32c | ChecksumAlgorithm::Xxh3)
+    }
 }
 
 // ── Computation ───────────────────────────────────────────────────────────────
 
 const BUF_SIZE: usize = 1024 * 1024; // 1 MiB
 
+/// Files at least this large are memory-mapped and hashed on several threads.
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024; // 16 MiB
+
 /// Compute the checksum of a file using the specified algorithm.
 ///
 /// Returns the lowercase hex-encoded digest.
 pub fn compute_checksum(path: &Path, algorithm: &ChecksumAlgorithm) -> Result<String, KgetError> {
+    let mut digests = compute_checksums(path, std::slice::from_ref(algorithm))?;
+    Ok(digests.remove(0).1)
+}
+
+/// Hash a file in consecutive `piece_length`-byte pieces, the way Metalink
+/// `<pieces>` and BitTorrent describe them; the last piece may be shorter.
+pub fn compute_piece_hashes(
+    path: &Path,
+    piece_length: u64,
+    algorithm: &ChecksumAlgorithm,
+) -> Result<Vec<String>, KgetError> {
+    let piece_length = usize::try_from(piece_length)
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| KgetError::Other(format!("Invalid piece length: {}", piece_length)))?;
     let mut file = File::open(path)?;
-    let mut buf = vec![0u8; BUF_SIZE];
+    let mut buf = vec![0u8; piece_length];
+    let mut pieces = Vec::new();
+    loop {
+        let mut filled = 0;
+        while filled < piece_length {
+            let n = file.read(&mut buf[filled..])?;
+            if n == 0 { break; }
+            filled += n;
+        }
+        if filled == 0 { break; }
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&buf[..filled]);
+        pieces.push(hasher.finalize());
+        if filled < piece_length { break; }
+    }
+    Ok(pieces)
+}
 
-    match algorithm {
-        ChecksumAlgorithm::Sha256 => {
-            let mut h = sha2::Sha256::new();
-            loop {
-                let n = file.read(&mut buf)?;
-                if n == 0 { break; }
-                sha2::Digest::update(&mut h, &buf[..n]);
-            }
-            Ok(hex::encode(sha2::Digest::finalize(h)))
+/// Compute several checksums of a file in a single pass.
+///
+/// Returns one `(algorithm, hex digest)` pair per distinct algorithm, in the
+/// order they were requested. Small files are streamed once through every
+/// hasher. Large files are memory-mapped and each algorithm hashes the mapping
+/// on its own thread, with BLAKE3 further split across the rayon pool, so the
+/// whole set costs little more than the slowest algorithm alone.
+pub fn compute_checksums(
+    path: &Path,
+    algorithms: &[ChecksumAlgorithm],
+) -> Result<Vec<(ChecksumAlgorithm, String)>, KgetError> {
+    let mut wanted: Vec<ChecksumAlgorithm> = Vec::with_capacity(algorithms.len());
+    for algo in algorithms {
+        if !wanted.contains(algo) {
+            wanted.push(algo.clone());
         }
-        ChecksumAlgorithm::Sha512 => {
-            let mut h = sha2::Sha512::new();
-            loop {
-                let n = file.read(&mut buf)?;
-                if n == 0 { break; }
-                sha2::Digest::update(&mut h, &buf[..n]);
+    }
+
+    let mut file = File::open(path)?;
+    let mut hashers: Vec<Hasher> = wanted.iter().map(Hasher::new).collect();
+    let metadata = file.metadata()?;
+
+    let mapped = if metadata.is_file() && metadata.len() >= MMAP_THRESHOLD {
+        // SAFETY: the mapping is only read, and only for the duration of this
+        // call. If another process truncates the file meanwhile we may fault,
+        // the same trade-off `blake3`'s own mmap support makes.
+        unsafe { memmap2::Mmap::map(&file) }.ok()
+    } else {
+        None
+    };
+
+    match mapped {
+        Some(map) => {
+            let data: &[u8] = &map;
+            if let [hasher] = hashers.as_mut_slice() {
+                hasher.update_large(data);
+            } else {
+                std::thread::scope(|scope| {
+                    for hasher in hashers.iter_mut() {
+                        scope.spawn(move || hasher.update_large(data));
+                    }
+                });
             }
-            Ok(hex::encode(sha2::Digest::finalize(h)))
         }
-        ChecksumAlgorithm::Sha1 => {
-            let mut h = sha1::Sha1::new();
+        None => {
+            let mut buf = vec![0u8; BUF_SIZE];
             loop {
                 let n = file.read(&mut buf)?;
                 if n == 0 { break; }
-                sha1::Digest::update(&mut h, &buf[..n]);
+                for hasher in hashers.iter_mut() {
+                    hasher.update(&buf[..n]);
+                }
             }
-            Ok(hex::encode(sha1::Digest::finalize(h)))
         }
-        ChecksumAlgorithm::Md5 => {
-            let mut h = md5::Md5::new();
-            loop {
-                let n = file.read(&mut buf)?;
-                if n == 0 { break; }
-                md5::Digest::update(&mut h, &buf[..n]);
-            }
-            Ok(hex::encode(md5::Digest::finalize(h)))
+    }
+
+    Ok(wanted.into_iter().zip(hashers.into_iter().map(Hasher::finalize)).collect())
+}
+
+/// Incremental state of one algorithm.
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Sha384(sha2::Sha384),
+    Sha512(sha2::Sha512),
+    Sha1(sha1::Sha1),
+    Md5(md5::Md5),
+    Sha3_256(sha3::Sha3_256),
+    Sha3_512(sha3::Sha3_512),
+    Blake2b(blake2::Blake2b512),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+    Crc32c(u32),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl Hasher {
+    fn new(algorithm: &ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Sha256   => Hasher::Sha256(sha2::Sha256::new()),
+            ChecksumAlgorithm::Sha384   => Hasher::Sha384(sha2::Sha384::new()),
+            ChecksumAlgorithm::Sha512   => Hasher::Sha512(sha2::Sha512::new()),
+            ChecksumAlgorithm::Sha1     => Hasher::Sha1(sha1::Sha1::new()),
+            ChecksumAlgorithm::Md5      => Hasher::Md5(md5::Md5::new()),
+            ChecksumAlgorithm::Sha3_256 => Hasher::Sha3_256(sha3::Sha3_256::new()),
+            ChecksumAlgorithm::Sha3_512 => Hasher::Sha3_512(sha3::Sha3_512::new()),
+            ChecksumAlgorithm::Blake2b  => Hasher::Blake2b(blake2::Blake2b512::new()),
+            ChecksumAlgorithm::Blake3   => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            ChecksumAlgorithm::Crc32    => Hasher::Crc32(crc32fast::Hasher::new()),
+            ChecksumAlgorithm::Crc32c   => Hasher::Crc32c(0),
+            ChecksumAlgorithm::Xxh3     => Hasher::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
         }
-        ChecksumAlgorithm::Blake3 => {
-            let mut h = blake3::Hasher::new();
-            loop {
-                let n = file.read(&mut buf)?;
-                if n == 0 { break; }
-                h.update(&buf[..n]);
-            }
-            Ok(h.finalize().to_hex().to_string())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h)   => sha2::Digest::update(h, data),
+            Hasher::Sha384(h)   => sha2::Digest::update(h, data),
+            Hasher::Sha512(h)   => sha2::Digest::update(h, data),
+            Hasher::Sha1(h)     => sha1::Digest::update(h, data),
+            Hasher::Md5(h)      => md5::Digest::update(h, data),
+            Hasher::Sha3_256(h) => sha3::Digest::update(h, data),
+            Hasher::Sha3_512(h) => sha3::Digest::update(h, data),
+            Hasher::Blake2b(h)  => blake2::Digest::update(h, data),
+            Hasher::Blake3(h)   => { h.update(data); }
+            Hasher::Crc32(h)    => h.update(data),
+            Hasher::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+            Hasher::Xxh3(h)     => h.update(data),
+        }
+    }
+
+    /// Like [`update`](Self::update), but lets BLAKE3 use every core.
+    fn update_large(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Blake3(h) => { h.update_rayon(data); }
+            other => other.update(data),
+        }
+    }
+
+    /// Lowercase hex digest; CRCs and XXH3 are printed big-endian, the way
+    /// `crc32` and `xxhsum` show them.
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(h)   => hex::encode(sha2::Digest::finalize(h)),
+            Hasher::Sha384(h)   => hex::encode(sha2::Digest::finalize(h)),
+            Hasher::Sha512(h)   => hex::encode(sha2::Digest::finalize(h)),
+            Hasher::Sha1(h)     => hex::encode(sha1::Digest::finalize(h)),
+            Hasher::Md5(h)      => hex::encode(md5::Digest::finalize(h)),
+            Hasher::Sha3_256(h) => hex::encode(sha3::Digest::finalize(h)),
+            Hasher::Sha3_512(h) => hex::encode(sha3::Digest::finalize(h)),
+            Hasher::Blake2b(h)  => hex::encode(blake2::Digest::finalize(h)),
+            Hasher::Blake3(h)   => h.finalize().to_hex().to_string(),
+            Hasher::Crc32(h)    => format!("{:08x}", h.finalize()),
+            Hasher::Crc32c(crc) => format!("{:08x}", crc),
+            Hasher::Xxh3(h)     => format!("{:016x}", h.digest()),
         }
     }
 }
@@ -123,49 +349,332 @@ pub fn compute_checksum(path: &Path, algorithm: &ChecksumAlgorithm) -> Result<St
 ///
 /// `filename` is matched as a suffix so both bare names and paths work.
 pub fn parse_sidecar(content: &str, filename: &str) -> Option<(ChecksumAlgorithm, String)> {
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') { continue; }
-
-        // ── GNU format: "<hash>  <file>" or "<hash> *<file>" ─────────────────
-        // Split on double-space first, then single-space-asterisk.
-        let gnu = line.split_once("  ")
-            .or_else(|| line.split_once(" *"));
-
-        if let Some((hash_part, file_part)) = gnu {
-            let hash = hash_part.trim();
-            let file = file_part.trim().trim_start_matches('*').trim();
-            if matches_filename(file, filename) {
-                if let Some(algo) = ChecksumAlgorithm::from_hex_len(hash) {
-                    return Some((algo, hash.to_lowercase()));
-                }
+    content
+        .lines()
+        .filter_map(|line| parse_line(line, None))
+        .find(|entry| matches_filename(&entry.path, filename))
+        .map(|entry| (entry.algorithm, entry.hash))
+}
+
+/// One line of a checksum manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// File name as written in the manifest, without a leading `./` or `*`.
+    pub path: String,
+    pub algorithm: ChecksumAlgorithm,
+    /// Lowercase hex digest.
+    pub hash: String,
+}
+
+/// Parse every entry of a `SHA256SUMS`-style manifest, in GNU or BSD format.
+///
+/// `default` names the algorithm of untagged lines whose hash has the right
+/// length for it — pass [`ChecksumAlgorithm::from_manifest_name`] so that a
+/// `B2SUMS` file is read as BLAKE2b rather than SHA-512. Other untagged lines
+/// fall back to [`ChecksumAlgorithm::from_hex_len`].
+pub fn parse_manifest(content: &str, default: Option<&ChecksumAlgorithm>) -> Vec<ManifestEntry> {
+    content.lines().filter_map(|line| parse_line(line, default)).collect()
+}
+
+fn parse_line(line: &str, default: Option<&ChecksumAlgorithm>) -> Option<ManifestEntry> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') { return None; }
+
+    // ── GNU format: "<hash>  <file>" or "<hash> *<file>" ─────────────────────
+    // Split on double-space first, then single-space-asterisk.
+    let gnu = line.split_once("  ")
+        .or_else(|| line.split_once(" *"));
+
+    if let Some((hash_part, file_part)) = gnu {
+        let file = file_part.trim().trim_start_matches('*').trim();
+        // An `alg:` prefix (`sha3-256:…`) names the algorithm outright.
+        let tagged = hash_part.trim().split_once(':').and_then(|(alg, hash)| {
+            ChecksumAlgorithm::from_name(alg).map(|algo| (algo, hash.trim()))
+        });
+        let hash = hash_part.trim();
+        let untagged = || match default {
+            Some(algo) if algo.hex_len() == hash.len() => Some((algo.clone(), hash)),
+            _ => ChecksumAlgorithm::from_hex_len(hash).map(|a| (a, hash)),
+        };
+        if let Some((algorithm, hash)) = tagged.or_else(untagged)
+            && hash.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Some(ManifestEntry { path: normalize_entry(file), algorithm, hash: hash.to_lowercase() });
+        }
+    }
+
+    // ── BSD format: "SHA256 (<file>) = <hash>" ────────────────────────────────
+    let (prefix, hash) = line.split_once(" = ")?;
+    let (lp, rp) = (prefix.find('(')?, prefix.rfind(')')?);
+    let algorithm = ChecksumAlgorithm::from_name(&prefix[..lp])?;
+    Some(ManifestEntry {
+        path: normalize_entry(prefix.get(lp + 1..rp)?.trim()),
+        algorithm,
+        hash: hash.trim().to_lowercase(),
+    })
+}
+
+fn normalize_entry(file: &str) -> String {
+    file.trim_start_matches("./").to_string()
+}
+
+// ── Manifest verification ─────────────────────────────────────────────────────
+
+/// A manifest entry whose file did not match.
+#[derive(Debug, Clone)]
+pub struct ManifestMismatch {
+    pub path: String,
+    pub algorithm: ChecksumAlgorithm,
+    pub expected: String,
+    pub got: String,
+}
+
+/// Outcome of [`verify_manifest`]. Paths are relative to the verified
+/// directory and sorted.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestReport {
+    /// Files whose digest matched.
+    pub ok: Vec<String>,
+    pub mismatched: Vec<ManifestMismatch>,
+    /// Files listed in the manifest but absent from the directory.
+    pub missing: Vec<String>,
+    /// Files in the directory that the manifest does not list.
+    pub extra: Vec<String>,
+    /// Entries that could not be checked, with the reason.
+    pub errors: Vec<(String, String)>,
+}
+
+impl ManifestReport {
+    /// `true` when every listed file is present and matches. Extra files do
+    /// not count as a failure.
+    pub fn passed(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty() && self.errors.is_empty()
+    }
+}
+
+enum EntryOutcome {
+    Ok,
+    Mismatch(String),
+    Missing,
+    Error(String),
+}
+
+/// Verify every file listed in a `SHA256SUMS`-style manifest against `dir`.
+///
+/// The manifest may mix GNU and BSD lines and algorithms; untagged lines use
+/// the algorithm suggested by the manifest's name (`B2SUMS` → BLAKE2b). Files
+/// are hashed in parallel on the rayon pool. Entries with absolute paths or
+/// `..` components are reported as errors instead of being read. The manifest
+/// itself and its detached signatures are never reported as extra.
+pub fn verify_manifest(manifest: &Path, dir: &Path) -> Result<ManifestReport, KgetError> {
+    use rayon::prelude::*;
+
+    let content = std::fs::read_to_string(manifest)?;
+    let manifest_name = manifest.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let default = ChecksumAlgorithm::from_manifest_name(manifest_name);
+    let entries = parse_manifest(&content, default.as_ref());
+    if entries.is_empty() {
+        return Err(KgetError::Other(format!("No checksum entries found in {}", manifest.display())));
+    }
+
+    let outcomes: Vec<EntryOutcome> = entries
+        .par_iter()
+        .map(|entry| {
+            let relative = Path::new(&entry.path);
+            if relative.is_absolute()
+                || relative.components().any(|c| !matches!(c, std::path::Component::Normal(_)))
+            {
+                return EntryOutcome::Error("path escapes the verified directory".into());
+            }
+            let path = dir.join(relative);
+            if !path.is_file() {
+                return EntryOutcome::Missing;
+            }
+            match compute_checksum(&path, &entry.algorithm) {
+                Ok(got) if got == entry.hash => EntryOutcome::Ok,
+                Ok(got) => EntryOutcome::Mismatch(got),
+                Err(e) => EntryOutcome::Error(e.to_string()),
             }
+        })
+        .collect();
+
+    let mut report = ManifestReport::default();
+    for (entry, outcome) in entries.iter().zip(outcomes) {
+        match outcome {
+            EntryOutcome::Ok => report.ok.push(entry.path.clone()),
+            EntryOutcome::Mismatch(got) => report.mismatched.push(ManifestMismatch {
+                path: entry.path.clone(),
+                algorithm: entry.algorithm.clone(),
+                expected: entry.hash.clone(),
+                got,
+            }),
+            EntryOutcome::Missing => report.missing.push(entry.path.clone()),
+            EntryOutcome::Error(e) => report.errors.push((entry.path.clone(), e)),
+        }
+    }
+
+    let listed: std::collections::HashSet<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    // The manifest and `SHA256SUMS.asc`-style signatures next to it.
+    let mut ignored = std::collections::HashSet::new();
+    if let Ok(manifest_abs) = manifest.canonicalize() {
+        for ext in crate::signature::SIGNATURE_EXTENSIONS.iter().chain(&[".gpg"]) {
+            let mut signature = manifest_abs.clone().into_os_string();
+            signature.push(ext);
+            ignored.insert(std::path::PathBuf::from(signature));
         }
+        ignored.insert(manifest_abs);
+    }
+    let mut files = Vec::new();
+    list_files(dir, dir, &mut files)?;
+    report.extra = files
+        .into_iter()
+        .filter(|(relative, path)| {
+            !listed.contains(relative.as_str())
+                && !path.canonicalize().is_ok_and(|abs| ignored.contains(&abs))
+        })
+        .map(|(relative, _)| relative)
+        .collect();
 
-        // ── BSD format: "SHA256 (<file>) = <hash>" ────────────────────────────
-        if let Some(eq_pos) = line.find(" = ") {
-            let hash = line[eq_pos + 3..].trim().to_lowercase();
-            let prefix = &line[..eq_pos];
-            if let (Some(lp), Some(rp)) = (prefix.find('('), prefix.rfind(')')) {
-                let file = prefix[lp + 1..rp].trim();
-                if matches_filename(file, filename) {
-                    let algo_str = prefix[..lp].trim().to_uppercase();
-                    let algo = match algo_str.as_str() {
-                        "SHA256" => Some(ChecksumAlgorithm::Sha256),
-                        "SHA512" => Some(ChecksumAlgorithm::Sha512),
-                        "SHA1"   => Some(ChecksumAlgorithm::Sha1),
-                        "MD5"    => Some(ChecksumAlgorithm::Md5),
-                        "BLAKE3" => Some(ChecksumAlgorithm::Blake3),
-                        _        => None,
-                    };
-                    if let Some(algo) = algo {
-                        return Some((algo, hash));
-                    }
+    report.ok.sort();
+    report.mismatched.sort_by(|a, b| a.path.cmp(&b.path));
+    report.missing.sort();
+    report.extra.sort();
+    report.errors.sort();
+    Ok(report)
+}
+
+/// Recursively collect regular files under `dir` as `(relative path, path)`.
+/// Relative paths use `/` so they compare equal to manifest entries.
+/// Symlinked directories are not followed.
+fn list_files(root: &Path, dir: &Path, out: &mut Vec<(String, std::path::PathBuf)>) -> Result<(), KgetError> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(root, &path, out)?;
+        } else if path.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.push((relative, path));
+        }
+    }
+    Ok(())
+}
+
+/// Per-file sidecar extensions tried by [`sidecar_candidates`].
+pub const SIDECAR_EXTENSIONS: &[&str] = &[".sha256", ".sha512"];
+
+/// Directory-wide checksum lists tried by [`sidecar_candidates`].
+pub const SIDECAR_MANIFESTS: &[&str] = &["SHA256SUMS", "CHECKSUMS", "sha256sum.txt"];
+
+/// Sidecar URLs that may hold the checksum of `file_url`, most specific first.
+pub fn sidecar_candidates(file_url: &str) -> Vec<String> {
+    let base = file_url.split(['?', '#']).next().unwrap_or(file_url);
+    let mut urls: Vec<String> = SIDECAR_EXTENSIONS.iter().map(|ext| format!("{base}{ext}")).collect();
+    if let Some((dir, _)) = base.rsplit_once('/') {
+        urls.extend(SIDECAR_MANIFESTS.iter().map(|name| format!("{dir}/{name}")));
+    }
+    urls
+}
+
+/// Parse a `Digest` (RFC 3230) or `Repr-Digest`/`Content-Digest` (RFC 9530)
+/// header value into `(algorithm, hex digest)` pairs.
+///
+/// Both `sha-256=<base64>` and the RFC 9530 byte-sequence form
+/// `sha-256=:<base64>:` are accepted. Unknown algorithms and digests of the
+/// wrong length are skipped.
+pub fn parse_digest_header(value: &str) -> Vec<(ChecksumAlgorithm, String)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let (name, digest) = item.split_once('=')?;
+            let name = name.trim().to_ascii_lowercase();
+            // RFC 3230 calls SHA-1 plain `SHA`; `id-sha-*` digests the
+            // unencoded representation, which is what ends up on disk.
+            let algorithm = match name.strip_prefix("id-").unwrap_or(&name) {
+                "sha" => ChecksumAlgorithm::Sha1,
+                other => ChecksumAlgorithm::from_name(other)?,
+            };
+            let digest = digest.split(';').next()?.trim();
+            let digest = digest.strip_prefix(':').and_then(|d| d.strip_suffix(':')).unwrap_or(digest);
+            let bytes = crate::local::base64_decode(digest.as_bytes())?;
+            (bytes.len() * 2 == algorithm.hex_len()).then(|| (algorithm, hex::encode(bytes)))
+        })
+        .collect()
+}
+
+/// Digests a response's headers promise for the file.
+///
+/// `Repr-Digest` (RFC 9530) and `Digest` (RFC 3230) describe the whole
+/// representation. `Content-Digest` covers only the bytes of this response,
+/// so it is used only when `full_body` is set — a `200` saved as received.
+pub fn response_digests(headers: &reqwest::header::HeaderMap, full_body: bool) -> Vec<(ChecksumAlgorithm, String)> {
+    let names: &[&str] = if full_body {
+        &["Repr-Digest", "Content-Digest", "Digest"]
+    } else {
+        &["Repr-Digest", "Digest"]
+    };
+    let mut digests: Vec<(ChecksumAlgorithm, String)> = Vec::new();
+    for name in names {
+        for value in headers.get_all(*name).iter().filter_map(|v| v.to_str().ok()) {
+            for (algo, hex) in parse_digest_header(value) {
+                if !digests.iter().any(|(a, _)| *a == algo) {
+                    digests.push((algo, hex));
                 }
             }
         }
     }
-    None
+    digests
+}
+
+/// Parse a Subresource Integrity string such as `sha384-<base64>` and return
+/// its strongest digest.
+///
+/// Several space-separated hashes may be given, as in HTML `integrity=`
+/// attributes; per the SRI spec only the strongest algorithm counts.
+/// `?`-options are ignored. Returns `None` if no valid SHA-256, SHA-384 or
+/// SHA-512 hash is present.
+pub fn parse_sri(integrity: &str) -> Option<(ChecksumAlgorithm, String)> {
+    const STRENGTH: [ChecksumAlgorithm; 3] =
+        [ChecksumAlgorithm::Sha512, ChecksumAlgorithm::Sha384, ChecksumAlgorithm::Sha256];
+    let hashes: Vec<(ChecksumAlgorithm, String)> = integrity
+        .split_whitespace()
+        .filter_map(|token| {
+            let (name, digest) = token.split_once('-')?;
+            let algorithm = STRENGTH.into_iter().find(|a| a.name() == name.to_ascii_lowercase())?;
+            let digest = digest.split('?').next()?;
+            let bytes = crate::local::base64_decode(digest.as_bytes())?;
+            (bytes.len() * 2 == algorithm.hex_len()).then(|| (algorithm, hex::encode(bytes)))
+        })
+        .collect();
+    STRENGTH
+        .into_iter()
+        .find_map(|algo| hashes.iter().find(|(a, _)| *a == algo).cloned())
+}
+
+/// Check a file against expected digests, computing them in one pass.
+///
+/// Fails with [`KgetError::ChecksumMismatch`] on the first digest that
+/// differs.
+pub fn verify_digests(path: &Path, expected: &[(ChecksumAlgorithm, String)]) -> Result<(), KgetError> {
+    let algorithms: Vec<ChecksumAlgorithm> = expected.iter().map(|(a, _)| a.clone()).collect();
+    let computed = compute_checksums(path, &algorithms)?;
+    for (algo, want) in expected {
+        let got = computed.iter().find(|(a, _)| a == algo).map(|(_, h)| h.as_str()).unwrap_or_default();
+        if !want.eq_ignore_ascii_case(got) {
+            return Err(KgetError::ChecksumMismatch {
+                algorithm: algo.name().to_string(),
+                expected: want.to_lowercase(),
+                got: got.to_string(),
+            });
+        }
+    }
+    Ok(())
 }
 
 /// Match file names flexibly — either exact or suffix match.
@@ -208,12 +717,190 @@ mod tests {
         assert_eq!(algo, ChecksumAlgorithm::Md5);
     }
 
+    #[test]
+    fn multi_digest_matches_single_digests() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small.bin");
+        let large = dir.path().join("large.bin");
+        std::fs::write(&small, b"hello world").unwrap();
+        let data: Vec<u8> = (0..MMAP_THRESHOLD as usize + 12345).map(|i| (i * 31 % 251) as u8).collect();
+        std::fs::write(&large, &data).unwrap();
+
+        let algos = [
+            ChecksumAlgorithm::Blake3,
+            ChecksumAlgorithm::Sha256,
+            ChecksumAlgorithm::Md5,
+            ChecksumAlgorithm::Sha256,
+        ];
+        for path in [&small, &large] {
+            let digests = compute_checksums(path, &algos).unwrap();
+            assert_eq!(digests.len(), 3);
+            assert_eq!(digests[0].0, ChecksumAlgorithm::Blake3);
+            for (algo, hex) in &digests {
+                assert_eq!(hex, &compute_checksum(path, algo).unwrap());
+            }
+        }
+        assert_eq!(
+            compute_checksum(&large, &ChecksumAlgorithm::Blake3).unwrap(),
+            blake3::hash(&data).to_hex().to_string()
+        );
+        assert_eq!(
+            compute_checksum(&small, &ChecksumAlgorithm::Sha256).unwrap(),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+
+    #[test]
+    fn algo_from_name() {
+        assert_eq!(ChecksumAlgorithm::from_name("SHA-256"),  Some(ChecksumAlgorithm::Sha256));
+        assert_eq!(ChecksumAlgorithm::from_name("blake3"),   Some(ChecksumAlgorithm::Blake3));
+        assert_eq!(ChecksumAlgorithm::from_name("SHA3_256"), Some(ChecksumAlgorithm::Sha3_256));
+        assert_eq!(ChecksumAlgorithm::from_name("sha3256"),  None);
+        assert_eq!(ChecksumAlgorithm::from_name("crc64"),    None);
+        for algo in ChecksumAlgorithm::ALL {
+            assert_eq!(ChecksumAlgorithm::from_name(algo.name()), Some(algo));
+        }
+    }
+
+    #[test]
+    fn known_digests_of_new_algorithms() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("check.txt");
+        std::fs::write(&path, b"123456789").unwrap();
+        let digests = compute_checksums(&path, &ChecksumAlgorithm::ALL).unwrap();
+        let get = |algo: ChecksumAlgorithm| digests.iter().find(|(a, _)| *a == algo).unwrap().1.clone();
+
+        // CRC check values from the CRC catalogue.
+        assert_eq!(get(ChecksumAlgorithm::Crc32),  "cbf43926");
+        assert_eq!(get(ChecksumAlgorithm::Crc32c), "e3069283");
+        assert_eq!(get(ChecksumAlgorithm::Xxh3),   format!("{:016x}", xxhash_rust::xxh3::xxh3_64(b"123456789")));
+
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            compute_checksum(&path, &ChecksumAlgorithm::Sha3_256).unwrap(),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            compute_checksum(&path, &ChecksumAlgorithm::Sha384).unwrap(),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+        );
+    }
+
+    #[test]
+    fn parse_tagged_sidecar_lines() {
+        let sha384 = "a".repeat(96);
+        let sha3 = "b".repeat(64);
+        let sidecar = format!(
+            "SHA384 (app.tar.gz) = {sha384}\n\
+             sha3-256:{sha3}  tool.bin\n\
+             SHA3-512 (big.iso) = {}\n",
+            "c".repeat(128)
+        );
+        assert_eq!(parse_sidecar(&sidecar, "app.tar.gz"), Some((ChecksumAlgorithm::Sha384, sha384)));
+        assert_eq!(parse_sidecar(&sidecar, "tool.bin"), Some((ChecksumAlgorithm::Sha3_256, sha3)));
+        assert_eq!(parse_sidecar(&sidecar, "big.iso").unwrap().0, ChecksumAlgorithm::Sha3_512);
+    }
+
     #[test]
     fn algo_from_hex_len() {
         assert_eq!(ChecksumAlgorithm::from_hex_len(&"a".repeat(32)),  Some(ChecksumAlgorithm::Md5));
         assert_eq!(ChecksumAlgorithm::from_hex_len(&"a".repeat(40)),  Some(ChecksumAlgorithm::Sha1));
         assert_eq!(ChecksumAlgorithm::from_hex_len(&"a".repeat(64)),  Some(ChecksumAlgorithm::Sha256));
         assert_eq!(ChecksumAlgorithm::from_hex_len(&"a".repeat(128)), Some(ChecksumAlgorithm::Sha512));
+        assert_eq!(ChecksumAlgorithm::from_hex_len(&"a".repeat(96)),  Some(ChecksumAlgorithm::Sha384));
         assert_eq!(ChecksumAlgorithm::from_hex_len(&"a".repeat(10)),  None);
     }
+
+    #[test]
+    fn manifest_name_hints_algorithm() {
+        assert_eq!(ChecksumAlgorithm::from_manifest_name("B2SUMS"),         Some(ChecksumAlgorithm::Blake2b));
+        assert_eq!(ChecksumAlgorithm::from_manifest_name("SHA512SUMS"),     Some(ChecksumAlgorithm::Sha512));
+        assert_eq!(ChecksumAlgorithm::from_manifest_name("sha256sum.txt"),  Some(ChecksumAlgorithm::Sha256));
+        assert_eq!(ChecksumAlgorithm::from_manifest_name("app.iso.sha256"), Some(ChecksumAlgorithm::Sha256));
+        assert_eq!(ChecksumAlgorithm::from_manifest_name("CHECKSUMS"),      None);
+    }
+
+    #[test]
+    fn verify_manifest_reports_every_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("good.txt"), b"abc").unwrap();
+        std::fs::write(dir.path().join("sub/bad.txt"), b"abd").unwrap();
+        std::fs::write(dir.path().join("extra.txt"), b"?").unwrap();
+        std::fs::write(dir.path().join("B2SUMS.asc"), b"sig").unwrap();
+
+        let b2 = |data: &[u8]| hex::encode(blake2::Blake2b512::digest(data));
+        let manifest = dir.path().join("B2SUMS");
+        std::fs::write(&manifest, format!(
+            "{}  good.txt\n{} *./sub/bad.txt\n{}  gone.txt\n{}  ../outside.txt\n",
+            b2(b"abc"), b2(b"abc"), b2(b"abc"), b2(b"abc"),
+        )).unwrap();
+
+        let report = verify_manifest(&manifest, dir.path()).unwrap();
+        assert_eq!(report.ok, ["good.txt"]);
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].path, "sub/bad.txt");
+        assert_eq!(report.mismatched[0].algorithm, ChecksumAlgorithm::Blake2b);
+        assert_eq!(report.mismatched[0].got, b2(b"abd"));
+        assert_eq!(report.missing, ["gone.txt"]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.extra, ["extra.txt"]);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn sidecar_candidates_for_url() {
+        assert_eq!(
+            sidecar_candidates("https://host/pub/app.iso?token=1"),
+            [
+                "https://host/pub/app.iso.sha256",
+                "https://host/pub/app.iso.sha512",
+                "https://host/pub/SHA256SUMS",
+                "https://host/pub/CHECKSUMS",
+                "https://host/pub/sha256sum.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_digest_headers() {
+        // sha-256 of "hello world" and md5 of "" in base64.
+        let sha256 = "uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
+        let rfc9530 = format!("sha-512=:{}:, sha-256=:{sha256}:", "A".repeat(8));
+        assert_eq!(
+            parse_digest_header(&rfc9530),
+            [(ChecksumAlgorithm::Sha256, "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9".to_string())]
+        );
+        assert_eq!(
+            parse_digest_header("MD5=1B2M2Y8AsgTpgAmY7PhCfg==, UNIXsum=30637"),
+            [(ChecksumAlgorithm::Md5, "d41d8cd98f00b204e9800998ecf8427e".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_sri_picks_strongest() {
+        // sha-256 and sha-512 of "hello world".
+        let sha256 = "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
+        let sha512 = "sha512-MJ7MSJwS1utMxA9QyQLytNDtd+5RGnx6m808qG1M2G+YndNbxf9JlnDaNCVbRbDP2DDoH2Bdz33FVC6TrpzXbw==";
+        assert_eq!(
+            parse_sri(sha256),
+            Some((ChecksumAlgorithm::Sha256, "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9".to_string()))
+        );
+        let (algo, hex) = parse_sri(&format!("{sha256} {sha512}?ct=text/plain")).unwrap();
+        assert_eq!(algo, ChecksumAlgorithm::Sha512);
+        assert!(hex.starts_with("309ecc489c12d6eb4cc40f50c902f2b4d0ed77ee511a7c7a9bcd3ca86d4cd86f"));
+        assert_eq!(parse_sri("md5-XUFAKrxLKna5cZ2REBfFkg=="), None);
+        assert_eq!(parse_sri("sha256-tooshort"), None);
+    }
+
+    #[test]
+    fn verify_digests_reports_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f.txt");
+        std::fs::write(&path, b"hello world").unwrap();
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9".to_string();
+        assert!(verify_digests(&path, &[(ChecksumAlgorithm::Sha256, sha256.to_uppercase())]).is_ok());
+        let err = verify_digests(&path, &[(ChecksumAlgorithm::Md5, "0".repeat(32))]).unwrap_err();
+        assert!(matches!(err, KgetError::ChecksumMismatch { ref algorithm, .. } if algorithm == "md5"));
+    }
 }
@@ -99,10 +99,28 @@ pub struct OptimizationConfig {
     pub cache_enabled: bool,
     /// Directory for cached files (default: ~/.cache/kget)
     pub cache_dir: String,
+    /// Maximum total size of the HTTP cache in bytes; least recently used
+    /// entries are evicted beyond it
+    #[serde(default = "default_cache_max_size")]
+    pub cache_max_size: u64,
     /// Speed limit in bytes per second (None = unlimited)
     pub speed_limit: Option<u64>,
     /// Maximum parallel connections per download (1-32)
     pub max_connections: usize,
+    /// Reuse and populate the content-addressed store of verified downloads
+    #[serde(default)]
+    pub store_enabled: bool,
+    /// Directory of the content-addressed store (default: ~/.local/share/kget/store)
+    #[serde(default = "default_store_dir")]
+    pub store_dir: String,
+}
+
+fn default_cache_max_size() -> u64 {
+    crate::cache::DEFAULT_MAX_SIZE
+}
+
+fn default_store_dir() -> String {
+    crate::store::DEFAULT_STORE_DIR.to_string()
 }
 
 // Function to provide the default value for max_peer_connections
@@ -159,6 +177,35 @@ pub struct FtpConfig {
     pub passive_mode: bool,
     /// Default FTP port (21)
     pub default_port: u16,
+    /// Control connections fetching segments of one file in parallel (None = 1)
+    #[serde(default)]
+    pub connections: Option<usize>,
+    /// Upgrade `ftp://` connections with `AUTH TLS` before logging in (`ftps://` always uses TLS)
+    #[serde(default)]
+    pub explicit_tls: bool,
+    /// Verify the server certificate and host name of FTPS connections
+    #[serde(default = "default_ftp_verify_certificates")]
+    pub verify_certificates: bool,
+    /// Extra CA certificate (PEM) trusted for FTPS, e.g. an internal CA
+    #[serde(default)]
+    pub ca_cert: Option<String>,
+}
+
+fn default_ftp_verify_certificates() -> bool {
+    true
+}
+
+impl Default for FtpConfig {
+    fn default() -> Self {
+        Self {
+            passive_mode: true,
+            default_port: 21,
+            connections: None,
+            explicit_tls: false,
+            verify_certificates: default_ftp_verify_certificates(),
+            ca_cert: None,
+        }
+    }
 }
 
 /// Configuration for SFTP (SSH File Transfer Protocol) downloads.
@@ -168,6 +215,74 @@ pub struct SftpConfig {
     pub default_port: u16,
     /// Path to SSH private key file for authentication
     pub key_path: Option<String>,
+    /// SSH sessions reading segments of one file in parallel (None = 1)
+    #[serde(default)]
+    pub connections: Option<usize>,
+}
+
+impl Default for SftpConfig {
+    fn default() -> Self {
+        Self {
+            default_port: 22,
+            key_path: None,
+            connections: None,
+        }
+    }
+}
+
+fn default_s3_presign_expiry() -> u64 {
+    3600
+}
+
+/// Configuration for S3-compatible object storage (`s3://` URLs).
+///
+/// Unset fields fall back to the standard AWS environment variables
+/// (`AWS_ACCESS_KEY_ID`, `AWS_REGION`, `AWS_ENDPOINT_URL`, …) and then to
+/// `~/.aws/credentials`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    /// Region used for request signing (None = `AWS_REGION` or `us-east-1`)
+    pub region: Option<String>,
+    /// Custom endpoint for S3-compatible services, e.g. `http://localhost:9000` for MinIO
+    pub endpoint: Option<String>,
+    /// Access key ID (None = environment or `~/.aws/credentials`)
+    pub access_key_id: Option<String>,
+    /// Secret access key (None = environment or `~/.aws/credentials`)
+    pub secret_access_key: Option<String>,
+    /// Force path-style addressing (`endpoint/bucket/key`); always used with a custom endpoint
+    #[serde(default)]
+    pub path_style: bool,
+    /// Lifetime of presigned GET URLs, in seconds
+    #[serde(default = "default_s3_presign_expiry")]
+    pub presign_expiry_secs: u64,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            region: None,
+            endpoint: None,
+            access_key_id: None,
+            secret_access_key: None,
+            path_style: false,
+            presign_expiry_secs: default_s3_presign_expiry(),
+        }
+    }
+}
+
+/// Preferences for choosing files and mirrors from Metalink manifests.
+///
+/// Files that declare no `<os>` or `<language>` always match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetalinkConfig {
+    /// Only download files meant for this OS (e.g. `linux`, `windows`)
+    pub os: Option<String>,
+    /// Only download files in this language (e.g. `en`, `pt-BR`)
+    pub language: Option<String>,
+    /// Try mirrors in this country first (ISO 3166-1 alpha-2, e.g. `de`)
+    pub country: Option<String>,
+    /// Files of one manifest downloaded at the same time (default 3)
+    pub concurrent_files: Option<usize>,
 }
 
 /// Main configuration structure containing all KGet settings.
@@ -206,6 +321,12 @@ pub struct Config {
     pub ftp: FtpConfig,
     /// SFTP protocol configuration
     pub sftp: SftpConfig,
+    /// S3-compatible object storage configuration
+    #[serde(default)]
+    pub s3: S3Config,
+    /// Metalink file and mirror selection
+    #[serde(default)]
+    pub metalink: MetalinkConfig,
 }
 
 impl Config {
@@ -279,8 +400,11 @@ impl Default for Config {
                 compression_level: 6,
                 cache_enabled: true,
                 cache_dir: "~/.cache/kget".to_string(),
+                cache_max_size: default_cache_max_size(),
                 speed_limit: None,
                 max_connections: 4,
+                store_enabled: false,
+                store_dir: default_store_dir(),
             },
             torrent: TorrentConfig {
                 enabled: false,
@@ -292,14 +416,10 @@ impl Default for Config {
                 max_peer_connections: default_torrent_max_peer_connections(),
                 max_upload_slots: default_torrent_max_upload_slots(),
             },
-            ftp: FtpConfig {
-                passive_mode: true,
-                default_port: 21,
-            },
-            sftp: SftpConfig {
-                default_port: 22,
-                key_path: None,
-            },
+            ftp: FtpConfig::default(),
+            sftp: SftpConfig::default(),
+            s3: S3Config::default(),
+            metalink: MetalinkConfig::default(),
         }
     }
 }
@@ -0,0 +1,711 @@
+//! zsync-style delta downloads.
+//!
+//! A [`DeltaControl`] describes the target file as a list of fixed-size blocks
+//! with per-block checksums, taken either from a `.zsync` control file or
+//! from the piece hashes in a Metalink manifest. [`fill_from_seed`] scans an
+//! older local copy (the *seed*) and writes every block it already contains
+//! straight into the output file; only the remaining ranges then need to be
+//! fetched. [`AdvancedDownloader::download_delta`](crate::AdvancedDownloader::download_delta)
+//! drives the whole process.
+//!
+//! `.zsync` files carry a weak rolling checksum per block, so matching blocks
+//! are found at any offset in the seed (insertions and deletions are fine).
+//! Metalink piece hashes have no rolling component, so pieces are only
+//! compared at their own offset — enough for files patched in place.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use kget::delta::parse_zsync;
+//! use kget::{AdvancedDownloader, ProxyConfig, Optimizer};
+//! use std::path::Path;
+//!
+//! let control = parse_zsync(&std::fs::read("nightly.iso.zsync")?)?;
+//! let downloader = AdvancedDownloader::new(
+//!     "https://example.com/nightly.iso".to_string(),
+//!     "nightly.iso".to_string(),
+//!     false,
+//!     ProxyConfig::default(),
+//!     Optimizer::new(),
+//! )?;
+//! let report = downloader.download_delta(&control, Path::new("yesterday.iso"))?;
+//! println!("Saved {} bytes", report.reused_bytes);
+//! # Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+//! ```
+
+use crate::advanced_download::AdvancedDownloader;
+use crate::config::ProxyConfig;
+use crate::metalink::{self, MetalinkFile};
+use crate::optimization::Optimizer;
+use sha2::Digest;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::FileExt;
+#[cfg(target_family = "windows")]
+use std::os::windows::fs::FileExt;
+
+/// How much of the seed is buffered at a time while scanning.
+const SCAN_BUF_SIZE: usize = 8 * 1024 * 1024;
+
+// ============================================================================
+// Control data
+// ============================================================================
+
+/// Per-block checksums describing the file to reconstruct.
+#[derive(Debug, Clone)]
+pub struct DeltaControl {
+    /// Target file name from the control file, if given.
+    pub filename: Option<String>,
+    /// Target URL from the control file, possibly relative to the control file's URL.
+    pub url: Option<String>,
+    /// Total size of the target file in bytes.
+    pub length: u64,
+    /// Size of every block except possibly the last.
+    pub block_size: usize,
+    /// Whole-file SHA-1 (`.zsync` only), checked after reconstruction.
+    pub sha1: Option<String>,
+    kind: BlockKind,
+    blocks: Vec<BlockSum>,
+}
+
+#[derive(Debug, Clone)]
+enum BlockKind {
+    /// Rolling checksum + truncated MD4, matched at any seed offset.
+    Zsync { rsum_bytes: usize, checksum_bytes: usize, seq_matches: usize },
+    /// Full piece digest, matched only at the piece's own offset.
+    Pieces { algorithm: PieceAlgorithm },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PieceAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+    Md5,
+}
+
+#[derive(Debug, Clone)]
+struct BlockSum {
+    rsum: u32,
+    strong: Vec<u8>,
+}
+
+impl DeltaControl {
+    /// Number of blocks in the target file.
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Byte range `[start, end)` covered by block `index`.
+    pub fn block_range(&self, index: usize) -> (u64, u64) {
+        let start = index as u64 * self.block_size as u64;
+        (start, (start + self.block_size as u64).min(self.length))
+    }
+
+    /// Build a control from the piece hashes of a Metalink file entry.
+    ///
+    /// Returns `None` when the entry has no usable `<pieces>` element.
+    pub fn from_metalink(file: &MetalinkFile) -> Option<Self> {
+        let pieces = file.pieces.as_ref()?;
+        let algorithm = match pieces.hash_type.as_str() {
+            "sha-1" => PieceAlgorithm::Sha1,
+            "sha-256" => PieceAlgorithm::Sha256,
+            "sha-512" => PieceAlgorithm::Sha512,
+            "md5" => PieceAlgorithm::Md5,
+            _ => return None,
+        };
+        let block_size = usize::try_from(pieces.length).ok().filter(|&n| n > 0)?;
+        let blocks = pieces
+            .hashes
+            .iter()
+            .map(|h| hex::decode(h).ok().map(|strong| BlockSum { rsum: 0, strong }))
+            .collect::<Option<Vec<_>>>()?;
+        let length = file
+            .size
+            .unwrap_or(block_size as u64 * blocks.len() as u64);
+        if blocks.len() as u64 != length.div_ceil(block_size as u64) {
+            return None;
+        }
+
+        Some(Self {
+            filename: Some(file.name.clone()),
+            url: file.urls.first().map(|u| u.url.clone()),
+            length,
+            block_size,
+            sha1: None,
+            kind: BlockKind::Pieces { algorithm },
+            blocks,
+        })
+    }
+}
+
+/// Parse a `.zsync` control file.
+///
+/// # Errors
+///
+/// Returns an error if required headers are missing, the block checksums are
+/// truncated, or the target is only available zsync-compressed (`Z-URL`).
+pub fn parse_zsync(data: &[u8]) -> Result<DeltaControl, Box<dyn Error + Send + Sync>> {
+    let split = data
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .ok_or("Malformed .zsync file (no header terminator)")?;
+    let header = std::str::from_utf8(&data[..split])
+        .map_err(|_| "Malformed .zsync file (header is not UTF-8)")?;
+    let checksums = &data[split + 2..];
+
+    let mut fields: HashMap<&str, &str> = HashMap::new();
+    for line in header.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim(), value.trim());
+        }
+    }
+
+    let field = |name: &str| -> Result<&str, String> {
+        fields
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("Malformed .zsync file (missing '{}' header)", name))
+    };
+
+    let length: u64 = field("Length")?.parse().map_err(|_| "Invalid .zsync Length")?;
+    let block_size: usize = field("Blocksize")?.parse().map_err(|_| "Invalid .zsync Blocksize")?;
+    if block_size == 0 {
+        return Err("Invalid .zsync Blocksize".into());
+    }
+
+    let lengths: Vec<usize> = fields
+        .get("Hash-Lengths")
+        .unwrap_or(&"1,4,16")
+        .split(',')
+        .map(|s| s.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| "Invalid .zsync Hash-Lengths")?;
+    let [seq_matches, rsum_bytes, checksum_bytes] = lengths[..] else {
+        return Err("Invalid .zsync Hash-Lengths".into());
+    };
+    if !(1..=2).contains(&seq_matches) || !(1..=4).contains(&rsum_bytes) || !(3..=16).contains(&checksum_bytes) {
+        return Err("Unsupported .zsync Hash-Lengths".into());
+    }
+
+    let url = fields.get("URL").map(|s| s.to_string());
+    if url.is_none() && fields.contains_key("Z-URL") {
+        return Err("Compressed zsync targets (Z-URL only) are not supported".into());
+    }
+
+    let block_count = length.div_ceil(block_size as u64) as usize;
+    let entry_len = rsum_bytes + checksum_bytes;
+    if checksums.len() < block_count * entry_len {
+        return Err(format!(
+            "Truncated .zsync file: expected {} block checksums",
+            block_count
+        )
+        .into());
+    }
+
+    let blocks = checksums
+        .chunks_exact(entry_len)
+        .take(block_count)
+        .map(|entry| BlockSum {
+            rsum: entry[..rsum_bytes].iter().fold(0u32, |acc, &b| (acc << 8) | b as u32),
+            strong: entry[rsum_bytes..].to_vec(),
+        })
+        .collect();
+
+    Ok(DeltaControl {
+        filename: fields.get("Filename").map(|s| s.to_string()),
+        url,
+        length,
+        block_size,
+        sha1: fields.get("SHA-1").map(|s| s.to_ascii_lowercase()),
+        kind: BlockKind::Zsync { rsum_bytes, checksum_bytes, seq_matches },
+        blocks,
+    })
+}
+
+/// Outcome of a delta download.
+#[derive(Debug, Clone, Default)]
+pub struct DeltaReport {
+    /// Size of the reconstructed file.
+    pub total_bytes: u64,
+    /// Bytes copied from the seed instead of downloaded.
+    pub reused_bytes: u64,
+    /// Bytes fetched from the server.
+    pub downloaded_bytes: u64,
+    /// Number of blocks in the target file.
+    pub blocks_total: usize,
+    /// Number of blocks found in the seed.
+    pub blocks_reused: usize,
+}
+
+impl DeltaReport {
+    /// Share of the file taken from the seed, in percent.
+    pub fn saved_percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        self.reused_bytes as f64 * 100.0 / self.total_bytes as f64
+    }
+}
+
+// ============================================================================
+// Entry point
+// ============================================================================
+
+/// Reconstruct the target of a `.zsync` control file or Metalink manifest
+/// from `seed`, downloading only the blocks the seed does not contain.
+///
+/// `source` is a local path or HTTP(S) URL. `output` may be a file or an
+/// existing directory; when `None`, the target's own file name is used in
+/// the current directory.
+pub fn download_with_seed(
+    source: &str,
+    seed: &Path,
+    output: Option<&str>,
+    quiet: bool,
+    proxy: ProxyConfig,
+    optimizer: Optimizer,
+) -> Result<DeltaReport, Box<dyn Error + Send + Sync>> {
+    let (control, target_url, expected_sha256) = if metalink::is_metalink(source) {
+        let manifest = metalink::parse(&metalink::fetch_manifest(source, quiet, &proxy)?)?;
+        let file = manifest
+            .files
+            .iter()
+            .find(|f| f.pieces.is_some())
+            .ok_or("Metalink manifest has no piece hashes; delta download needs <pieces>")?;
+        let control = DeltaControl::from_metalink(file)
+            .ok_or("Metalink piece hashes are unsupported or inconsistent with the file size")?;
+        (control, file.urls[0].url.clone(), file.sha256.clone())
+    } else {
+        let control = parse_zsync(&fetch_control(source, quiet, &proxy)?)?;
+        let url = control.url.as_deref().ok_or(".zsync file has no URL header")?;
+        let target_url = resolve_target_url(source, url)?;
+        (control, target_url, None)
+    };
+
+    let name = match &control.filename {
+        Some(name) => metalink::sanitise_filename(name),
+        None => crate::utils::get_filename_from_url_or_default(&target_url, "download"),
+    };
+    let output_path = match output {
+        Some(dir) if Path::new(dir).is_dir() => Path::new(dir).join(name),
+        Some(path) => path.into(),
+        None => name.into(),
+    };
+
+    let mut downloader = AdvancedDownloader::new(
+        target_url,
+        output_path.to_string_lossy().into_owned(),
+        quiet,
+        proxy,
+        optimizer,
+    )?;
+    if let Some(sha256) = expected_sha256 {
+        downloader.set_expected_sha256(sha256);
+    }
+    downloader.download_delta(&control, seed)
+}
+
+/// Read a `.zsync` file from disk or over HTTP.
+fn fetch_control(source: &str, quiet: bool, proxy: &ProxyConfig) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        if !quiet {
+            println!("Fetching zsync control file: {}", source);
+        }
+        let response = metalink::build_http_client(proxy)?.get(source).send()?;
+        if !response.status().is_success() {
+            return Err(format!("Failed to fetch zsync control file: HTTP {}", response.status()).into());
+        }
+        Ok(response.bytes()?.to_vec())
+    } else {
+        std::fs::read(source).map_err(|e| format!("Cannot read zsync file '{}': {}", source, e).into())
+    }
+}
+
+/// Resolve the control file's `URL:` header, which is usually relative to
+/// the control file itself.
+fn resolve_target_url(source: &str, target: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if let Ok(absolute) = url::Url::parse(target) {
+        return Ok(absolute.into());
+    }
+    let base = url::Url::parse(source).map_err(|_| {
+        format!(
+            "The .zsync URL '{}' is relative; fetch the control file by URL so it can be resolved",
+            target
+        )
+    })?;
+    Ok(base.join(target)?.into())
+}
+
+// ============================================================================
+// Seed matching
+// ============================================================================
+
+/// Copy every target block found in `seed` into `output` at its final offset.
+///
+/// `output` must already be sized to [`DeltaControl::length`]. Returns one
+/// flag per block, `true` where the block was filled from the seed.
+pub fn fill_from_seed(control: &DeltaControl, seed: &Path, output: &File) -> io::Result<Vec<bool>> {
+    let mut seed_file = File::open(seed)?;
+    match control.kind {
+        BlockKind::Zsync { rsum_bytes, checksum_bytes, seq_matches } => {
+            scan_rolling(control, &mut seed_file, output, rsum_bytes, checksum_bytes, seq_matches)
+        }
+        BlockKind::Pieces { algorithm } => scan_aligned(control, &seed_file, output, algorithm),
+    }
+}
+
+/// Coalesce the blocks not yet filled into `[start, end)` byte ranges.
+pub fn missing_ranges(control: &DeltaControl, have: &[bool]) -> Vec<(u64, u64)> {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for (index, _) in have.iter().enumerate().filter(|(_, h)| !**h) {
+        let (start, end) = control.block_range(index);
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+/// Check the reconstructed file against the control's whole-file SHA-1 or,
+/// for Metalink pieces, every piece hash.
+pub fn verify(control: &DeltaControl, path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut file = File::open(path)?;
+    match control.kind {
+        BlockKind::Zsync { .. } => {
+            let Some(expected) = &control.sha1 else {
+                return Ok(());
+            };
+            let mut hasher = sha1::Sha1::new();
+            io::copy(&mut file, &mut hasher)?;
+            let got = hex::encode(hasher.finalize());
+            if &got != expected {
+                return Err(format!("Delta result SHA-1 mismatch: expected {}, got {}", expected, got).into());
+            }
+        }
+        BlockKind::Pieces { algorithm } => {
+            let mut buf = vec![0u8; control.block_size];
+            for (index, block) in control.blocks.iter().enumerate() {
+                let (start, end) = control.block_range(index);
+                let data = &mut buf[..(end - start) as usize];
+                file.read_exact(data)?;
+                if piece_digest(algorithm, data) != block.strong {
+                    return Err(format!("Delta result piece {} hash mismatch", index).into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn scan_aligned(
+    control: &DeltaControl,
+    seed: &File,
+    output: &File,
+    algorithm: PieceAlgorithm,
+) -> io::Result<Vec<bool>> {
+    let seed_len = seed.metadata()?.len();
+    let mut have = vec![false; control.blocks.len()];
+    let mut buf = vec![0u8; control.block_size];
+
+    for (index, block) in control.blocks.iter().enumerate() {
+        let (start, end) = control.block_range(index);
+        if end > seed_len {
+            break;
+        }
+        let data = &mut buf[..(end - start) as usize];
+        read_exact_at(seed, data, start)?;
+        if piece_digest(algorithm, data) == block.strong {
+            write_all_at(output, data, start)?;
+            have[index] = true;
+        }
+    }
+    Ok(have)
+}
+
+fn scan_rolling(
+    control: &DeltaControl,
+    seed: &mut File,
+    output: &File,
+    rsum_bytes: usize,
+    checksum_bytes: usize,
+    seq_matches: usize,
+) -> io::Result<Vec<bool>> {
+    let bs = control.block_size;
+    let n = control.blocks.len();
+    let mut have = vec![false; n];
+    if n == 0 {
+        return Ok(have);
+    }
+
+    let mask = if rsum_bytes == 4 { u32::MAX } else { (1u32 << (8 * rsum_bytes)) - 1 };
+    let mut by_rsum: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, block) in control.blocks.iter().enumerate() {
+        by_rsum.entry(block.rsum & mask).or_default().push(index);
+    }
+
+    let strong = |data: &[u8]| -> Vec<u8> { md4::Md4::digest(data)[..checksum_bytes].to_vec() };
+
+    // Blocks are compared over `seq_matches` consecutive windows, so keep that
+    // many blocks (plus one byte to roll into) buffered ahead of `pos`.
+    let lookahead = bs * seq_matches + 1;
+    let mut buf: Vec<u8> = Vec::with_capacity(SCAN_BUF_SIZE + lookahead);
+    let mut base: u64 = 0; // seed offset of buf[0]
+    let mut pos = 0usize;
+    let mut eof = false;
+    let mut sums: Option<(u16, u16)> = None;
+    // (block index, seed offset) of the previous accepted match
+    let mut last_match: Option<(usize, u64)> = None;
+    let mut remaining = n;
+
+    loop {
+        if !eof && buf.len() - pos < lookahead {
+            buf.drain(..pos);
+            base += pos as u64;
+            pos = 0;
+            let filled = buf.len();
+            buf.resize(filled + SCAN_BUF_SIZE, 0);
+            let got = read_full(seed, &mut buf[filled..])?;
+            buf.truncate(filled + got);
+            if got == 0 {
+                eof = true;
+                // zsync zero-pads the final block, so pad the seed the same way.
+                buf.extend(std::iter::repeat_n(0u8, bs));
+            }
+        }
+        if buf.len() - pos < bs || remaining == 0 {
+            break;
+        }
+
+        let (a, b) = *sums.get_or_insert_with(|| rsum(&buf[pos..pos + bs]));
+        let key = (((a as u32) << 16) | b as u32) & mask;
+
+        let mut matched = false;
+        if let Some(candidates) = by_rsum.get(&key) {
+            let window = strong(&buf[pos..pos + bs]);
+            let offset = base + pos as u64;
+            for &index in candidates {
+                if control.blocks[index].strong != window {
+                    continue;
+                }
+                // With short hashes zsync requires two consecutive blocks to
+                // match: either the one after this, or the one just before.
+                if seq_matches > 1 && index + 1 < n {
+                    let follows = last_match == Some((index.wrapping_sub(1), offset.wrapping_sub(bs as u64)));
+                    let next_ok = buf.len() - pos >= 2 * bs
+                        && strong(&buf[pos + bs..pos + 2 * bs]) == control.blocks[index + 1].strong;
+                    if !follows && !next_ok {
+                        continue;
+                    }
+                }
+                if !have[index] {
+                    let (start, end) = control.block_range(index);
+                    write_all_at(output, &buf[pos..pos + (end - start) as usize], start)?;
+                    have[index] = true;
+                    remaining -= 1;
+                }
+                last_match = Some((index, offset));
+                matched = true;
+            }
+        }
+
+        if matched {
+            pos += bs;
+            sums = None;
+        } else if pos + bs < buf.len() {
+            let (old, new) = (buf[pos] as u16, buf[pos + bs] as u16);
+            let a = a.wrapping_sub(old).wrapping_add(new);
+            let b = b.wrapping_sub((bs as u16).wrapping_mul(old)).wrapping_add(a);
+            sums = Some((a, b));
+            pos += 1;
+        } else {
+            pos += 1;
+            sums = None;
+        }
+    }
+
+    Ok(have)
+}
+
+/// zsync's weak checksum: `a = Σ x`, `b = Σ (len - i)·x`, both mod 2¹⁶.
+fn rsum(data: &[u8]) -> (u16, u16) {
+    let len = data.len();
+    let mut a: u16 = 0;
+    let mut b: u16 = 0;
+    for (i, &c) in data.iter().enumerate() {
+        a = a.wrapping_add(c as u16);
+        b = b.wrapping_add(((len - i) as u16).wrapping_mul(c as u16));
+    }
+    (a, b)
+}
+
+fn piece_digest(algorithm: PieceAlgorithm, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        PieceAlgorithm::Sha1 => sha1::Sha1::digest(data).to_vec(),
+        PieceAlgorithm::Sha256 => sha2::Sha256::digest(data).to_vec(),
+        PieceAlgorithm::Sha512 => sha2::Sha512::digest(data).to_vec(),
+        PieceAlgorithm::Md5 => md5::Md5::digest(data).to_vec(),
+    }
+}
+
+fn read_full(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        #[cfg(target_family = "unix")]
+        let n = file.read_at(buf, offset)?;
+        #[cfg(target_family = "windows")]
+        let n = file.seek_read(buf, offset)?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf = &mut buf[n..];
+        offset += n as u64;
+    }
+    Ok(())
+}
+
+fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        #[cfg(target_family = "unix")]
+        let n = file.write_at(buf, offset)?;
+        #[cfg(target_family = "windows")]
+        let n = file.seek_write(buf, offset)?;
+        buf = &buf[n..];
+        offset += n as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize, mut seed: u32) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as u8
+            })
+            .collect()
+    }
+
+    /// Build a `.zsync` file the way `zsyncmake` lays it out.
+    fn build_zsync(target: &[u8], bs: usize, seq: usize, rsum_bytes: usize, ck: usize) -> Vec<u8> {
+        let mut out = format!(
+            "zsync: 0.6.2\nFilename: t.bin\nBlocksize: {}\nLength: {}\nHash-Lengths: {},{},{}\nURL: t.bin\nSHA-1: {}\n\n",
+            bs,
+            target.len(),
+            seq,
+            rsum_bytes,
+            ck,
+            hex::encode(sha1::Sha1::digest(target))
+        )
+        .into_bytes();
+        for chunk in target.chunks(bs) {
+            let mut block = chunk.to_vec();
+            block.resize(bs, 0);
+            let (a, b) = rsum(&block);
+            let r = [(a >> 8) as u8, a as u8, (b >> 8) as u8, b as u8];
+            out.extend_from_slice(&r[4 - rsum_bytes..]);
+            out.extend_from_slice(&md4::Md4::digest(&block)[..ck]);
+        }
+        out
+    }
+
+    fn reconstruct(control: &DeltaControl, seed: &[u8]) -> (Vec<bool>, Vec<u8>) {
+        let dir = tempfile::tempdir().unwrap();
+        let seed_path = dir.path().join("seed");
+        std::fs::write(&seed_path, seed).unwrap();
+        let out_path = dir.path().join("out");
+        let out = File::options().read(true).write(true).create(true).truncate(true).open(&out_path).unwrap();
+        out.set_len(control.length).unwrap();
+        let have = fill_from_seed(control, &seed_path, &out).unwrap();
+        (have, std::fs::read(&out_path).unwrap())
+    }
+
+    #[test]
+    fn rolling_update_matches_recompute() {
+        let data = pseudo_random(600, 7);
+        let bs = 128;
+        let (mut a, mut b) = rsum(&data[..bs]);
+        for pos in 1..data.len() - bs {
+            let (old, new) = (data[pos - 1] as u16, data[pos - 1 + bs] as u16);
+            a = a.wrapping_sub(old).wrapping_add(new);
+            b = b.wrapping_sub((bs as u16).wrapping_mul(old)).wrapping_add(a);
+            assert_eq!((a, b), rsum(&data[pos..pos + bs]), "offset {}", pos);
+        }
+    }
+
+    #[test]
+    fn parses_zsync_headers() {
+        let target = pseudo_random(5000, 1);
+        let control = parse_zsync(&build_zsync(&target, 1024, 2, 3, 5)).unwrap();
+        assert_eq!(control.length, 5000);
+        assert_eq!(control.block_size, 1024);
+        assert_eq!(control.block_count(), 5);
+        assert_eq!(control.block_range(4), (4096, 5000));
+        assert_eq!(control.filename.as_deref(), Some("t.bin"));
+        assert_eq!(control.url.as_deref(), Some("t.bin"));
+
+        assert!(parse_zsync(b"zsync: 0.6.2\nBlocksize: 1024\nLength: 10\nZ-URL: t.gz\n\n").is_err());
+        assert!(parse_zsync(b"zsync: 0.6.2\nBlocksize: 1024\nLength: 5000\n\nshort").is_err());
+    }
+
+    #[test]
+    fn finds_shifted_blocks_in_seed() {
+        let target = pseudo_random(16 * 1024 + 300, 42);
+        let control = parse_zsync(&build_zsync(&target, 1024, 1, 4, 16)).unwrap();
+
+        // Seed: 77 bytes inserted at the front and block 5 overwritten.
+        let mut seed = pseudo_random(77, 9);
+        seed.extend_from_slice(&target);
+        for byte in &mut seed[77 + 5 * 1024..77 + 5 * 1024 + 10] {
+            *byte ^= 0xff;
+        }
+
+        let (have, out) = reconstruct(&control, &seed);
+        let missing: Vec<usize> = (0..have.len()).filter(|&i| !have[i]).collect();
+        assert_eq!(missing, vec![5]);
+        for (i, _) in have.iter().enumerate().filter(|(_, h)| **h) {
+            let (start, end) = control.block_range(i);
+            assert_eq!(out[start as usize..end as usize], target[start as usize..end as usize]);
+        }
+        assert_eq!(missing_ranges(&control, &have), vec![(5120, 6144)]);
+    }
+
+    #[test]
+    fn sequential_matching_with_short_hashes() {
+        let target = pseudo_random(8 * 512, 3);
+        let control = parse_zsync(&build_zsync(&target, 512, 2, 2, 3)).unwrap();
+        let mut seed = target.clone();
+        seed[512 * 6] ^= 1;
+
+        let (have, _) = reconstruct(&control, &seed);
+        assert_eq!(have, vec![true, true, true, true, true, true, false, true]);
+    }
+
+    #[test]
+    fn missing_ranges_coalesce_adjacent_blocks() {
+        let control = parse_zsync(&build_zsync(&pseudo_random(4500, 5), 1000, 1, 4, 16)).unwrap();
+        let have = [true, false, false, true, false];
+        assert_eq!(missing_ranges(&control, &have), vec![(1000, 3000), (4000, 4500)]);
+    }
+}
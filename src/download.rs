@@ -28,12 +28,19 @@
 //! ```
 
 use crate::DownloadOptions;
+use crate::cache::{CacheEntry, HttpCache, TeeReader};
+use crate::checksum::{self, ChecksumAlgorithm};
 use crate::config::ProxyConfig;
+use crate::error::KgetError;
+use crate::local;
+use crate::metalink::{self, HttpMetalink};
 use crate::optimization::Optimizer;
 use crate::progress::create_progress_bar;
+use crate::store::{self, OutputMode};
 use crate::utils::{self, print};
 use humansize::{DECIMAL, format_size};
 use mime::Mime;
+use reqwest::StatusCode;
 use reqwest::blocking::Client;
 use reqwest::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE};
 use sha2::Digest;
@@ -45,6 +52,8 @@ use std::time::{Duration, Instant};
 
 const MAX_RETRIES: u32 = 3;
 const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Redirects followed per request, as many as reqwest's default policy
+const MAX_REDIRECTS: u32 = 10;
 
 /// Check if there's enough disk space for the download.
 ///
@@ -119,11 +128,18 @@ pub fn validate_filename(filename: &str) -> Result<(), Box<dyn Error + Send + Sy
 /// This is the simple download function for basic use cases. For parallel
 /// connections and resume support, use [`AdvancedDownloader`](crate::AdvancedDownloader).
 ///
+/// `file://` and `data:` URLs are read locally (see [`crate::local`]) and go
+/// through the same output-path, progress and verification handling.
+///
+/// When the optimizer's cache is enabled, cacheable responses are stored in
+/// the [HTTP cache](crate::cache) and later served from it, fresh or after a
+/// `304 Not Modified` revalidation.
+///
 /// # Arguments
 ///
 /// * `target` - URL to download
 /// * `proxy` - Proxy configuration (use `ProxyConfig::default()` for no proxy)
-/// * `_optimizer` - Optimizer instance (reserved for future use)
+/// * `optimizer` - Speed limit, HTTP cache and content-store settings
 /// * `options` - Download options (quiet mode, output path, ISO verification)
 /// * `status_callback` - Optional callback for status messages
 ///
@@ -160,11 +176,72 @@ pub fn download(
     options: DownloadOptions,
     status_callback: Option<&(dyn Fn(String) + Send + Sync)>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if local::is_local_url(target) {
+        return download_local(target, optimizer, options, status_callback);
+    }
+
     let quiet_mode = options.quiet_mode;
 
+    // A verified copy in the content store makes the request unnecessary.
+    if let Some(expected) = options.expected_sha256.as_deref()
+        && let Some(store) = optimizer.content_store()
+    {
+        let dest = resolve_destination(target, options.output_path.clone(), None)?;
+        if let Some(kind) = store.materialize(&ChecksumAlgorithm::Sha256, expected, &dest)? {
+            let msg = format!("Reused {} from content store ({})", dest.display(), kind);
+            print(&msg, quiet_mode);
+            if let Some(cb) = status_callback {
+                cb(msg);
+            }
+            return Ok(());
+        }
+    }
+
+    // Fresh cached responses are served without a request; stale ones with a
+    // validator are revalidated with a conditional GET below.
+    // Ranged and credentialed requests bypass the cache: a partial body, or
+    // one meant for a particular user, must not answer other requests.
+    let bypass = options.extra_headers.iter().any(|(n, _)| {
+        ["range", "authorization", "cookie"]
+            .iter()
+            .any(|h| n.eq_ignore_ascii_case(h))
+    });
+    let cache = optimizer.http_cache().filter(|_| !bypass);
+    let mut cached = cache
+        .as_ref()
+        .and_then(|c| c.lookup(target))
+        .filter(|e| e.matches_request(&options.extra_headers));
+    if let (Some(cache), Some(entry)) = (&cache, cached.as_mut())
+        && entry.is_fresh(crate::cache::now())
+    {
+        match download_cached(target, cache, entry, &optimizer, options.clone(), status_callback) {
+            Ok(()) => {
+                let size = entry.size;
+                cache.record(|s| {
+                    s.hits += 1;
+                    s.bytes_served += size;
+                });
+                return Ok(());
+            }
+            Err(e) => {
+                print(&format!("Cached copy unusable ({}), downloading again", e), quiet_mode);
+                cache.remove(target);
+                cached = None;
+            }
+        }
+    }
+    let conditional = cached
+        .as_ref()
+        .filter(|e| e.has_validator())
+        .map(HttpCache::conditional_headers)
+        .unwrap_or_default();
+
+    // Redirects are followed below so RFC 6249 `Link` headers on a mirror
+    // redirector's `302` are seen.
     let mut client_builder = Client::builder()
         .timeout(Duration::from_secs(30))
         .user_agent(concat!("KGet/", env!("CARGO_PKG_VERSION")))
+        .redirect(reqwest::redirect::Policy::none())
         .no_gzip()
         .no_deflate();
 
@@ -186,10 +263,16 @@ pub fn download(
 
     let client = client_builder.build()?;
 
+    let origin = reqwest::Url::parse(target)?;
+    let mut url = origin.clone();
+    let mut links = HttpMetalink::default();
+    let mut redirects = 0;
     let mut retries = 0;
     let response = loop {
-        let mut req = client.get(target);
-        for (name, value) in &options.extra_headers {
+        let mut req = client.get(url.as_str());
+        // Extra headers may carry credentials; keep them on the original host.
+        let extra = options.extra_headers.iter().filter(|_| url.origin() == origin.origin());
+        for (name, value) in extra.chain(&conditional) {
             if let (Ok(n), Ok(v)) = (
                 reqwest::header::HeaderName::from_bytes(name.as_bytes()),
                 reqwest::header::HeaderValue::from_str(value),
@@ -200,7 +283,25 @@ pub fn download(
         match req.send() {
             Ok(resp) => {
                 let status = resp.status();
-                if status.is_success() {
+                links.merge(HttpMetalink::from_headers(resp.headers(), url.as_str()));
+                let location = resp
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|l| l.to_str().ok())
+                    .and_then(|l| url.join(l).ok());
+                if status.is_redirection()
+                    && let Some(next) = location
+                {
+                    redirects += 1;
+                    if redirects > MAX_REDIRECTS {
+                        return Err(format!("Too many redirects for {}", target).into());
+                    }
+                    url = next;
+                    continue;
+                }
+                if status.is_success()
+                    || (status == StatusCode::NOT_MODIFIED && !conditional.is_empty())
+                {
                     break resp;
                 } else if status.is_server_error() {
                     // 5xx — transient, worth retrying
@@ -248,12 +349,35 @@ pub fn download(
         quiet_mode,
     );
 
+    if response.status() == StatusCode::NOT_MODIFIED
+        && let (Some(cache), Some(entry)) = (&cache, cached.as_mut())
+    {
+        cache.revalidated(entry, response.headers());
+        download_cached(target, cache, entry, &optimizer, options, status_callback).inspect_err(|_| {
+            cache.remove(target);
+        })?;
+        let size = entry.size;
+        cache.record(|s| {
+            s.revalidated += 1;
+            s.bytes_served += size;
+        });
+        return Ok(());
+    }
+    if let Some(cache) = &cache {
+        cache.record(|s| s.misses += 1);
+    }
+
     let content_length = response
         .headers()
         .get(CONTENT_LENGTH)
         .and_then(|ct_len| ct_len.to_str().ok())
         .and_then(|s| s.parse::<u64>().ok());
 
+    if links.has_mirrors() {
+        drop(response);
+        return download_from_mirrors(target, links, content_length, &proxy, &optimizer, options, status_callback);
+    }
+
     let content_type = response
         .headers()
         .get(CONTENT_TYPE)
@@ -266,6 +390,156 @@ pub fn download(
         .and_then(|v| v.to_str().ok())
         .and_then(parse_content_disposition_filename);
 
+    // Only a complete `200` body is what the digest headers describe.
+    let digests = if response.status() == StatusCode::OK {
+        checksum::response_digests(response.headers(), true)
+    } else {
+        Vec::new()
+    };
+
+    let writer = cache
+        .as_ref()
+        .filter(|_| response.status() == StatusCode::OK)
+        .and_then(|c| c.writer(target, response.headers(), content_length, &options.extra_headers));
+
+    let response_content_length = response.content_length();
+    let mut source = TeeReader::new(
+        response.take(response_content_length.unwrap_or(u64::MAX)),
+        writer,
+    );
+
+    save_stream(
+        target,
+        &mut source,
+        SourceInfo {
+            content_length,
+            content_type,
+            server_filename,
+            digests,
+        },
+        &optimizer,
+        options,
+        status_callback,
+    )?;
+    source.commit();
+    Ok(())
+}
+
+/// Download through the Metalink engine from the mirrors a server listed in
+/// RFC 6249 `Link: rel=duplicate` headers, verifying any `Digest` it sent.
+fn download_from_mirrors(
+    target: &str,
+    links: HttpMetalink,
+    size: Option<u64>,
+    proxy: &ProxyConfig,
+    optimizer: &Optimizer,
+    options: DownloadOptions,
+    status_callback: Option<&(dyn Fn(String) + Send + Sync)>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let dest = resolve_destination(target, options.output_path.clone(), None)?;
+    let msg = format!(
+        "Server lists {} mirror(s) (RFC 6249), switching to multi-mirror mode",
+        links.mirrors.len()
+    );
+    print(&msg, options.quiet_mode);
+    if let Some(cb) = status_callback {
+        cb(msg);
+    }
+
+    let file = links.into_file(target, size);
+    metalink::download_file(&file, &dest, options.quiet_mode, proxy, optimizer, None)?;
+    if let Some(expected) = options.expected_sha256.as_deref() {
+        verify_file_sha256(&dest, Some(expected), status_callback)?;
+        if let Some(store) = optimizer.content_store()
+            && let Err(e) = store.insert(&dest, &ChecksumAlgorithm::Sha256, expected)
+        {
+            print(&format!("Warning: could not add to content store: {}", e), options.quiet_mode);
+        }
+    }
+    Ok(())
+}
+
+/// Save a cached response body as if it had just been downloaded.
+fn download_cached(
+    target: &str,
+    cache: &HttpCache,
+    entry: &mut CacheEntry,
+    optimizer: &Optimizer,
+    options: DownloadOptions,
+    status_callback: Option<&(dyn Fn(String) + Send + Sync)>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    print("Using cached copy", options.quiet_mode);
+    cache.touch(entry);
+    let info = SourceInfo {
+        content_length: Some(entry.size),
+        content_type: entry.content_type.as_deref().and_then(|s| s.parse::<Mime>().ok()),
+        server_filename: entry
+            .content_disposition
+            .as_deref()
+            .and_then(parse_content_disposition_filename),
+        digests: Vec::new(),
+    };
+    save_stream(target, cache.open(entry)?, info, optimizer, options, status_callback)
+}
+
+/// Metadata about a source stream, gathered from response headers or the
+/// local filesystem before any bytes are written.
+struct SourceInfo {
+    content_length: Option<u64>,
+    content_type: Option<Mime>,
+    server_filename: Option<String>,
+    /// Digests announced by `Repr-Digest`/`Content-Digest`/`Digest` headers.
+    digests: Vec<(ChecksumAlgorithm, String)>,
+}
+
+/// Copy a local `file://` path or decode a `data:` URL to the output path.
+fn download_local(
+    target: &str,
+    optimizer: Optimizer,
+    options: DownloadOptions,
+    status_callback: Option<&(dyn Fn(String) + Send + Sync)>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let source = local::open(target)?;
+
+    print(
+        &format!(
+            "Reading local source... {}",
+            if local::is_data_url(target) { "data: URL" } else { "file" }
+        ),
+        options.quiet_mode,
+    );
+
+    let info = SourceInfo {
+        content_length: source.length,
+        content_type: source.content_type.as_deref().and_then(|s| s.parse::<Mime>().ok()),
+        server_filename: source.filename,
+        digests: Vec::new(),
+    };
+
+    save_stream(target, source.reader, info, &optimizer, options, status_callback)
+}
+
+/// Resolve the destination, stream `source` into it with progress reporting
+/// and throttling, then run the requested SHA-256 verification and check any
+/// digests the server announced.
+///
+/// Shared by the HTTP path and the local `file://` / `data:` path.
+fn save_stream(
+    target: &str,
+    mut source: impl Read,
+    info: SourceInfo,
+    optimizer: &Optimizer,
+    options: DownloadOptions,
+    status_callback: Option<&(dyn Fn(String) + Send + Sync)>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let quiet_mode = options.quiet_mode;
+    let SourceInfo {
+        content_length,
+        content_type,
+        server_filename,
+        digests,
+    } = info;
+
     if let Some(len) = content_length {
         print(
             &format!("Length: {} ({})", len, format_size(len, DECIMAL)),
@@ -292,58 +566,7 @@ pub fn download(
         );
     }
 
-    let tentative_path: PathBuf;
-
-    if let Some(output_arg_str) = options.output_path {
-        let user_path = PathBuf::from(output_arg_str.clone());
-
-        let is_target_dir =
-            user_path.is_dir() || output_arg_str.ends_with(std::path::MAIN_SEPARATOR);
-
-        if is_target_dir {
-            let base_filename = utils::get_filename_from_url_or_default(target, "downloaded_file");
-            validate_filename(&base_filename)?;
-            tentative_path = user_path.join(base_filename);
-        } else {
-            if let Some(file_name_osstr) = user_path.file_name() {
-                if let Some(file_name_str) = file_name_osstr.to_str() {
-                    if file_name_str.is_empty() {
-                        return Err(format!(
-                            "Invalid output path, does not specify a file name: {}",
-                            user_path.display()
-                        )
-                        .into());
-                    }
-                    validate_filename(file_name_str)?;
-                } else {
-                    return Err("Output filename contains invalid characters (non-UTF-8)".into());
-                }
-            } else {
-                return Err(format!(
-                    "Invalid output path, does not specify a file name: {}",
-                    user_path.display()
-                )
-                .into());
-            }
-            tentative_path = user_path;
-        }
-    } else {
-        let base_filename = if let Some(ref name) = server_filename {
-            name.clone()
-        } else {
-            utils::get_filename_from_url_or_default(target, "downloaded_file")
-        };
-        validate_filename(&base_filename)?;
-        tentative_path = PathBuf::from(base_filename);
-    }
-
-    let final_path: PathBuf = if tentative_path.is_absolute() {
-        tentative_path
-    } else {
-        let current_dir = std::env::current_dir()
-            .map_err(|e| format!("Failed to get current directory: {}", e))?;
-        current_dir.join(tentative_path)
-    };
+    let final_path = resolve_destination(target, options.output_path.clone(), server_filename)?;
 
     if let Some(parent_dir) = final_path.parent() {
         if !parent_dir.as_os_str().is_empty()
@@ -370,10 +593,21 @@ pub fn download(
         check_disk_space(&final_path, len)?;
     }
 
-    let mut dest = File::create(&final_path)
+    // Copying a file:// source onto itself would truncate it before it is read.
+    if let Some(src_path) = local::file_url_path(target)
+        && final_path.exists()
+        && src_path.canonicalize().ok() == final_path.canonicalize().ok()
+    {
+        return Err(format!(
+            "Source and destination are the same file: {}",
+            final_path.display()
+        )
+        .into());
+    }
+
+    let mut dest = store::open_output(&final_path, OutputMode::Create)
         .map_err(|e| format!("Failed to create file {}: {}", final_path.display(), e))?;
 
-    let response_content_length = response.content_length();
     let progress_bar_filename = final_path
         .file_name()
         .unwrap_or_default()
@@ -382,11 +616,10 @@ pub fn download(
     let progress = create_progress_bar(
         quiet_mode,
         progress_bar_filename,
-        response_content_length,
+        content_length,
         false,
     );
 
-    let mut source = response.take(response_content_length.unwrap_or(u64::MAX));
     let mut buffered_reader = progress.wrap_read(&mut source);
 
     // Stream data instead of reading all into memory
@@ -401,7 +634,7 @@ pub fn download(
         dest.write_all(&buffer[..n])?;
         downloaded += n as u64;
 
-        if let Some(total) = response_content_length {
+        if let Some(total) = content_length {
             if let Some(cb) = status_callback {
                 let percent = downloaded as f64 / total.max(1) as f64 * 100.0;
                 cb(format!(
@@ -426,9 +659,91 @@ pub fn download(
         verify_file_sha256(&final_path, Some(expected), status_callback)?;
     }
 
+    if !digests.is_empty() {
+        checksum::verify_digests(&final_path, &digests)?;
+        let names: Vec<&str> = digests.iter().map(|(a, _)| a.name()).collect();
+        let msg = format!("Digest header verified ({})", names.join(", "));
+        print(&msg, quiet_mode);
+        if let Some(cb) = status_callback {
+            cb(msg);
+        }
+    }
+
+    // Both branches above verified the expected hash if one was given.
+    if let Some(expected) = options.expected_sha256.as_deref()
+        && let Some(store) = optimizer.content_store()
+        && let Err(e) = store.insert(&final_path, &ChecksumAlgorithm::Sha256, expected)
+    {
+        print(&format!("Warning: could not add to content store: {}", e), quiet_mode);
+    }
+
     Ok(())
 }
 
+/// Work out where `target` is saved: `output_path` as a file or directory,
+/// otherwise the server-supplied or URL-derived filename in the current
+/// directory. Filenames are validated; nothing is created on disk.
+fn resolve_destination(
+    target: &str,
+    output_path: Option<String>,
+    server_filename: Option<String>,
+) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
+    let tentative_path: PathBuf;
+
+    if let Some(output_arg_str) = output_path {
+        let user_path = PathBuf::from(output_arg_str.clone());
+
+        let is_target_dir =
+            user_path.is_dir() || output_arg_str.ends_with(std::path::MAIN_SEPARATOR);
+
+        if is_target_dir {
+            let base_filename = utils::get_filename_from_url_or_default(target, "downloaded_file");
+            validate_filename(&base_filename)?;
+            tentative_path = user_path.join(base_filename);
+        } else {
+            if let Some(file_name_osstr) = user_path.file_name() {
+                if let Some(file_name_str) = file_name_osstr.to_str() {
+                    if file_name_str.is_empty() {
+                        return Err(format!(
+                            "Invalid output path, does not specify a file name: {}",
+                            user_path.display()
+                        )
+                        .into());
+                    }
+                    validate_filename(file_name_str)?;
+                } else {
+                    return Err("Output filename contains invalid characters (non-UTF-8)".into());
+                }
+            } else {
+                return Err(format!(
+                    "Invalid output path, does not specify a file name: {}",
+                    user_path.display()
+                )
+                .into());
+            }
+            tentative_path = user_path;
+        }
+    } else {
+        let base_filename = if let Some(ref name) = server_filename {
+            name.clone()
+        } else {
+            utils::get_filename_from_url_or_default(target, "downloaded_file")
+        };
+        validate_filename(&base_filename)?;
+        tentative_path = PathBuf::from(base_filename);
+    }
+
+    let final_path: PathBuf = if tentative_path.is_absolute() {
+        tentative_path
+    } else {
+        let current_dir = std::env::current_dir()
+            .map_err(|e| format!("Failed to get current directory: {}", e))?;
+        current_dir.join(tentative_path)
+    };
+
+    Ok(final_path)
+}
+
 /// Parse the `filename` or `filename*` from a `Content-Disposition` header value.
 ///
 /// Prefers the RFC 5987 `filename*` form (percent-encoded, with charset) over
@@ -470,7 +785,7 @@ pub fn parse_content_disposition_filename(header_value: &str) -> Option<String>
     plain
 }
 
-fn throttle_download(downloaded: u64, started_at: Instant, speed_limit: Option<u64>) {
+pub(crate) fn throttle_download(downloaded: u64, started_at: Instant, speed_limit: Option<u64>) {
     let Some(limit) = speed_limit else { return };
     if limit == 0 {
         return;
@@ -547,9 +862,11 @@ pub fn verify_file_sha256(
     if let Some(expected_hash) = expected_hash {
         let expected_hash = expected_hash.trim().to_ascii_lowercase();
         if hash != expected_hash {
-            return Err(
-                format!("SHA256 mismatch: expected {}, got {}", expected_hash, hash).into(),
-            );
+            return Err(Box::new(KgetError::ChecksumMismatch {
+                algorithm: "sha256".to_string(),
+                expected: expected_hash,
+                got: hash,
+            }));
         }
         send("SHA256 matches expected hash.");
     }
@@ -36,6 +36,9 @@ pub enum KgetError {
     /// A checksum sidecar file could not be fetched or parsed.
     SidecarError(String),
 
+    /// A detached signature was missing, malformed, or did not verify.
+    SignatureError(String),
+
     /// Catch-all for errors that don't fit a more specific variant.
     Other(String),
 }
@@ -57,6 +60,8 @@ impl fmt::Display for KgetError {
                 write!(f, "Resource not found: {url}"),
             KgetError::SidecarError(e) =>
                 write!(f, "Checksum sidecar error: {e}"),
+            KgetError::SignatureError(e) =>
+                write!(f, "Signature verification failed: {e}"),
             KgetError::Other(e) =>
                 write!(f, "{e}"),
         }
@@ -94,6 +99,10 @@ impl From<reqwest::Error> for KgetError {
 
 impl From<Box<dyn std::error::Error + Send + Sync>> for KgetError {
     fn from(e: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        let e = match e.downcast::<KgetError>() {
+            Ok(kget) => return *kget,
+            Err(e) => e,
+        };
         let msg = e.to_string();
         if msg.to_lowercase().contains("cancel") {
             KgetError::Cancelled
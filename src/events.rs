@@ -52,3 +52,13 @@ pub enum DownloadEvent {
     /// The download failed.  Contains the error message.
     Error(String),
 }
+
+/// A [`DownloadEvent`] about one file of a multi-file download, such as the
+/// files of a Metalink manifest run by
+/// [`MetalinkBuilder::spawn`](crate::MetalinkBuilder::spawn).
+#[derive(Debug)]
+pub struct FileEvent {
+    /// Name of the file, as listed in the manifest.
+    pub file: String,
+    pub event: DownloadEvent,
+}
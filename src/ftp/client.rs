@@ -1,28 +1,62 @@
 //! FTP client implementation.
 
-use crate::config::ProxyConfig;
+use super::is_tree_url;
+use super::list::{self, EntryKind, RemoteEntry};
+use crate::config::{FtpConfig, ProxyConfig};
 use crate::optimization::Optimizer;
 use crate::progress::create_progress_bar;
-use crate::utils::print;
+use crate::segments::{MAX_RETRIES, SegmentLog, calculate_segments};
+use crate::store::{self, OutputMode};
+use crate::utils::{glob_match, is_safe_file_name, print, split_tree_path};
+use indicatif::ProgressBar;
 use std::error::Error;
-use std::io::Write;
-use suppaftp::FtpStream;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use suppaftp::native_tls::{Certificate, TlsConnector};
+use suppaftp::{FtpError, Mode, NativeTlsConnector, NativeTlsFtpStream, Status};
 use url::Url;
 
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::FileExt;
+#[cfg(target_family = "windows")]
+use std::os::windows::fs::FileExt;
+
+/// Smallest segment worth its own control connection (1 MB)
+const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+/// How long active mode waits for the server to open the data connection
+const ACTIVE_ACCEPT_TIMEOUT: Duration = Duration::from_secs(15);
+
 /// FTP file downloader with progress tracking.
 ///
 /// Supports:
 /// - Anonymous FTP (use "anonymous" as username)
 /// - Authenticated FTP with username/password in URL
 /// - Binary transfer mode (safe for all file types)
+/// - Resuming partial files with `REST`
+/// - Segmented downloads over parallel control connections, resumable
+///   through a `<output>.kget-ftp` record of the finished ranges
+/// - FTPS: implicit TLS for `ftps://` URLs, explicit `AUTH TLS` for `ftp://`
+/// - Recursive directory downloads and glob patterns
+/// - Passive (`PASV`/`EPSV`) and active (`PORT`/`EPRT`) data connections,
+///   falling back to the next mode when one fails
 /// - SOCKS5 proxy connections
 ///
 /// # URL Format
 ///
 /// ```text
 /// ftp://[user[:password]@]host[:port]/path/to/file
+/// ftps://[user[:password]@]host[:port]/path/to/file   (implicit TLS, port 990)
+/// ftp://host/pub/dir/                                 (whole directory tree)
+/// ftp://host/pub/*.iso                                (files matching a glob)
 /// ```
 ///
+/// For directories and globs, `output_path` is the local directory the
+/// remote tree is mirrored into.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -56,6 +90,29 @@ pub struct FtpDownloader {
     proxy: ProxyConfig,
     #[allow(dead_code)]
     optimizer: Optimizer,
+    config: FtpConfig,
+    max_depth: Option<usize>,
+    progress_callback: Option<Arc<dyn Fn(f32) + Send + Sync>>,
+    status_callback: Option<Arc<dyn Fn(String) + Send + Sync>>,
+    /// Whether the control connection goes to an IPv6 address
+    ipv6: AtomicBool,
+    /// Data connection modes given up on so far (see [`Self::data_modes`])
+    failed_modes: AtomicUsize,
+}
+
+/// A file found while walking a remote directory.
+struct TreeFile {
+    remote: String,
+    relative: PathBuf,
+    size: u64,
+}
+
+/// State of a directory walk: the control connection, whether the server
+/// supports `MLSD`, and the files found so far.
+struct Walk<'a> {
+    ftp: &'a mut NativeTlsFtpStream,
+    mlsd: bool,
+    files: Vec<TreeFile>,
 }
 
 impl FtpDownloader {
@@ -81,24 +138,425 @@ impl FtpDownloader {
             quiet_mode,
             proxy,
             optimizer,
+            config: FtpConfig::default(),
+            max_depth: None,
+            progress_callback: None,
+            status_callback: None,
+            ipv6: AtomicBool::new(false),
+            failed_modes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Apply FTP settings (default port, passive/active mode, parallel
+    /// connections, TLS, ...).
+    pub fn set_config(&mut self, config: FtpConfig) {
+        self.config = config;
+    }
+
+    /// Limit how many directory levels below the URL a recursive download
+    /// descends (`Some(0)` = only the files directly inside it).
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Set a callback for download progress of the current file (0.0 to 1.0).
+    ///
+    /// Recursive downloads report each file from 0.0 to 1.0 in turn; the
+    /// status callback announces which file is next.
+    pub fn set_progress_callback(&mut self, callback: impl Fn(f32) + Send + Sync + 'static) {
+        self.progress_callback = Some(Arc::new(callback));
+    }
+
+    /// Set a callback for status messages.
+    pub fn set_status_callback(&mut self, callback: impl Fn(String) + Send + Sync + 'static) {
+        self.status_callback = Some(Arc::new(callback));
+    }
+
+    fn send_status(&self, msg: &str) {
+        if let Some(cb) = &self.status_callback {
+            cb(msg.to_string());
         }
+        print(msg, self.quiet_mode);
     }
 
+    fn report_progress(&self, progress: &ProgressBar) {
+        if let Some(cb) = &self.progress_callback {
+            let len = progress.length().unwrap_or(0).max(1);
+            cb(progress.position() as f32 / len as f32);
+        }
+    }
+
+    /// Download the file, directory tree or glob matches.
+    ///
+    /// An existing partial file at the output path is resumed with `REST`
+    /// instead of being downloaded again. With
+    /// [`FtpConfig::connections`] above 1, the remaining bytes are split
+    /// into segments fetched over separate control connections.
+    ///
+    /// Data connections start in the mode [`FtpConfig::passive_mode`] asks
+    /// for. When the server or a firewall refuses it, the download starts
+    /// over in the next mode (see [`Self::data_modes`]), which resumes the
+    /// work already done; the switch is reported as a status message.
     pub fn download(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        loop {
+            match self.download_once() {
+                Err(e) if is_data_connection_error(&*e) && self.fall_back(&*e) => {}
+                result => return result,
+            }
+        }
+    }
+
+    fn download_once(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
         let url = Url::parse(&self.url)?;
+        let path = urlencoding::decode(url.path())?.into_owned();
+        let output = Path::new(&self.output_path);
+
+        let mut ftp = self.connect(&url, self.quiet_mode)?;
+        let result = if is_tree_url(&self.url) {
+            self.download_tree(&mut ftp, &url, &path, output)
+        } else {
+            match ftp.size(&path) {
+                Ok(size) => self
+                    .download_file(&mut ftp, &url, &path, output, size as u64)
+                    .map(|()| print("Download completed successfully!", self.quiet_mode)),
+                // No size: it may be a directory given without the trailing slash.
+                Err(e) => match ftp.cwd(&path) {
+                    Ok(()) => self.download_tree(&mut ftp, &url, &path, output),
+                    Err(_) => Err(e.into()),
+                },
+            }
+        };
+        let _ = ftp.quit();
+        result
+    }
+
+    /// Data connection modes to try, in order of preference.
+    ///
+    /// Passive mode uses `PASV` on IPv4, where it is most widely supported,
+    /// and `EPSV` on IPv6, where `PASV` cannot describe the address. Active
+    /// mode (`PORT`, or `EPRT` on IPv6) comes first when `passive_mode` is
+    /// off, for servers behind firewalls that block inbound data ports.
+    fn data_modes(&self, ipv6: bool) -> &'static [Mode] {
+        match (self.config.passive_mode, ipv6) {
+            (true, false) => &[Mode::Passive, Mode::ExtendedPassive, Mode::Active],
+            (true, true) => &[Mode::ExtendedPassive, Mode::Active],
+            (false, false) => &[Mode::Active, Mode::Passive, Mode::ExtendedPassive],
+            (false, true) => &[Mode::Active, Mode::ExtendedPassive],
+        }
+    }
+
+    fn data_mode(&self, ipv6: bool) -> Mode {
+        let modes = self.data_modes(ipv6);
+        let failed = self.failed_modes.load(Ordering::SeqCst);
+        modes[failed.min(modes.len() - 1)]
+    }
+
+    /// Give up on the current data connection mode; returns `false` when
+    /// there is none left to try.
+    fn fall_back(&self, error: &(dyn Error + 'static)) -> bool {
+        let modes = self.data_modes(self.ipv6.load(Ordering::SeqCst));
+        let failed = self.failed_modes.load(Ordering::SeqCst);
+        if failed + 1 >= modes.len() {
+            return false;
+        }
+        self.failed_modes.store(failed + 1, Ordering::SeqCst);
+        self.send_status(&format!(
+            "Data connection in {} failed ({}); falling back to {}",
+            mode_name(modes[failed]),
+            error,
+            mode_name(modes[failed + 1])
+        ));
+        true
+    }
+
+    /// Download one remote file to `output`, resuming a partial copy.
+    fn download_file(
+        &self,
+        ftp: &mut NativeTlsFtpStream,
+        url: &Url,
+        path: &str,
+        output: &Path,
+        size: u64,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // An unfinished segmented download: its output is already full size.
+        if let Some(log) = SegmentLog::load(output, "ftp", size)? {
+            let progress = create_progress_bar(
+                self.quiet_mode,
+                format!("Downloading {}", path),
+                Some(size),
+                false,
+            );
+            progress.set_position(log.completed());
+            let segments = log.missing();
+            if !segments.is_empty() {
+                print(
+                    &format!(
+                        "Resuming {} unfinished segment(s) ({} of {} bytes done)",
+                        segments.len(),
+                        log.completed(),
+                        size
+                    ),
+                    self.quiet_mode,
+                );
+                self.download_segments(url, path, output, &segments, size, &progress, &log)?;
+            }
+            log.finish()?;
+            progress.finish();
+            return Ok(());
+        }
+
+        let existing_size = match fs::metadata(output) {
+            Ok(meta) if meta.len() <= size => meta.len(),
+            Ok(_) => {
+                print(
+                    "Existing file is larger than remote; starting over",
+                    self.quiet_mode,
+                );
+                0
+            }
+            Err(_) => 0,
+        };
+        if existing_size == size && size > 0 {
+            print("File already fully downloaded", self.quiet_mode);
+            if let Some(cb) = &self.progress_callback {
+                cb(1.0);
+            }
+            return Ok(());
+        }
+
+        let progress = create_progress_bar(
+            self.quiet_mode,
+            format!("Downloading {}", path),
+            Some(size),
+            false,
+        );
+        progress.set_position(existing_size);
+
+        let segments = calculate_segments(
+            existing_size,
+            size,
+            self.config.connections,
+            MIN_SEGMENT_SIZE,
+        );
+        if segments.len() > 1 {
+            let log = SegmentLog::create(output, "ftp", size, existing_size)?;
+            self.download_segments(url, path, output, &segments, size, &progress, &log)?;
+            log.finish()?;
+        } else {
+            let mut file = if existing_size > 0 {
+                print(
+                    &format!("Resuming download from byte: {}", existing_size),
+                    self.quiet_mode,
+                );
+                ftp.resume_transfer(existing_size as usize)?;
+                store::open_output(output, OutputMode::Append)?
+            } else {
+                store::open_output(output, OutputMode::Create)?
+            };
+
+            let mut downloaded = existing_size;
+            ftp.retr(path, |reader| {
+                let mut buffer = vec![0; 8192];
+                loop {
+                    match reader.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            file.write_all(&buffer[..n])
+                                .map_err(suppaftp::FtpError::ConnectionError)?;
+                            downloaded += n as u64;
+                            progress.set_position(downloaded);
+                            self.report_progress(&progress);
+                        }
+                        Err(e) => return Err(suppaftp::FtpError::ConnectionError(e)),
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        progress.finish();
+        Ok(())
+    }
+
+    /// Mirror a remote directory, or the entries matching a glob, into `dest`.
+    fn download_tree(
+        &self,
+        ftp: &mut NativeTlsFtpStream,
+        url: &Url,
+        path: &str,
+        dest: &Path,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let (dir, pattern) = split_tree_path(path);
+
+        // Only use MLSD when FEAT advertises it: after a rejected data command
+        // suppaftp refuses to open another one, so trying it blindly would
+        // break the LIST fallback.
+        let mlsd = ftp
+            .feat()
+            .is_ok_and(|features| features.keys().any(|k| k.eq_ignore_ascii_case("MLST")));
+        if !mlsd {
+            self.send_status("Server does not support MLSD; parsing LIST output");
+        }
+        let mut walk = Walk {
+            ftp,
+            mlsd,
+            files: Vec::new(),
+        };
+        self.collect_files(&mut walk, dir, Path::new(""), pattern, 0)?;
+        let Walk { ftp, files, .. } = walk;
+
+        if files.is_empty() {
+            return Err(format!("No files found at {}", self.url).into());
+        }
+        self.send_status(&format!("Found {} file(s) under {}", files.len(), dir));
+
+        let total = files.len();
+        let mut failed = 0;
+        for (i, file) in files.iter().enumerate() {
+            self.send_status(&format!(
+                "[{}/{}] {} ({} bytes)",
+                i + 1,
+                total,
+                file.remote,
+                file.size
+            ));
+            let local = dest.join(&file.relative);
+            let result = match local.parent() {
+                Some(parent) => fs::create_dir_all(parent).map_err(Into::into),
+                None => Ok(()),
+            }
+            .and_then(|()| self.download_file(ftp, url, &file.remote, &local, file.size));
+            if let Err(e) = result {
+                eprintln!("[FAIL] {} — {}", file.remote, e);
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(format!("{} of {} file(s) failed to download", failed, total).into());
+        }
+        print(
+            &format!("Downloaded {} file(s) into {}", total, dest.display()),
+            self.quiet_mode,
+        );
+        Ok(())
+    }
+
+    /// Walk `dir`, collecting files (optionally only names matching
+    /// `pattern` at this level) and descending into subdirectories.
+    fn collect_files(
+        &self,
+        walk: &mut Walk,
+        dir: &str,
+        relative: &Path,
+        pattern: Option<&str>,
+        depth: usize,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for entry in self.list_dir(walk, dir)? {
+            if pattern.is_some_and(|p| !glob_match(p, &entry.name)) {
+                continue;
+            }
+            let remote = format!("{}/{}", dir.trim_end_matches('/'), entry.name);
+            if !is_safe_file_name(&entry.name) {
+                self.send_status(&format!(
+                    "Skipping {}: unsafe name for a local path",
+                    remote
+                ));
+                continue;
+            }
+            let local = relative.join(&entry.name);
+            match entry.kind {
+                EntryKind::File => walk.files.push(TreeFile {
+                    remote,
+                    relative: local,
+                    size: entry.size,
+                }),
+                EntryKind::Dir => {
+                    if self.max_depth.is_some_and(|max| depth >= max) {
+                        self.send_status(&format!("Skipping {}/: depth limit reached", remote));
+                        continue;
+                    }
+                    self.collect_files(walk, &remote, &local, None, depth + 1)?;
+                }
+                // Links to files answer SIZE; links to directories are not followed.
+                EntryKind::Link => match walk.ftp.size(&remote) {
+                    Ok(size) => walk.files.push(TreeFile {
+                        remote,
+                        relative: local,
+                        size: size as u64,
+                    }),
+                    Err(_) => {
+                        self.send_status(&format!("Skipping symlink {}", remote));
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// List a directory with `MLSD`, or by parsing `LIST` output when the
+    /// server does not support it.
+    fn list_dir(
+        &self,
+        walk: &mut Walk,
+        dir: &str,
+    ) -> Result<Vec<RemoteEntry>, Box<dyn Error + Send + Sync>> {
+        if walk.mlsd {
+            let lines = walk.ftp.mlsd(Some(dir))?;
+            return Ok(lines
+                .iter()
+                .filter_map(|l| list::parse_mlsd_line(l))
+                .collect());
+        }
+        let lines = walk.ftp.list(Some(dir))?;
+        Ok(lines
+            .iter()
+            .filter_map(|l| list::parse_list_line(l))
+            .collect())
+    }
+
+    /// Open a control connection, secure it if configured, log in and
+    /// switch to binary mode.
+    fn connect(
+        &self,
+        url: &Url,
+        quiet: bool,
+    ) -> Result<NativeTlsFtpStream, Box<dyn Error + Send + Sync>> {
         let host = url.host_str().ok_or("Invalid host")?;
-        let port = url.port().unwrap_or(21);
-        let path = url.path();
+        let implicit_tls = url.scheme() == "ftps";
+        let port = url.port().unwrap_or(if implicit_tls {
+            990
+        } else {
+            self.config.default_port
+        });
 
         print(
             &format!("Connecting to FTP server {}:{}...", host, port),
-            self.quiet_mode,
+            quiet,
         );
 
-        let mut ftp = if self.proxy.enabled {
-            self.connect_via_proxy(host, port)?
+        let mut ftp = if implicit_tls {
+            if self.proxy.enabled {
+                return Err(
+                    "Implicit FTPS through a proxy is not supported; use ftp:// with AUTH TLS"
+                        .into(),
+                );
+            }
+            NativeTlsFtpStream::connect_secure_implicit((host, port), self.tls_connector()?, host)
+                .map_err(|e| connect_error(host, port, e))?
         } else {
-            FtpStream::connect((host, port))?
+            let ftp = if self.proxy.enabled {
+                self.connect_via_proxy(host, port)?
+            } else {
+                NativeTlsFtpStream::connect((host, port))
+                    .map_err(|e| connect_error(host, port, e))?
+            };
+            if self.config.explicit_tls {
+                print("Securing connection with AUTH TLS...", quiet);
+                ftp.into_secure(self.tls_connector()?, host)?
+            } else {
+                ftp
+            }
         };
 
         // url.username() returns "" (empty string) when the URL has no user — fall back to "anonymous"
@@ -109,44 +567,176 @@ impl FtpDownloader {
         };
         let password = url.password().unwrap_or("anonymous");
 
-        print(&format!("Logging in as {}...", username), self.quiet_mode);
+        print(&format!("Logging in as {}...", username), quiet);
         ftp.login(username, password)?;
 
         ftp.transfer_type(suppaftp::types::FileType::Binary)?;
 
-        let size = ftp.size(path)? as u64;
+        let ipv6 = ftp.get_ref().peer_addr().is_ok_and(|addr| addr.is_ipv6());
+        self.ipv6.store(ipv6, Ordering::SeqCst);
+        let mode = self.data_mode(ipv6);
+        print(&format!("Using {}", mode_name(mode)), quiet);
+        let mut ftp = match mode {
+            Mode::Active => ftp.active_mode(ACTIVE_ACCEPT_TIMEOUT),
+            mode => {
+                ftp.set_mode(mode);
+                ftp
+            }
+        };
+        // Like curl, connect to the control connection's address instead of
+        // the one in the PASV reply, which is often private behind NAT.
+        ftp.set_passive_nat_workaround(true);
 
-        let progress = create_progress_bar(
+        Ok(ftp)
+    }
+
+    /// TLS settings for FTPS control and data connections.
+    fn tls_connector(&self) -> Result<NativeTlsConnector, Box<dyn Error + Send + Sync>> {
+        let mut builder = TlsConnector::builder();
+        if !self.config.verify_certificates {
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+        if let Some(path) = &self.config.ca_cert {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("Failed to read CA certificate {}: {}", path, e))?;
+            builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+        Ok(NativeTlsConnector::from(builder.build()?))
+    }
+
+    /// Fetch every segment over its own control connection into a
+    /// preallocated file, recording finished ranges in `log`.
+    #[allow(clippy::too_many_arguments)]
+    fn download_segments(
+        &self,
+        url: &Url,
+        path: &str,
+        output: &Path,
+        segments: &[(u64, u64)],
+        total: u64,
+        progress: &ProgressBar,
+        log: &SegmentLog,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let file = store::open_output(output, OutputMode::Update)?;
+        file.set_len(total)?;
+        print(
+            &format!(
+                "Downloading in {} segments over parallel connections",
+                segments.len()
+            ),
             self.quiet_mode,
-            format!("Downloading {}", path),
-            Some(size),
-            false,
         );
 
-        let mut file = std::fs::File::create(&self.output_path)?;
-
-        // Download file
-        let mut downloaded = 0;
-        ftp.retr(path, |reader| {
-            let mut buffer = vec![0; 8192];
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        file.write_all(&buffer[..n])
-                            .map_err(|e| suppaftp::FtpError::ConnectionError(e))?;
-                        downloaded += n;
-                        progress.set_position(downloaded as u64);
-                    }
-                    Err(e) => return Err(suppaftp::FtpError::ConnectionError(e)),
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = segments
+                .iter()
+                .map(|&(start, end)| {
+                    let file = &file;
+                    scope.spawn(move || {
+                        self.fetch_segment(url, path, file, start, end, total, progress, log)
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|_| Err("FTP segment worker panicked".into()))
+                })
+                .collect::<Result<Vec<()>, _>>()
+        })?;
+
+        Ok(())
+    }
+
+    /// Fetch `[start, end)` starting with `REST`, reconnecting from the last
+    /// written byte when the transfer drops. What was written is recorded
+    /// in `log` after every attempt, including the failed ones.
+    #[allow(clippy::too_many_arguments)]
+    fn fetch_segment(
+        &self,
+        url: &Url,
+        path: &str,
+        file: &File,
+        start: u64,
+        end: u64,
+        total: u64,
+        progress: &ProgressBar,
+        log: &SegmentLog,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut position = start;
+        let mut attempt = 0;
+        loop {
+            let result = self.fetch_range(url, path, file, &mut position, end, total, progress);
+            log.mark(start, position)?;
+            match result {
+                Ok(()) => return Ok(()),
+                // Retrying in the same mode will not help; let download() switch modes.
+                Err(e) if is_data_connection_error(&*e) => return Err(e),
+                Err(e) if attempt + 1 < MAX_RETRIES => {
+                    attempt += 1;
+                    print(
+                        &format!(
+                            "Segment {}-{} failed at byte {} ({}), retrying...",
+                            start, end, position, e
+                        ),
+                        self.quiet_mode,
+                    );
+                }
+                Err(e) => {
+                    return Err(format!(
+                        "Failed to download segment {}-{} after retries: {}",
+                        start, end, e
+                    )
+                    .into());
                 }
             }
-            Ok(())
-        })?;
+        }
+    }
 
-        progress.finish();
-        print("Download completed successfully!", self.quiet_mode);
+    #[allow(clippy::too_many_arguments)]
+    fn fetch_range(
+        &self,
+        url: &Url,
+        path: &str,
+        file: &File,
+        position: &mut u64,
+        end: u64,
+        total: u64,
+        progress: &ProgressBar,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut ftp = self.connect(url, true)?;
+        ftp.resume_transfer(*position as usize)?;
+        let mut stream = ftp.retr_as_stream(path)?;
+
+        let mut buffer = vec![0; 65536];
+        while *position < end {
+            let limit = (end - *position).min(buffer.len() as u64) as usize;
+            let n = stream.read(&mut buffer[..limit])?;
+            if n == 0 {
+                return Err("connection closed early".into());
+            }
+
+            #[cfg(target_family = "unix")]
+            file.write_at(&buffer[..n], *position)?;
+
+            #[cfg(target_family = "windows")]
+            file.seek_write(&buffer[..n], *position)?;
+
+            *position += n as u64;
+            progress.inc(n as u64);
+            self.report_progress(progress);
+        }
 
+        if end == total {
+            ftp.finalize_retr_stream(stream)?;
+            let _ = ftp.quit();
+        }
+        // Otherwise the server is still sending the rest of the file; dropping
+        // the data and control connections aborts it.
         Ok(())
     }
 
@@ -154,7 +744,7 @@ impl FtpDownloader {
         &self,
         host: &str,
         port: u16,
-    ) -> Result<FtpStream, Box<dyn Error + Send + Sync>> {
+    ) -> Result<NativeTlsFtpStream, Box<dyn Error + Send + Sync>> {
         match self.proxy.proxy_type {
             crate::config::ProxyType::Http | crate::config::ProxyType::Https => {
                 Err("HTTP/HTTPS proxy not supported for FTP".into())
@@ -168,7 +758,9 @@ impl FtpDownloader {
                     let stream =
                         socks::Socks5Stream::connect((proxy_host, proxy_port), (host, port))?;
 
-                    Ok(FtpStream::connect_with_stream(stream.into_inner())?)
+                    Ok(NativeTlsFtpStream::connect_with_stream(
+                        stream.into_inner(),
+                    )?)
                 } else {
                     Err("Proxy URL not configured".into())
                 }
@@ -176,3 +768,48 @@ impl FtpDownloader {
         }
     }
 }
+
+/// Human-readable name of a data connection mode.
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Passive => "passive mode (PASV)",
+        Mode::ExtendedPassive => "extended passive mode (EPSV)",
+        Mode::Active => "active mode (PORT/EPRT)",
+    }
+}
+
+/// Errors opening the control connection are reported as plain messages so
+/// they are never mistaken for data connection failures.
+fn connect_error(host: &str, port: u16, error: FtpError) -> Box<dyn Error + Send + Sync> {
+    format!(
+        "Failed to connect to FTP server {}:{}: {}",
+        host, port, error
+    )
+    .into()
+}
+
+/// Returns `true` if the error means the data connection could not be set
+/// up in the current mode: the mode command was refused, the server could
+/// not open it (425), or connecting/accepting timed out or was refused.
+fn is_data_connection_error(error: &(dyn Error + 'static)) -> bool {
+    match error.downcast_ref::<FtpError>() {
+        Some(FtpError::UnexpectedResponse(response)) => matches!(
+            response.status,
+            Status::CannotOpenDataConnection
+                | Status::BadCommand
+                | Status::BadArguments
+                | Status::NotImplemented
+                | Status::NotImplementedParameter
+        ),
+        Some(FtpError::ConnectionError(e)) => matches!(
+            e.kind(),
+            std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::WouldBlock
+                | std::io::ErrorKind::AddrNotAvailable
+                | std::io::ErrorKind::HostUnreachable
+                | std::io::ErrorKind::NetworkUnreachable
+        ),
+        _ => false,
+    }
+}
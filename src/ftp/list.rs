@@ -0,0 +1,110 @@
+//! Directory listings: MLSD facts and Unix/DOS `LIST` lines.
+
+use suppaftp::list::{File, ListParser};
+
+/// What a listing entry points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    File,
+    Dir,
+    Link,
+}
+
+/// One entry of a remote directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RemoteEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub size: u64,
+}
+
+/// Parse a line of `MLSD` output.
+///
+/// The `cdir`/`pdir` entries describing the listed directory and its parent
+/// are dropped, as are entry types other than file, dir and link.
+pub(crate) fn parse_mlsd_line(line: &str) -> Option<RemoteEntry> {
+    let facts = line.split_once(' ').map_or(line, |(facts, _)| facts);
+    let facts = facts.to_ascii_lowercase();
+    if facts.contains("type=cdir;") || facts.contains("type=pdir;") {
+        return None;
+    }
+    ListParser::parse_mlsd(line).ok().and_then(to_entry)
+}
+
+/// Parse a line of `LIST` output in Unix `ls -l` or DOS/IIS format.
+///
+/// Lines that are neither (such as `total 42`) yield `None`.
+pub(crate) fn parse_list_line(line: &str) -> Option<RemoteEntry> {
+    ListParser::parse_posix(line)
+        .or_else(|_| ListParser::parse_dos(line))
+        .ok()
+        .and_then(to_entry)
+}
+
+fn to_entry(file: File) -> Option<RemoteEntry> {
+    let name = file.name();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    let kind = if file.is_directory() {
+        EntryKind::Dir
+    } else if file.is_symlink() {
+        EntryKind::Link
+    } else {
+        EntryKind::File
+    };
+    Some(RemoteEntry {
+        name: name.to_string(),
+        kind,
+        size: file.size() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mlsd_line() {
+        let entry =
+            parse_mlsd_line("type=file;size=1048576;modify=20240101120000; debian.iso").unwrap();
+        assert_eq!(entry.name, "debian.iso");
+        assert_eq!(entry.kind, EntryKind::File);
+        assert_eq!(entry.size, 1048576);
+
+        let dir = parse_mlsd_line("type=dir;modify=20240101120000; images").unwrap();
+        assert_eq!(dir.kind, EntryKind::Dir);
+
+        assert!(parse_mlsd_line("type=cdir;modify=20240101120000; pub").is_none());
+        assert!(parse_mlsd_line("type=pdir;modify=20240101120000; ..").is_none());
+    }
+
+    #[test]
+    fn test_parse_unix_and_dos_list_lines() {
+        let file =
+            parse_list_line("-rw-r--r--    1 ftp      ftp        4096 Jan 05  2024 notes.txt")
+                .unwrap();
+        assert_eq!(file.name, "notes.txt");
+        assert_eq!(file.kind, EntryKind::File);
+        assert_eq!(file.size, 4096);
+
+        let dir = parse_list_line("drwxr-xr-x    2 ftp      ftp        4096 Mar 10 12:30 releases")
+            .unwrap();
+        assert_eq!(dir.kind, EntryKind::Dir);
+
+        let link = parse_list_line(
+            "lrwxrwxrwx    1 ftp      ftp          11 Mar 10 12:30 latest -> v2.0.tar.gz",
+        )
+        .unwrap();
+        assert_eq!(link.name, "latest");
+        assert_eq!(link.kind, EntryKind::Link);
+
+        let dos = parse_list_line("10-19-24  03:15PM               123456 setup.exe").unwrap();
+        assert_eq!(dos.name, "setup.exe");
+        assert_eq!(dos.size, 123456);
+        let dos_dir = parse_list_line("10-19-24  03:15PM       <DIR>          Drivers").unwrap();
+        assert_eq!(dos_dir.kind, EntryKind::Dir);
+
+        assert!(parse_list_line("total 42").is_none());
+    }
+}
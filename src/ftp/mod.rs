@@ -4,6 +4,13 @@
 //! - Anonymous and authenticated access
 //! - Binary transfer mode for all file types
 //! - Progress tracking
+//! - Resuming partial files with `REST`
+//! - Optional segmented downloads over parallel control connections
+//! - FTPS: implicit TLS (`ftps://`) and explicit `AUTH TLS`
+//! - Recursive directory downloads (MLSD, or Unix/DOS `LIST` output) and
+//!   glob patterns such as `ftp://host/pub/*.iso`
+//! - Passive (`PASV`/`EPSV`) or active (`PORT`/`EPRT`) data connections,
+//!   falling back to the next mode when one fails
 //! - SOCKS5 proxy support
 //!
 //! # Example
@@ -24,5 +31,7 @@
 //! ```
 
 mod client;
+mod list;
 
+pub use crate::utils::is_tree_url;
 pub use client::FtpDownloader;
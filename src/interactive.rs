@@ -6,6 +6,7 @@ use kget::ftp::FtpDownloader;
 use kget::metalink;
 use kget::optimization::Optimizer;
 use kget::queue::{DownloadHistory, EntryStatus, HistoryEntry};
+use kget::scp::ScpDownloader;
 use kget::sftp::SftpDownloader;
 use kget::utils;
 use rustyline::DefaultEditor;
@@ -127,12 +128,13 @@ fn run_download(args: DownloadArgs, config: &Config) -> Result<(), Box<dyn Error
 
     if is_metalink {
         let output_dir = args.output.as_deref().unwrap_or(".");
-        let result = metalink::download_metalink(
+        let result = metalink::download_metalink_with(
             &args.url,
             output_dir,
             args.quiet,
             config.proxy.clone(),
             optimizer,
+            &config.metalink,
         );
         // Record to history
         let mut history = DownloadHistory::load();
@@ -163,14 +165,32 @@ fn run_download(args: DownloadArgs, config: &Config) -> Result<(), Box<dyn Error
                 optimizer,
                 kget::torrent::TorrentCallbacks::default(),
             )
-        } else if args.ftp {
-            let output = utils::resolve_output_path(args.output, &args.url, "ftp_output");
-            FtpDownloader::new(args.url, output, args.quiet, config.proxy.clone(), optimizer)
-                .download()
+        } else if args.ftp || args.url.starts_with("ftps://") {
+            let output = if kget::ftp::is_tree_url(&args.url) {
+                args.output.unwrap_or_else(|| ".".to_string())
+            } else {
+                utils::resolve_output_path(args.output, &args.url, "ftp_output")
+            };
+            let mut downloader =
+                FtpDownloader::new(args.url, output, args.quiet, config.proxy.clone(), optimizer);
+            downloader.set_config(config.ftp.clone());
+            downloader.download()
         } else if args.sftp {
-            let output = utils::resolve_output_path(args.output, &args.url, "sftp_output");
-            SftpDownloader::new(args.url, output, args.quiet, config.proxy.clone(), optimizer)
-                .download()
+            let output = if kget::sftp::is_tree_url(&args.url) {
+                args.output.unwrap_or_else(|| ".".to_string())
+            } else {
+                utils::resolve_output_path(args.output, &args.url, "sftp_output")
+            };
+            let mut downloader =
+                SftpDownloader::new(args.url, output, args.quiet, config.proxy.clone(), optimizer);
+            downloader.set_config(config.sftp.clone());
+            downloader.download()
+        } else if kget::is_scp_url(&args.url) {
+            let output = utils::resolve_output_path(args.output, &args.url, "scp_output");
+            let mut downloader =
+                ScpDownloader::new(args.url, output, args.quiet, config.proxy.clone(), optimizer);
+            downloader.set_config(config.sftp.clone());
+            downloader.download()
         } else if args.advanced {
             let output = utils::resolve_output_path(args.output, &args.url, "download");
             AdvancedDownloader::new(
@@ -327,8 +347,9 @@ Commands
       -o, --output <path>    Save to this file or directory
       -q, --quiet            No progress output
           --sha256 <hash>    Verify SHA-256 after download
-          --ftp              Use FTP protocol  (ftp://[user:pass@]host/path)
+          --ftp              Use FTP protocol  (ftp://[user:pass@]host/path, ftps:// for TLS)
           --sftp             Use SFTP protocol (sftp://[user[:pass]@]host/path)
+                             scp://[user[:pass]@]host/path URLs use SCP automatically
           --torrent          Force torrent/magnet handling
           --metalink         Treat source as a Metalink manifest (.meta4/.metalink)
 
@@ -357,6 +378,7 @@ Examples
   download --sha256 abc123 https://example.com/checksummed.tar.gz
   download --ftp ftp://ftp.gnu.org/gnu/emacs/emacs-28.2.tar.gz
   download --sftp sftp://user@server.com/backups/db.sql.gz
+  download scp://admin@switch01/cfg/running-config
   download magnet:?xt=urn:btih:...
   download --metalink ubuntu.meta4
   download --metalink https://releases.ubuntu.com/ubuntu.meta4
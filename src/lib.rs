@@ -3,9 +3,12 @@
 //! `kget` provides robust downloading capabilities for modern applications:
 //!
 //! - **HTTP/HTTPS downloads** with parallel connections (up to 32x speed)
-//! - **FTP/SFTP support** for legacy and secure file transfers  
+//! - **FTP/SFTP/SCP support** for legacy and secure file transfers  
+//! - **S3-compatible storage** (`s3://`) with SigV4 signing, including MinIO
 //! - **BitTorrent** via magnet links with native client (requires `torrent-native` feature)
 //! - **ISO verification** with automatic SHA-256 integrity checking
+//! - **Signature verification** of OpenPGP and minisign detached signatures
+//! - **Content-addressed store** that hardlinks verified files instead of re-fetching them
 //! - **Auto-optimization** based on file type and network conditions
 //!
 //! ## Quick Start
@@ -73,8 +76,10 @@
 pub mod advanced_download;
 pub mod app;
 pub mod builder;
+pub mod cache;
 pub mod checksum;
 pub mod config;
+pub mod delta;
 pub mod download;
 pub mod error;
 pub mod events;
@@ -82,10 +87,16 @@ pub mod metalink;
 pub mod optimization;
 pub mod progress;
 pub mod queue;
+mod segments;
+pub mod signature;
+pub mod store;
 pub mod utils;
 
 // Protocol modules
 pub mod ftp;
+pub mod local;
+pub mod s3;
+pub mod scp;
 pub mod sftp;
 pub mod torrent;
 pub mod webdav;
@@ -107,17 +118,20 @@ pub use torrent::{TorrentCallbacks, download_magnet};
 pub use utils::{auto_extract, get_filename_from_url_or_default, is_extractable, print, resolve_output_path};
 
 // Re-exports: Protocol helpers
+pub use local::is_local_url;
+pub use s3::is_s3_url;
+pub use scp::is_scp_url;
 pub use webdav::is_webdav_url;
 pub use ytdlp::{is_video_url, ytdlp_available, ytdlp_binary};
 
 // Re-exports: High-level builder API (v1.7.0+)
 pub use builder::{
     Backoff, BatchBuilder, BatchResult, ComputedChecksums, DownloadBuilder,
-    DownloadResult, RetryConfig,
+    DownloadResult, MetalinkBuilder, MetalinkResult, RetryConfig,
 };
 pub use checksum::ChecksumAlgorithm;
 pub use error::KgetError;
-pub use events::DownloadEvent;
+pub use events::{DownloadEvent, FileEvent};
 
 /// Create a [`DownloadBuilder`] for a single URL — the recommended API entry point.
 ///
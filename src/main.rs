@@ -2,9 +2,10 @@
 
 #[cfg(not(feature = "gui"))]
 use clap::CommandFactory;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use serde_json::json;
 use std::error::Error;
+use std::path::PathBuf;
 
 #[cfg(feature = "gui")]
 use std::sync::mpsc::{self, Receiver as MpscReceiver, Sender as MpscSender};
@@ -20,6 +21,7 @@ mod gui;
 #[cfg(feature = "gui")]
 use crate::gui::KGetGui;
 use kget::advanced_download::AdvancedDownloader;
+use kget::checksum::{self, ChecksumAlgorithm, compute_checksums, verify_manifest};
 #[cfg(feature = "gui")]
 use kget::app::{DownloadCommand, WorkerToGuiMessage, spawn_download_worker};
 use kget::config::{Config, ProxyType};
@@ -28,7 +30,11 @@ use kget::ftp::FtpDownloader;
 use kget::metalink;
 use kget::optimization::Optimizer;
 use kget::queue::{DownloadHistory, EntryStatus, HistoryEntry};
-use kget::sftp::SftpDownloader;
+use kget::s3::S3Downloader;
+use kget::signature::SignatureCheck;
+use kget::scp::ScpDownloader;
+use kget::sftp::{SftpDownloader, SymlinkMode};
+use kget::store::{ContentStore, GcOptions};
 use kget::utils;
 use kget::webdav::WebDavDownloader;
 use kget::ytdlp::{VideoQuality, download_video, is_video_url, ytdlp_available, ytdlp_binary};
@@ -106,14 +112,66 @@ struct Args {
     #[arg(long = "sha256")]
     sha256: Option<String>,
 
+    /// Expected Subresource Integrity hash (sha256-/sha384-/sha512-<base64>)
+    #[arg(long = "integrity", value_name = "SRI")]
+    integrity: Option<String>,
+
     /// Use GUI mode
     #[arg(long = "gui")]
     gui: bool,
 
-    /// Use FTP download
+    /// Use FTP download (auto-detected for ftps:// URLs)
     #[arg(long = "ftp")]
     ftp: bool,
 
+    /// FTP: secure ftp:// connections with AUTH TLS before logging in (saved to config)
+    #[arg(long = "ftp-tls", conflicts_with = "no_ftp_tls")]
+    ftp_tls: bool,
+
+    /// FTP: stop using AUTH TLS on ftp:// connections (saved to config)
+    #[arg(long = "no-ftp-tls")]
+    no_ftp_tls: bool,
+
+    /// FTP: use active mode (PORT/EPRT) instead of passive mode (saved to config)
+    #[arg(long = "ftp-active", conflicts_with = "ftp_passive")]
+    ftp_active: bool,
+
+    /// FTP: go back to passive mode (PASV/EPSV) after --ftp-active (saved to config)
+    #[arg(long = "ftp-passive")]
+    ftp_passive: bool,
+
+    /// FTPS: skip server certificate and host name verification (this run only)
+    #[arg(long = "ftp-insecure")]
+    ftp_insecure: bool,
+
+    /// FTPS: also trust this PEM CA certificate (saved to config)
+    #[arg(long = "ftp-ca-cert", value_name = "PATH")]
+    ftp_ca_cert: Option<String>,
+
+    /// Recursive FTP/SFTP downloads: descend at most N directory levels (0 = only the directory itself)
+    #[arg(long = "max-depth", value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// FTP: fetch segments of the file over N parallel control connections (saved to config)
+    #[arg(long = "ftp-connections", value_name = "N")]
+    ftp_connections: Option<usize>,
+
+    /// SFTP: fetch segments of the file over N parallel SSH sessions (saved to config)
+    #[arg(long = "sftp-connections", value_name = "N")]
+    sftp_connections: Option<usize>,
+
+    /// SFTP: private key to try before ~/.ssh/config keys and the agent (saved to config)
+    #[arg(long = "sftp-key", value_name = "PATH")]
+    sftp_key: Option<String>,
+
+    /// Recursive SFTP downloads: symlinks to follow, skip or preserve (default: follow)
+    #[arg(long = "sftp-symlinks", value_name = "MODE")]
+    sftp_symlinks: Option<SymlinkMode>,
+
+    /// SFTP: keep remote permissions and modification times
+    #[arg(long = "preserve")]
+    preserve: bool,
+
     /// Use SFTP download
     #[arg(long = "sftp")]
     sftp: bool,
@@ -158,6 +216,31 @@ struct Args {
     #[arg(long = "webdav")]
     webdav: bool,
 
+    /// Delta download: reuse unchanged blocks from this older local copy
+    /// (URL must be a .zsync control file or a Metalink manifest with piece hashes)
+    #[arg(long = "seed")]
+    seed: Option<String>,
+
+    /// Custom S3 endpoint for s3:// URLs (e.g. http://localhost:9000 for MinIO)
+    #[arg(long = "s3-endpoint")]
+    s3_endpoint: Option<String>,
+
+    /// Region used to sign s3:// requests (default: AWS_REGION or us-east-1)
+    #[arg(long = "s3-region")]
+    s3_region: Option<String>,
+
+    /// Metalink: only download files for this OS (e.g. linux, windows)
+    #[arg(long = "metalink-os", value_name = "OS")]
+    metalink_os: Option<String>,
+
+    /// Metalink: only download files in this language (e.g. en, pt-BR)
+    #[arg(long = "metalink-language", value_name = "LANG")]
+    metalink_language: Option<String>,
+
+    /// Metalink: try mirrors in this country first (ISO code, e.g. de)
+    #[arg(long = "metalink-country", value_name = "CC")]
+    metalink_country: Option<String>,
+
     /// Route URL through yt-dlp (auto-detected for known video platforms)
     #[arg(long = "ytdlp")]
     ytdlp: bool,
@@ -165,6 +248,408 @@ struct Args {
     /// Video quality for yt-dlp downloads: best, 1080p, 720p, 480p, 360p, audio
     #[arg(long = "quality", default_value = "best")]
     quality: String,
+
+    /// Reuse verified files from the content-addressed store and add new ones (saved to config)
+    #[arg(long = "store", conflicts_with = "no_store")]
+    store: bool,
+
+    /// Stop using the content-addressed store (saved to config)
+    #[arg(long = "no-store")]
+    no_store: bool,
+
+    /// Detached signature to verify the download with (.asc/.sig/.minisig URL or path);
+    /// may sign the file itself or a SHA256SUMS list that names it
+    #[arg(long = "sig", value_name = "URL", requires = "keyring")]
+    sig: Option<String>,
+
+    /// Trusted keys: exported OpenPGP public key(s) or a minisign.pub file.
+    /// Without --sig the signature is looked up next to the file
+    #[arg(long = "keyring", value_name = "PATH")]
+    keyring: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect and clean up the HTTP cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Print checksums of local files (one read per file, any set of algorithms)
+    Hash {
+        /// Files to hash
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Comma-separated algorithms: sha256, sha384, sha512, sha1, md5, sha3-256,
+        /// sha3-512, blake2b, blake3, crc32, crc32c, xxh3
+        #[arg(long = "algo", value_name = "ALGOS", value_delimiter = ',', default_value = "sha256", value_parser = parse_algorithm)]
+        algorithms: Vec<ChecksumAlgorithm>,
+    },
+    /// Verify a directory against a SHA256SUMS-style checksum manifest
+    Verify {
+        /// Manifest file (SHA256SUMS, SHA512SUMS, B2SUMS, *.sha256, ...)
+        manifest: PathBuf,
+
+        /// Directory holding the listed files (defaults to the manifest's directory)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create Metalink manifests
+    Metalink {
+        #[command(subcommand)]
+        action: MetalinkAction,
+    },
+    /// Manage the content-addressed store of verified downloads
+    Store {
+        #[command(subcommand)]
+        action: StoreAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// List cached responses with hit/miss statistics
+    List,
+    /// Show the cache entry for a URL
+    Info {
+        /// URL the response was downloaded from
+        url: String,
+    },
+    /// Check every entry's size and SHA-256 and flag corrupted ones
+    Verify {
+        /// Delete corrupted entries
+        #[arg(long = "remove")]
+        remove: bool,
+    },
+    /// Remove entries unused for a while and shrink the cache
+    Prune {
+        /// Remove entries not used in the last DAYS days
+        #[arg(long = "older-than", value_name = "DAYS")]
+        older_than: Option<u64>,
+
+        /// Evict least recently used entries until the cache fits (e.g. 500M, 2G)
+        #[arg(long = "max-size", value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+    },
+    /// Remove every entry
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+enum MetalinkAction {
+    /// Hash local files and write a Metalink 4 (.meta4) listing them on every mirror
+    Create {
+        /// Files to describe
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Mirror base URL, optionally with priority and country: URL[,PRIORITY[,CC]] (repeatable)
+        #[arg(long = "mirror", value_name = "MIRROR", required = true, value_parser = parse_mirror)]
+        mirrors: Vec<metalink::MetalinkMirror>,
+
+        /// Comma-separated whole-file hash algorithms
+        #[arg(long = "algo", value_name = "ALGOS", value_delimiter = ',', default_value = "sha256", value_parser = parse_algorithm)]
+        algorithms: Vec<ChecksumAlgorithm>,
+
+        /// Piece length for piece hashes (e.g. 1M); chosen from the file size by default
+        #[arg(long = "piece-size", value_name = "SIZE", value_parser = parse_size)]
+        piece_size: Option<u64>,
+
+        /// Embed each file's detached signature (<file>.asc)
+        #[arg(long = "sign")]
+        sign: bool,
+
+        /// URL the manifest will be published at
+        #[arg(long = "origin", value_name = "URL")]
+        origin: Option<String>,
+
+        /// Write the manifest here instead of stdout
+        #[arg(short = 'o', long = "output", value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum StoreAction {
+    /// Remove objects that are no longer linked from outside the store
+    Gc {
+        /// Also remove objects not used in the last DAYS days
+        #[arg(long = "older-than", value_name = "DAYS")]
+        older_than: Option<u64>,
+
+        /// Remove every object
+        #[arg(long = "all")]
+        all: bool,
+
+        /// Only report what would be removed
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+    },
+}
+
+fn run_command(command: &Command, config: &Config) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match command {
+        Command::Cache { action } => run_cache_command(action, config),
+        Command::Hash { files, algorithms } => {
+            // A single algorithm prints `sha256sum`-compatible lines; several
+            // use the tagged BSD format so each line names its algorithm.
+            let tagged = algorithms.len() > 1;
+            for file in files {
+                for (algo, hex) in compute_checksums(file, algorithms)? {
+                    if tagged {
+                        println!("{} ({}) = {}", algo.name().to_uppercase(), file.display(), hex);
+                    } else {
+                        println!("{}  {}", hex, file.display());
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::Verify { manifest, dir, json } => {
+            let dir = dir.clone().unwrap_or_else(|| match manifest.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            });
+            let report = verify_manifest(manifest, &dir)?;
+            if *json {
+                let mismatched: Vec<_> = report.mismatched.iter().map(|m| json!({
+                    "path": m.path,
+                    "algorithm": m.algorithm.name(),
+                    "expected": m.expected,
+                    "got": m.got,
+                })).collect();
+                let errors: Vec<_> = report.errors.iter()
+                    .map(|(path, error)| json!({ "path": path, "error": error }))
+                    .collect();
+                println!("{}", json!({
+                    "manifest": manifest.display().to_string(),
+                    "dir": dir.display().to_string(),
+                    "passed": report.passed(),
+                    "ok": report.ok,
+                    "mismatched": mismatched,
+                    "missing": report.missing,
+                    "extra": report.extra,
+                    "errors": errors,
+                }));
+            } else {
+                for path in &report.ok { println!("{}: OK", path); }
+                for m in &report.mismatched {
+                    println!("{}: FAILED ({} expected {}, got {})", m.path, m.algorithm.name(), m.expected, m.got);
+                }
+                for path in &report.missing { println!("{}: MISSING", path); }
+                for (path, error) in &report.errors { println!("{}: ERROR ({})", path, error); }
+                for path in &report.extra { println!("{}: not listed", path); }
+                println!(
+                    "{} ok, {} failed, {} missing, {} unreadable, {} extra",
+                    report.ok.len(),
+                    report.mismatched.len(),
+                    report.missing.len(),
+                    report.errors.len(),
+                    report.extra.len()
+                );
+            }
+            if !report.passed() {
+                return Err(format!("Verification against {} failed", manifest.display()).into());
+            }
+            Ok(())
+        }
+        Command::Metalink { action: MetalinkAction::Create { files, mirrors, algorithms, piece_size, sign, origin, output } } => {
+            let doc = metalink::generate(files, &metalink::GenerateOptions {
+                mirrors: mirrors.clone(),
+                algorithms: algorithms.clone(),
+                piece_length: *piece_size,
+                signature: *sign,
+                origin: origin.clone(),
+            })?;
+            let xml = doc.to_xml();
+            match output {
+                Some(path) => {
+                    std::fs::write(path, xml)?;
+                    println!("Wrote {} ({} file(s), {} mirror(s))", path.display(), doc.files.len(), mirrors.len());
+                }
+                None => print!("{}", xml),
+            }
+            Ok(())
+        }
+        Command::Store { action: StoreAction::Gc { older_than, all, dry_run } } => {
+            let store = ContentStore::from_config(&config.optimization);
+            let report = store.gc(&GcOptions {
+                older_than: older_than.map(|d| std::time::Duration::from_secs(d * 86400)),
+                all: *all,
+                dry_run: *dry_run,
+            })?;
+            println!(
+                "{} {} object(s), {} freed; {} kept ({})",
+                if *dry_run { "Would remove" } else { "Removed" },
+                report.removed,
+                humansize::format_size(report.bytes_freed, humansize::DECIMAL),
+                report.kept,
+                store.root().display()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn run_cache_command(action: &CacheAction, config: &Config) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let optimizer = Optimizer::from_config(config.optimization.clone());
+    let size = |n: u64| humansize::format_size(n, humansize::DECIMAL);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let state = |e: &kget::cache::CacheEntry| {
+        if e.is_fresh(now) {
+            "fresh"
+        } else if e.has_validator() {
+            "stale"
+        } else {
+            "expired"
+        }
+    };
+
+    match action {
+        CacheAction::List => {
+            let mut entries = optimizer.cache_entries();
+            entries.sort_by_key(|e| std::cmp::Reverse(e.last_access));
+            if !entries.is_empty() {
+                println!("{:<8} {:>10} {:>10} {:>6} {:>8}  URL", "State", "Size", "On disk", "Hits", "Used");
+                for e in &entries {
+                    println!(
+                        "{:<8} {:>10} {:>10} {:>6} {:>8}  {}",
+                        state(e),
+                        size(e.size),
+                        size(e.stored_size),
+                        e.hits,
+                        format_age(now.saturating_sub(e.last_access)),
+                        e.url
+                    );
+                }
+            }
+            let stats = optimizer.cache_stats();
+            println!(
+                "{} entries, {} on disk (limit {}); {} hits, {} revalidated, {} misses ({:.0}% hit ratio), {} served from cache",
+                entries.len(),
+                size(entries.iter().map(|e| e.stored_size).sum()),
+                size(config.optimization.cache_max_size),
+                stats.hits,
+                stats.revalidated,
+                stats.misses,
+                stats.hit_ratio() * 100.0,
+                size(stats.bytes_served)
+            );
+        }
+        CacheAction::Info { url } => {
+            let e = optimizer
+                .cache_info(url)
+                .ok_or_else(|| format!("No cache entry for {}", url))?;
+            println!("URL:            {}", e.url);
+            println!("State:          {}", state(&e));
+            println!("Size:           {} ({} on disk, {:?})", size(e.size), size(e.stored_size), e.codec);
+            println!("SHA-256:        {}", e.sha256);
+            println!("Content-Type:   {}", e.content_type.as_deref().unwrap_or("-"));
+            println!("ETag:           {}", e.etag.as_deref().unwrap_or("-"));
+            println!("Last-Modified:  {}", e.last_modified.as_deref().unwrap_or("-"));
+            println!("Stored:         {} ago", format_age(now.saturating_sub(e.stored_at)));
+            match e.expires_at {
+                Some(t) if t > now => println!("Expires:        in {}", format_age(t - now)),
+                Some(t) => println!("Expired:        {} ago", format_age(now - t)),
+                None => println!("Expires:        always revalidated"),
+            }
+            println!("Hits:           {} (last used {} ago)", e.hits, format_age(now.saturating_sub(e.last_access)));
+        }
+        CacheAction::Verify { remove } => {
+            let report = optimizer.verify_cache(*remove);
+            for c in &report.corrupted {
+                println!(
+                    "[CORRUPT] {}: {}",
+                    c.url.clone().unwrap_or_else(|| c.path.display().to_string()),
+                    c.problem
+                );
+            }
+            println!(
+                "{} ok, {} corrupted{}",
+                report.ok,
+                report.corrupted.len(),
+                if *remove { format!(", {} removed", report.removed) } else { String::new() }
+            );
+            if !report.corrupted.is_empty() && !*remove {
+                return Err("Corrupted cache entries found (re-run with --remove to delete them)".into());
+            }
+        }
+        CacheAction::Prune { older_than, max_size } => {
+            let report = optimizer.prune_cache(
+                older_than.map(|d| std::time::Duration::from_secs(d * 86400)),
+                *max_size,
+            );
+            println!(
+                "Removed {} entries, {} freed; {} kept",
+                report.removed,
+                size(report.bytes_freed),
+                report.kept
+            );
+        }
+        CacheAction::Clear => {
+            let report = optimizer.clear_cache();
+            println!("Removed {} entries, {} freed", report.removed, size(report.bytes_freed));
+        }
+    }
+    Ok(())
+}
+
+fn parse_algorithm(s: &str) -> Result<ChecksumAlgorithm, String> {
+    ChecksumAlgorithm::from_name(s).ok_or_else(|| format!("unknown algorithm: {}", s))
+}
+
+/// Parse a mirror spec `URL[,PRIORITY[,COUNTRY]]`.
+fn parse_mirror(s: &str) -> Result<metalink::MetalinkMirror, String> {
+    let mut parts = s.split(',').map(str::trim);
+    let base_url = parts.next().filter(|u| !u.is_empty()).ok_or("empty mirror URL")?.to_string();
+    let priority = match parts.next().filter(|p| !p.is_empty()) {
+        Some(p) => Some(p.parse::<u32>().map_err(|_| format!("invalid mirror priority: {}", p))?),
+        None => None,
+    };
+    let location = parts.next().filter(|l| !l.is_empty()).map(str::to_string);
+    if parts.next().is_some() {
+        return Err(format!("too many fields in mirror spec: {}", s));
+    }
+    Ok(metalink::MetalinkMirror { base_url, priority, location })
+}
+
+/// Parse a size like `1048576`, `500K`, `200M` or `2G` (binary units).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, unit) = s
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((s, ""), |i| s.split_at(i));
+    let n: u64 = digits.parse().map_err(|_| format!("invalid size: {}", s))?;
+    let mult: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("invalid size unit in {}", s)),
+    };
+    n.checked_mul(mult).ok_or_else(|| format!("size too large: {}", s))
+}
+
+/// Compact age: `45s`, `12m`, `3h`, `9d`.
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
 }
 
 fn emit_jsonl(value: serde_json::Value) {
@@ -199,9 +684,13 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         AttachConsole(ATTACH_PARENT_PROCESS);
     }
 
-    let args = Args::parse();
+    let mut args = Args::parse();
     let mut config = Config::load()?;
 
+    if let Some(ref command) = args.command {
+        return run_command(command, &config);
+    }
+
     if args.interactive {
         interactive::interactive_mode();
         return Ok(());
@@ -275,6 +764,12 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         if args.no_cache {
             config.optimization.cache_enabled = false;
         }
+        if args.store {
+            config.optimization.store_enabled = true;
+        }
+        if args.no_store {
+            config.optimization.store_enabled = false;
+        }
         if args.torrent {
             config.torrent.enabled = true;
         }
@@ -290,7 +785,54 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         if args.no_dht {
             config.torrent.dht_enabled = false;
         }
+        if let Some(connections) = args.ftp_connections {
+            config.ftp.connections = Some(connections);
+        }
+        if let Some(connections) = args.sftp_connections {
+            config.sftp.connections = Some(connections);
+        }
+        if let Some(key) = args.sftp_key.clone() {
+            config.sftp.key_path = Some(key);
+        }
+        if args.ftp_tls {
+            config.ftp.explicit_tls = true;
+        }
+        if args.no_ftp_tls {
+            config.ftp.explicit_tls = false;
+        }
+        if args.ftp_active {
+            config.ftp.passive_mode = false;
+        }
+        if args.ftp_passive {
+            config.ftp.passive_mode = true;
+        }
+        if let Some(ca_cert) = args.ftp_ca_cert.clone() {
+            config.ftp.ca_cert = Some(ca_cert);
+        }
         config.save()?;
+        // Not persisted: turning certificate checks off should stay a one-off decision.
+        if args.ftp_insecure {
+            config.ftp.verify_certificates = false;
+        }
+        // Not persisted: an endpoint or region given on the command line
+        // targets this URL; the `s3` config section holds the defaults.
+        if let Some(endpoint) = args.s3_endpoint.clone() {
+            config.s3.endpoint = Some(endpoint);
+        }
+        if let Some(region) = args.s3_region.clone() {
+            config.s3.region = Some(region);
+        }
+        // Not persisted: Metalink selection is about this document only; the
+        // `metalink` config section holds the defaults.
+        if let Some(os) = args.metalink_os.clone() {
+            config.metalink.os = Some(os);
+        }
+        if let Some(language) = args.metalink_language.clone() {
+            config.metalink.language = Some(language);
+        }
+        if let Some(country) = args.metalink_country.clone() {
+            config.metalink.country = Some(country);
+        }
     }
 
     if should_start_gui {
@@ -458,13 +1000,43 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             "url": args.url.clone(),
             "advanced": args.advanced,
             "torrent": args.torrent || args.url.starts_with("magnet:?"),
-            "ftp": args.ftp,
+            "ftp": args.ftp || args.url.starts_with("ftps://"),
             "sftp": args.sftp,
+            "scp": kget::is_scp_url(&args.url),
         }));
     }
 
     let is_metalink_source = args.metalink || metalink::is_metalink(&args.url);
 
+    // An SRI SHA-256 goes through the downloaders' own check; SHA-384 and
+    // SHA-512 are checked once the file is saved.
+    let mut integrity_digest = None;
+    if let Some(sri) = &args.integrity {
+        match checksum::parse_sri(sri) {
+            Some((ChecksumAlgorithm::Sha256, hex)) if args.sha256.is_none() => args.sha256 = Some(hex),
+            Some(digest) => integrity_digest = Some(digest),
+            None => return Err(format!("Invalid integrity string: {sri}").into()),
+        }
+    }
+
+    // Fetch the signature up front: a signed checksum list is verified before
+    // anything is downloaded and supplies the expected SHA-256.
+    let mut signed_sha256 = None;
+    let pending_signature = match &args.keyring {
+        Some(keyring) if !is_metalink_source => {
+            let check = SignatureCheck::new(args.sig.clone(), keyring);
+            let pending = check.prepare_with_proxy(&config.proxy, &args.url).map_err(|e| e.to_string())?;
+            if let Some((ChecksumAlgorithm::Sha256, hex)) = pending.expected_digest()
+                && args.sha256.is_none()
+            {
+                args.sha256 = Some(hex.to_string());
+                signed_sha256 = Some(hex.to_string());
+            }
+            Some(pending)
+        }
+        _ => None,
+    };
+
     // Save before args fields are moved into dispatch branches
     let history_url = args.url.clone();
     let history_output_dir = args.output.as_deref().unwrap_or(".").to_string();
@@ -482,7 +1054,17 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         return Err("yt-dlp not found".into());
     }
 
-    let result: Result<(), Box<dyn Error + Send + Sync>> = if use_ytdlp {
+    let result: Result<(), Box<dyn Error + Send + Sync>> = if let Some(ref seed) = args.seed {
+        kget::delta::download_with_seed(
+            &args.url,
+            std::path::Path::new(seed),
+            args.output.as_deref(),
+            quiet_mode,
+            config.proxy.clone(),
+            optimizer.clone(),
+        )
+        .map(|_| ())
+    } else if use_ytdlp {
         let output_dir = args.output.as_deref().unwrap_or(".");
         let quality = VideoQuality::from_str(&args.quality);
         if !quiet_mode {
@@ -507,26 +1089,71 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             optimizer.clone(),
         );
         downloader.download()
+    } else if kget::is_s3_url(&args.url) {
+        let output = if kget::s3::is_prefix_url(&args.url) {
+            args.output.unwrap_or_else(|| ".".to_string())
+        } else {
+            utils::resolve_output_path(args.output, &args.url, "s3_output")
+        };
+        let mut downloader = S3Downloader::new(
+            args.url.clone(),
+            output,
+            quiet_mode,
+            config.proxy.clone(),
+            optimizer.clone(),
+        );
+        downloader.set_config(config.s3.clone());
+        downloader.download()
     } else if is_metalink_source {
         let output_dir = args.output.as_deref().unwrap_or(".");
-        metalink::download_metalink(
+        metalink::download_metalink_with(
             &args.url,
             output_dir,
             quiet_mode,
             config.proxy.clone(),
             optimizer.clone(),
+            &config.metalink,
         )
-    } else if args.ftp {
+    } else if args.ftp || args.url.starts_with("ftps://") {
         let url = args.url.clone();
-        let output = utils::resolve_output_path(args.output, &url, "ftp_output");
-        let downloader =
+        let output = if kget::ftp::is_tree_url(&url) {
+            args.output.unwrap_or_else(|| ".".to_string())
+        } else {
+            utils::resolve_output_path(args.output, &url, "ftp_output")
+        };
+        let mut downloader =
             FtpDownloader::new(url.to_owned(), output, quiet_mode, config.proxy, optimizer);
+        downloader.set_config(config.ftp.clone());
+        downloader.set_max_depth(args.max_depth);
+        if args.jsonl {
+            downloader.set_status_callback(emit_jsonl_status);
+        }
         downloader.download()
     } else if args.sftp {
         let url = args.url.clone();
-        let output = utils::resolve_output_path(args.output, &url, "sftp_output");
-        let downloader =
+        let output = if kget::sftp::is_tree_url(&url) {
+            args.output.unwrap_or_else(|| ".".to_string())
+        } else {
+            utils::resolve_output_path(args.output, &url, "sftp_output")
+        };
+        let mut downloader =
             SftpDownloader::new(url.to_owned(), output, quiet_mode, config.proxy, optimizer);
+        downloader.set_config(config.sftp.clone());
+        downloader.set_max_depth(args.max_depth);
+        downloader.set_symlink_mode(args.sftp_symlinks.unwrap_or_default());
+        downloader.set_preserve(args.preserve);
+        if args.jsonl {
+            downloader.set_status_callback(emit_jsonl_status);
+        }
+        downloader.download()
+    } else if kget::is_scp_url(&args.url) {
+        let output = utils::resolve_output_path(args.output, &args.url, "scp_output");
+        let mut downloader =
+            ScpDownloader::new(args.url.clone(), output, quiet_mode, config.proxy, optimizer);
+        downloader.set_config(config.sftp.clone());
+        if args.jsonl {
+            downloader.set_status_callback(emit_jsonl_status);
+        }
         downloader.download()
     } else if args.torrent || args.url.starts_with("magnet:?") {
         let output_dir = args.output.unwrap_or_else(|| "torrent_output".to_string());
@@ -553,7 +1180,7 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             optimizer,
             callbacks,
         )
-    } else if args.advanced {
+    } else if args.advanced && !kget::is_local_url(&args.url) {
         let output = utils::resolve_output_path(args.output, &args.url, "advanced_output");
         let mut downloader = AdvancedDownloader::new(
             args.url.clone(),
@@ -616,6 +1243,31 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         }
     };
 
+    let result = match (result, &pending_signature) {
+        (Ok(()), Some(pending)) => {
+            let path = single_download_path(&history_output_dir, &history_url);
+            let digests: Vec<_> = signed_sha256.iter().map(|h| (ChecksumAlgorithm::Sha256, h.as_str())).collect();
+            match pending.verify_with_digests(std::path::Path::new(&path), &digests) {
+                Ok(info) => {
+                    if !quiet_mode {
+                        println!("Good {} signature from {}", info.kind, info.signer);
+                    }
+                    Ok(())
+                }
+                Err(e) => Err(e.to_string().into()),
+            }
+        }
+        (result, _) => result,
+    };
+
+    let result = match (result, integrity_digest) {
+        (Ok(()), Some(digest)) if !is_metalink_source => {
+            let path = single_download_path(&history_output_dir, &history_url);
+            checksum::verify_digests(std::path::Path::new(&path), &[digest]).map_err(|e| e.to_string().into())
+        }
+        (result, _) => result,
+    };
+
     // Record to history (best-effort; never fail the download over a history error)
     if !is_metalink_source {
         let mut history = DownloadHistory::load();
@@ -630,13 +1282,7 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
 
     // Auto-extract after a successful single-URL download
     if result.is_ok() && args.extract && !is_metalink_source && !args.torrent && !args.ftp && !args.sftp {
-        let fname = utils::get_filename_from_url_or_default(&history_url, "download");
-        let output_path = utils::resolve_output_path(
-            // history_output_dir is "." when --output was omitted
-            if history_output_dir == "." { None } else { Some(history_output_dir.clone()) },
-            &history_url,
-            &fname,
-        );
+        let output_path = single_download_path(&history_output_dir, &history_url);
         let path = std::path::Path::new(&output_path);
         if kget::is_extractable(path) {
             if let Err(e) = kget::auto_extract(path, quiet_mode) {
@@ -670,6 +1316,17 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
 // Helpers
 // ============================================================================
 
+/// Where a single-URL download was saved; `output_dir` is "." when
+/// `--output` was omitted.
+fn single_download_path(output_dir: &str, url: &str) -> String {
+    let fname = utils::get_filename_from_url_or_default(url, "download");
+    utils::resolve_output_path(
+        if output_dir == "." { None } else { Some(output_dir.to_string()) },
+        url,
+        &fname,
+    )
+}
+
 fn parse_extra_headers(raw: &[String]) -> Vec<(String, String)> {
     raw.iter()
         .filter_map(|h| {
@@ -6,10 +6,16 @@
 //! the hash after a successful download — all automatically.
 //!
 //! # Supported features
-//! - Multiple mirrors with optional `priority` attribute (lower = preferred)
-//! - `sha-256`, `sha-512`, and `md5` hash types (sha-256 preferred)
+//! - Metalink 4 (RFC 5854) and Metalink 3.0 (`<resources>`, `<verification>`)
+//! - Multiple mirrors with optional `priority` attribute (lower = preferred),
+//!   or Metalink 3 `preference`; mirrors in a configured country go first
+//! - `<os>` and `<language>` filtering via [`MetalinkConfig`]
+//! - `<signature>`, `<metaurl>`, publisher, version and description metadata
+//! - Every `<hash type>` [`ChecksumAlgorithm`] knows (`sha-256`, `sha-384`,
+//!   `sha-512`, `sha3-256`, `md5`, `crc32c`, …); the strongest is verified
 //! - Multiple `<file>` entries per manifest
 //! - Local `.meta4` files and remote `.meta4` URLs
+//! - Writing `.meta4` manifests for local files with [`generate`]
 //!
 //! # Example
 //!
@@ -26,11 +32,18 @@
 //! ).unwrap();
 //! ```
 
-use crate::config::ProxyConfig;
-use crate::download::verify_file_sha256;
+use crate::advanced_download::AdvancedDownloader;
+use crate::builder::{ComputedChecksums, DownloadResult, MetalinkResult};
+use crate::checksum::{ChecksumAlgorithm, compute_checksum, compute_checksums, compute_piece_hashes};
+use crate::config::{MetalinkConfig, ProxyConfig};
+use crate::error::KgetError;
+use crate::events::{DownloadEvent, FileEvent};
 use crate::optimization::Optimizer;
 use std::error::Error;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Instant;
 
 // ============================================================================
 // Public data model
@@ -41,8 +54,32 @@ use std::path::{Path, PathBuf};
 pub struct MetalinkUrl {
     pub url: String,
     /// Lower priority number means the mirror is tried first.
-    /// Defaults to 999 if the attribute is absent.
+    /// Defaults to 999 if the attribute is absent. Metalink 3 `preference`
+    /// values (higher = better, 1–100) are mapped onto the same scale.
     pub priority: u32,
+    /// ISO 3166-1 alpha-2 country code of the mirror, lowercase.
+    pub location: Option<String>,
+}
+
+/// A `<metaurl>`: another manifest (e.g. a `.torrent`) describing the same
+/// file. Metalink 3 `type="bittorrent"` URLs are reported here too.
+#[derive(Debug, Clone)]
+pub struct MetalinkMetaUrl {
+    pub url: String,
+    /// Media type of the manifest, e.g. `torrent`.
+    pub mediatype: String,
+    pub priority: u32,
+    /// Name of the file inside a multi-file manifest.
+    pub name: Option<String>,
+}
+
+/// A detached signature embedded in a `<signature>` element.
+#[derive(Debug, Clone)]
+pub struct MetalinkSignature {
+    /// Media type, e.g. `application/pgp-signature`.
+    pub mediatype: String,
+    /// The signature itself, usually ASCII-armored.
+    pub body: String,
 }
 
 /// A single file described in a Metalink manifest.
@@ -53,8 +90,35 @@ pub struct MetalinkFile {
     pub sha256: Option<String>,
     pub sha512: Option<String>,
     pub md5: Option<String>,
+    /// Every whole-file hash with a recognised type, in document order.
+    pub hashes: Vec<(ChecksumAlgorithm, String)>,
+    /// Per-piece hashes, used for delta downloads against a local seed.
+    pub pieces: Option<MetalinkPieces>,
     /// Mirrors sorted by priority (ascending).
     pub urls: Vec<MetalinkUrl>,
+    /// Alternative manifests, sorted by priority (ascending).
+    pub metaurls: Vec<MetalinkMetaUrl>,
+    pub identity: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    /// Publisher name, inherited from the document in Metalink 3.
+    pub publisher: Option<String>,
+    /// Languages of the file's content (`<language>`); empty means any.
+    pub languages: Vec<String>,
+    /// Operating systems the file is for (`<os>`); empty means any.
+    pub os: Vec<String>,
+    pub signature: Option<MetalinkSignature>,
+}
+
+/// Piece hashes from a `<pieces>` element: one digest per `length`-byte piece.
+#[derive(Debug, Clone)]
+pub struct MetalinkPieces {
+    /// Piece size in bytes.
+    pub length: u64,
+    /// Normalised hash type (`sha-1`, `sha-256`, …).
+    pub hash_type: String,
+    /// Lowercase hex digests, in piece order.
+    pub hashes: Vec<String>,
 }
 
 impl MetalinkFile {
@@ -72,95 +136,736 @@ impl MetalinkFile {
         }
         None
     }
+
+    /// Return the strongest available hash of any supported algorithm.
+    ///
+    /// SHA-256 comes first (the content store is usually keyed by it), then
+    /// the other cryptographic hashes from strongest to weakest, then the
+    /// checksums that only catch accidental corruption.
+    pub fn best_digest(&self) -> Option<(ChecksumAlgorithm, &str)> {
+        const PREFERENCE: [ChecksumAlgorithm; 12] = [
+            ChecksumAlgorithm::Sha256,
+            ChecksumAlgorithm::Sha512,
+            ChecksumAlgorithm::Sha3_512,
+            ChecksumAlgorithm::Sha384,
+            ChecksumAlgorithm::Sha3_256,
+            ChecksumAlgorithm::Blake2b,
+            ChecksumAlgorithm::Blake3,
+            ChecksumAlgorithm::Sha1,
+            ChecksumAlgorithm::Md5,
+            ChecksumAlgorithm::Xxh3,
+            ChecksumAlgorithm::Crc32c,
+            ChecksumAlgorithm::Crc32,
+        ];
+        PREFERENCE.into_iter().find_map(|algo| {
+            self.hashes
+                .iter()
+                .find(|(a, _)| *a == algo)
+                .map(|(_, h)| (algo, h.as_str()))
+        })
+    }
+
+    /// Whether the file is meant for `os`. Compared case-insensitively, and
+    /// a bare name matches a qualified one (`linux` matches `Linux-x86`).
+    /// Files without `<os>` match every OS.
+    pub fn matches_os(&self, os: &str) -> bool {
+        self.os.is_empty() || self.os.iter().any(|candidate| tag_matches(candidate, os))
+    }
+
+    /// Whether the file is in `language`. `en` matches `en-US`; files
+    /// without `<language>` match every language.
+    pub fn matches_language(&self, language: &str) -> bool {
+        self.languages.is_empty() || self.languages.iter().any(|candidate| tag_matches(candidate, language))
+    }
+
+    /// Mirrors in the order to try them: by priority, with mirrors located
+    /// in `country` ahead of the rest.
+    pub fn mirrors(&self, country: Option<&str>) -> Vec<&MetalinkUrl> {
+        let mut mirrors: Vec<&MetalinkUrl> = self.urls.iter().collect();
+        if let Some(country) = country {
+            mirrors.sort_by_key(|u| !u.location.as_deref().is_some_and(|l| l.eq_ignore_ascii_case(country)));
+        }
+        mirrors
+    }
+}
+
+/// `candidate` equals `wanted`, or starts with it followed by `-` or `_`.
+fn tag_matches(candidate: &str, wanted: &str) -> bool {
+    let candidate = candidate.trim().to_ascii_lowercase();
+    let wanted = wanted.trim().to_ascii_lowercase();
+    candidate == wanted
+        || candidate
+            .strip_prefix(&wanted)
+            .is_some_and(|rest| rest.starts_with(['-', '_']))
+}
+
+/// Which Metalink dialect a document was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetalinkVersion {
+    /// Metalink 3.0 (`http://www.metalinker.org/`).
+    V3,
+    /// Metalink 4 (RFC 5854, `urn:ietf:params:xml:ns:metalink`).
+    V4,
 }
 
 /// A parsed Metalink manifest, potentially containing multiple files.
 #[derive(Debug, Clone)]
 pub struct MetalinkDoc {
+    pub version: MetalinkVersion,
     pub files: Vec<MetalinkFile>,
+    pub generator: Option<String>,
+    /// URL the manifest itself was published at.
+    pub origin: Option<String>,
+    pub published: Option<String>,
+}
+
+impl MetalinkDoc {
+    /// Files matching the OS and language in `prefs`.
+    pub fn select(&self, prefs: &MetalinkConfig) -> Vec<&MetalinkFile> {
+        self.files
+            .iter()
+            .filter(|f| prefs.os.as_deref().is_none_or(|os| f.matches_os(os)))
+            .filter(|f| prefs.language.as_deref().is_none_or(|lang| f.matches_language(lang)))
+            .collect()
+    }
 }
 
 // ============================================================================
 // Parser
 // ============================================================================
 
+const METALINK3_NS: &str = "http://www.metalinker.org/";
+
 /// Parse a `.meta4` or `.metalink` XML string into a [`MetalinkDoc`].
 ///
 /// Both the RFC 5854 namespace (`urn:ietf:params:xml:ns:metalink`) and the
-/// older Metalink 3.x format (no namespace) are accepted.
+/// older Metalink 3.0 format (`<files>`, `<resources>`, `<verification>`)
+/// are accepted.
 pub fn parse(content: &str) -> Result<MetalinkDoc, Box<dyn Error + Send + Sync>> {
     let doc = roxmltree::Document::parse(content)
         .map_err(|e| format!("Metalink XML parse error: {}", e))?;
 
     let root = doc.root_element();
+    let version = if root.tag_name().namespace() == Some(METALINK3_NS)
+        || root.attribute("version").is_some_and(|v| v.starts_with('3'))
+    {
+        MetalinkVersion::V3
+    } else {
+        MetalinkVersion::V4
+    };
+    let doc_publisher = child(root, "publisher").and_then(publisher_name);
+
+    // Metalink 3 wraps the files in <files>.
+    let file_nodes = root.children().filter(|n| n.is_element()).flat_map(|n| match n.tag_name().name() {
+        "file" => vec![n],
+        "files" => n.children().filter(|f| f.is_element() && f.tag_name().name() == "file").collect(),
+        _ => Vec::new(),
+    });
+
     let mut files: Vec<MetalinkFile> = Vec::new();
+    for file_node in file_nodes {
+        let mut file = parse_file(file_node);
+        if file.publisher.is_none() {
+            file.publisher = doc_publisher.clone();
+        }
+        if !file.urls.is_empty() {
+            files.push(file);
+        }
+    }
 
-    for file_node in root
-        .children()
-        .filter(|n| n.is_element() && n.tag_name().name() == "file")
-    {
-        let name = file_node
-            .attribute("name")
-            .unwrap_or("download")
-            .to_string();
+    if files.is_empty() {
+        return Err("Metalink contains no downloadable files with at least one URL".into());
+    }
 
-        let mut size: Option<u64> = None;
-        let mut sha256: Option<String> = None;
-        let mut sha512: Option<String> = None;
-        let mut md5: Option<String> = None;
-        let mut urls: Vec<MetalinkUrl> = Vec::new();
+    Ok(MetalinkDoc {
+        version,
+        files,
+        generator: child_text(root, "generator"),
+        origin: child_text(root, "origin"),
+        published: child_text(root, "published").or_else(|| root.attribute("pubdate").map(str::to_string)),
+    })
+}
 
-        for child in file_node.children().filter(|n| n.is_element()) {
-            match child.tag_name().name() {
-                "size" => {
-                    size = child.text().and_then(|t| t.trim().parse::<u64>().ok());
+/// Parse one `<file>` element of either version.
+fn parse_file(file_node: roxmltree::Node) -> MetalinkFile {
+    let mut file = MetalinkFile {
+        name: file_node.attribute("name").unwrap_or("download").to_string(),
+        size: None,
+        sha256: None,
+        sha512: None,
+        md5: None,
+        hashes: Vec::new(),
+        pieces: None,
+        urls: Vec::new(),
+        metaurls: Vec::new(),
+        identity: None,
+        version: None,
+        description: None,
+        publisher: None,
+        languages: Vec::new(),
+        os: Vec::new(),
+        signature: None,
+    };
+
+    for node in file_node.children().filter(|n| n.is_element()) {
+        let text = || node.text().map(str::trim).filter(|t| !t.is_empty()).map(str::to_string);
+        match node.tag_name().name() {
+            "size" => file.size = text().and_then(|t| t.parse::<u64>().ok()),
+            "identity" => file.identity = text(),
+            "version" => file.version = text(),
+            "description" => file.description = text(),
+            "publisher" => file.publisher = publisher_name(node),
+            "language" => file.languages.extend(text()),
+            "os" => file.os.extend(text()),
+            "hash" => push_hash(&mut file.hashes, node),
+            "pieces" => file.pieces = parse_pieces(node),
+            "signature" => file.signature = parse_signature(node),
+            "url" => file.urls.extend(parse_url(node)),
+            "metaurl" => {
+                if let Some(url) = text() {
+                    file.metaurls.push(MetalinkMetaUrl {
+                        url,
+                        mediatype: node.attribute("mediatype").unwrap_or("torrent").to_string(),
+                        priority: parse_priority(node),
+                        name: node.attribute("name").map(str::to_string),
+                    });
                 }
-                "hash" => {
-                    let hash_type = child.attribute("type").unwrap_or("").to_lowercase();
-                    let value = child.text().map(|t| t.trim().to_ascii_lowercase());
-                    match hash_type.as_str() {
-                        "sha-256" => sha256 = value,
-                        "sha-512" => sha512 = value,
-                        "md5" => md5 = value,
+            }
+            // Metalink 3.0 nests hashes, pieces and signatures under <verification>…
+            "verification" => {
+                for v in node.children().filter(|n| n.is_element()) {
+                    match v.tag_name().name() {
+                        "hash" => push_hash(&mut file.hashes, v),
+                        "pieces" => file.pieces = parse_pieces(v),
+                        "signature" => file.signature = parse_signature(v),
                         _ => {}
                     }
                 }
-                "url" => {
-                    let priority = child
-                        .attribute("priority")
-                        .and_then(|p| p.parse::<u32>().ok())
-                        .unwrap_or(999);
-                    if let Some(url_text) = child.text() {
-                        let url = url_text.trim().to_string();
-                        if !url.is_empty() {
-                            urls.push(MetalinkUrl { url, priority });
+            }
+            // …and mirrors under <resources>.
+            "resources" => {
+                for u in node.children().filter(|n| n.is_element() && n.tag_name().name() == "url") {
+                    if u.attribute("type").is_some_and(|t| t.eq_ignore_ascii_case("bittorrent")) {
+                        if let Some(url) = u.text().map(str::trim).filter(|t| !t.is_empty()) {
+                            file.metaurls.push(MetalinkMetaUrl {
+                                url: url.to_string(),
+                                mediatype: "torrent".to_string(),
+                                priority: parse_priority(u),
+                                name: None,
+                            });
                         }
+                    } else {
+                        file.urls.extend(parse_url(u));
                     }
                 }
-                _ => {}
             }
+            _ => {}
         }
+    }
 
-        // Sort URLs: lowest priority number first.
-        urls.sort_by_key(|u| u.priority);
+    let first = |algo: ChecksumAlgorithm| file.hashes.iter().find(|(a, _)| *a == algo).map(|(_, h)| h.clone());
+    file.sha256 = first(ChecksumAlgorithm::Sha256);
+    file.sha512 = first(ChecksumAlgorithm::Sha512);
+    file.md5 = first(ChecksumAlgorithm::Md5);
 
-        if !urls.is_empty() {
-            files.push(MetalinkFile {
-                name,
-                size,
-                sha256,
-                sha512,
-                md5,
-                urls,
-            });
+    // Sort URLs: lowest priority number first.
+    file.urls.sort_by_key(|u| u.priority);
+    file.metaurls.sort_by_key(|u| u.priority);
+    file
+}
+
+/// The first child element called `name`.
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Trimmed, non-empty text of the first child element called `name`.
+fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    child(node, name)?.text().map(str::trim).filter(|t| !t.is_empty()).map(str::to_string)
+}
+
+/// `<publisher name="…"/>` (Metalink 4) or `<publisher><name>…</name></publisher>` (3.0).
+fn publisher_name(node: roxmltree::Node) -> Option<String> {
+    node.attribute("name").map(str::to_string).or_else(|| child_text(node, "name"))
+}
+
+/// Record a `<hash type="…">` with a recognised type.
+fn push_hash(hashes: &mut Vec<(ChecksumAlgorithm, String)>, node: roxmltree::Node) {
+    let value = node.text().map(|t| t.trim().to_ascii_lowercase());
+    if let (Some(algo), Some(value)) = (node.attribute("type").and_then(ChecksumAlgorithm::from_name), value) {
+        hashes.push((algo, value));
+    }
+}
+
+/// `priority` (Metalink 4, lower first) or `preference` (3.0, 1–100,
+/// higher first) on one scale; 999 when absent.
+fn parse_priority(node: roxmltree::Node) -> u32 {
+    if let Some(priority) = node.attribute("priority").and_then(|p| p.trim().parse::<u32>().ok()) {
+        return priority;
+    }
+    node.attribute("preference")
+        .and_then(|p| p.trim().parse::<u32>().ok())
+        .map_or(999, |p| 101 - p.clamp(1, 100))
+}
+
+fn parse_url(node: roxmltree::Node) -> Option<MetalinkUrl> {
+    let url = node.text()?.trim();
+    if url.is_empty() {
+        return None;
+    }
+    Some(MetalinkUrl {
+        url: url.to_string(),
+        priority: parse_priority(node),
+        location: node.attribute("location").map(|l| l.trim().to_ascii_lowercase()),
+    })
+}
+
+/// `<signature mediatype="…">` (Metalink 4) or `<signature type="pgp">` (3.0).
+fn parse_signature(node: roxmltree::Node) -> Option<MetalinkSignature> {
+    let body = node.text()?.trim();
+    if body.is_empty() {
+        return None;
+    }
+    let mediatype = match (node.attribute("mediatype"), node.attribute("type")) {
+        (Some(m), _) => m.to_string(),
+        (None, Some(t)) if t.eq_ignore_ascii_case("pgp") => "application/pgp-signature".to_string(),
+        (None, Some(t)) => t.to_string(),
+        (None, None) => "application/pgp-signature".to_string(),
+    };
+    Some(MetalinkSignature { mediatype, body: body.to_string() })
+}
+
+/// Parse a `<pieces length=".." type="..">` element.
+fn parse_pieces(node: roxmltree::Node) -> Option<MetalinkPieces> {
+    let length = node.attribute("length")?.trim().parse::<u64>().ok()?;
+    let hash_type = match node.attribute("type")?.to_lowercase().as_str() {
+        "sha1" | "sha-1" => "sha-1".to_string(),
+        "sha256" | "sha-256" => "sha-256".to_string(),
+        "sha512" | "sha-512" => "sha-512".to_string(),
+        other => other.to_string(),
+    };
+    let hashes: Vec<String> = node
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "hash")
+        .filter_map(|n| n.text().map(|t| t.trim().to_ascii_lowercase()))
+        .collect();
+    if length == 0 || hashes.is_empty() {
+        return None;
+    }
+    Some(MetalinkPieces { length, hash_type, hashes })
+}
+
+// ============================================================================
+// Generator
+// ============================================================================
+
+const METALINK4_NS: &str = "urn:ietf:params:xml:ns:metalink";
+
+/// A mirror serving every generated file under `base_url`.
+#[derive(Debug, Clone)]
+pub struct MetalinkMirror {
+    pub base_url: String,
+    /// RFC 5854 priority, 1 (most preferred) to 999999.
+    pub priority: Option<u32>,
+    /// ISO 3166-1 alpha-2 country code.
+    pub location: Option<String>,
+}
+
+/// Settings for [`generate`].
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    pub mirrors: Vec<MetalinkMirror>,
+    /// Whole-file `<hash>` algorithms (SHA-256 by default).
+    pub algorithms: Vec<ChecksumAlgorithm>,
+    /// `<pieces>` length in bytes; picked from the file size when `None`.
+    pub piece_length: Option<u64>,
+    /// Attach the detached ASCII-armored signature `<file>.asc`.
+    pub signature: bool,
+    /// URL the manifest will be published at.
+    pub origin: Option<String>,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            mirrors: Vec::new(),
+            algorithms: vec![ChecksumAlgorithm::Sha256],
+            piece_length: None,
+            signature: false,
+            origin: None,
         }
     }
+}
+
+/// Describe local files as a Metalink 4 document.
+///
+/// Every file is hashed whole and in SHA-256 pieces, and gets one `<url>` per
+/// mirror (`base_url` + file name). Serialize the result with
+/// [`MetalinkDoc::to_xml`]; [`parse`] reads it back.
+pub fn generate(files: &[PathBuf], options: &GenerateOptions) -> Result<MetalinkDoc, Box<dyn Error + Send + Sync>> {
+    use rayon::prelude::*;
 
     if files.is_empty() {
-        return Err("Metalink contains no downloadable files with at least one URL".into());
+        return Err("No files to describe".into());
+    }
+    if options.mirrors.is_empty() {
+        return Err("At least one mirror is required".into());
     }
+    let files = files
+        .par_iter()
+        .map(|path| generate_file(path, options))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(MetalinkDoc { files })
+    Ok(MetalinkDoc {
+        version: MetalinkVersion::V4,
+        files,
+        generator: Some(format!("KGet/{}", env!("CARGO_PKG_VERSION"))),
+        origin: options.origin.clone(),
+        published: Some(rfc3339_now()),
+    })
+}
+
+fn generate_file(path: &Path, options: &GenerateOptions) -> Result<MetalinkFile, Box<dyn Error + Send + Sync>> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("{} has no usable file name", path.display()))?
+        .to_string();
+    let size = std::fs::metadata(path)?.len();
+
+    let algorithms = if options.algorithms.is_empty() {
+        vec![ChecksumAlgorithm::Sha256]
+    } else {
+        options.algorithms.clone()
+    };
+    let hashes = compute_checksums(path, &algorithms)?;
+    let find = |algo: ChecksumAlgorithm| hashes.iter().find(|(a, _)| *a == algo).map(|(_, h)| h.clone());
+
+    let length = options.piece_length.unwrap_or_else(|| auto_piece_length(size));
+    let piece_hashes = compute_piece_hashes(path, length, &ChecksumAlgorithm::Sha256)?;
+    let pieces = (!piece_hashes.is_empty()).then(|| MetalinkPieces {
+        length,
+        hash_type: "sha-256".to_string(),
+        hashes: piece_hashes,
+    });
+
+    let signature = if options.signature {
+        let sig_path = PathBuf::from(format!("{}.asc", path.display()));
+        let body = std::fs::read_to_string(&sig_path)
+            .map_err(|e| format!("Cannot read signature {}: {}", sig_path.display(), e))?;
+        Some(MetalinkSignature {
+            mediatype: "application/pgp-signature".to_string(),
+            body: body.trim().to_string(),
+        })
+    } else {
+        None
+    };
+
+    let urls = options
+        .mirrors
+        .iter()
+        .map(|m| MetalinkUrl {
+            url: format!("{}/{}", m.base_url.trim_end_matches('/'), urlencoding::encode(&name)),
+            priority: m.priority.unwrap_or(999),
+            location: m.location.as_ref().map(|l| l.trim().to_ascii_lowercase()),
+        })
+        .collect();
+
+    Ok(MetalinkFile {
+        sha256: find(ChecksumAlgorithm::Sha256),
+        sha512: find(ChecksumAlgorithm::Sha512),
+        md5: find(ChecksumAlgorithm::Md5),
+        name,
+        size: Some(size),
+        hashes,
+        pieces,
+        urls,
+        metaurls: Vec::new(),
+        identity: None,
+        version: None,
+        description: None,
+        publisher: None,
+        languages: Vec::new(),
+        os: Vec::new(),
+        signature,
+    })
+}
+
+/// Smallest power of two from 256 KiB that keeps a file under 2048 pieces.
+fn auto_piece_length(size: u64) -> u64 {
+    let mut length = 256 * 1024;
+    while size.div_ceil(length) > 2048 {
+        length *= 2;
+    }
+    length
+}
+
+/// Current UTC time as an RFC 3339 timestamp, the format of `<published>`.
+fn rfc3339_now() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = crate::queue::civil_date(secs / 86_400);
+    let t = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, t / 3600, t % 3600 / 60, t % 60
+    )
+}
+
+/// Hash type token RFC 5854 uses (the IANA textual names where they exist).
+fn hash_type_name(algo: &ChecksumAlgorithm) -> &'static str {
+    match algo {
+        ChecksumAlgorithm::Sha256 => "sha-256",
+        ChecksumAlgorithm::Sha384 => "sha-384",
+        ChecksumAlgorithm::Sha512 => "sha-512",
+        ChecksumAlgorithm::Sha1 => "sha-1",
+        other => other.name(),
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl MetalinkDoc {
+    /// Serialize as a Metalink 4 (RFC 5854) document.
+    ///
+    /// Metalink 3 manifests are converted on the way out, so this also
+    /// upgrades an old `.metalink` to `.meta4`.
+    pub fn to_xml(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(xml, r#"<metalink xmlns="{}">"#, METALINK4_NS);
+        if let Some(generator) = &self.generator {
+            let _ = writeln!(xml, "  <generator>{}</generator>", xml_escape(generator));
+        }
+        if let Some(origin) = &self.origin {
+            let _ = writeln!(xml, "  <origin>{}</origin>", xml_escape(origin));
+        }
+        if let Some(published) = &self.published {
+            let _ = writeln!(xml, "  <published>{}</published>", xml_escape(published));
+        }
+        for file in &self.files {
+            let _ = writeln!(xml, r#"  <file name="{}">"#, xml_escape(&file.name));
+            let text = [
+                ("description", &file.description),
+                ("identity", &file.identity),
+                ("version", &file.version),
+            ];
+            for (tag, value) in text {
+                if let Some(value) = value {
+                    let _ = writeln!(xml, "    <{tag}>{}</{tag}>", xml_escape(value));
+                }
+            }
+            if let Some(publisher) = &file.publisher {
+                let _ = writeln!(xml, r#"    <publisher name="{}"/>"#, xml_escape(publisher));
+            }
+            for language in &file.languages {
+                let _ = writeln!(xml, "    <language>{}</language>", xml_escape(language));
+            }
+            for os in &file.os {
+                let _ = writeln!(xml, "    <os>{}</os>", xml_escape(os));
+            }
+            if let Some(size) = file.size {
+                let _ = writeln!(xml, "    <size>{}</size>", size);
+            }
+            for (algo, hash) in &file.hashes {
+                let _ = writeln!(xml, r#"    <hash type="{}">{}</hash>"#, hash_type_name(algo), xml_escape(hash));
+            }
+            if let Some(pieces) = &file.pieces {
+                let _ = writeln!(xml, r#"    <pieces length="{}" type="{}">"#, pieces.length, xml_escape(&pieces.hash_type));
+                for hash in &pieces.hashes {
+                    let _ = writeln!(xml, "      <hash>{}</hash>", xml_escape(hash));
+                }
+                let _ = writeln!(xml, "    </pieces>");
+            }
+            if let Some(signature) = &file.signature {
+                let _ = writeln!(
+                    xml,
+                    r#"    <signature mediatype="{}">{}</signature>"#,
+                    xml_escape(&signature.mediatype),
+                    xml_escape(&signature.body)
+                );
+            }
+            for metaurl in &file.metaurls {
+                let _ = write!(xml, r#"    <metaurl mediatype="{}""#, xml_escape(&metaurl.mediatype));
+                if metaurl.priority != 999 {
+                    let _ = write!(xml, r#" priority="{}""#, metaurl.priority);
+                }
+                if let Some(name) = &metaurl.name {
+                    let _ = write!(xml, r#" name="{}""#, xml_escape(name));
+                }
+                let _ = writeln!(xml, ">{}</metaurl>", xml_escape(&metaurl.url));
+            }
+            for url in &file.urls {
+                let _ = write!(xml, "    <url");
+                if let Some(location) = &url.location {
+                    let _ = write!(xml, r#" location="{}""#, xml_escape(location));
+                }
+                if url.priority != 999 {
+                    let _ = write!(xml, r#" priority="{}""#, url.priority);
+                }
+                let _ = writeln!(xml, ">{}</url>", xml_escape(&url.url));
+            }
+            let _ = writeln!(xml, "  </file>");
+        }
+        xml.push_str("</metalink>\n");
+        xml
+    }
+}
+
+// ============================================================================
+// Metalink/HTTP (RFC 6249)
+// ============================================================================
+
+/// Mirrors and digests a server advertised in the headers of an ordinary
+/// file response: `Link: <…>; rel=duplicate` plus `Digest`/`Repr-Digest`.
+#[derive(Debug, Clone, Default)]
+pub struct HttpMetalink {
+    pub mirrors: Vec<MetalinkUrl>,
+    pub digests: Vec<(ChecksumAlgorithm, String)>,
+}
+
+impl HttpMetalink {
+    /// Read the headers of one response; relative links are resolved
+    /// against `base`, the URL that was requested.
+    pub fn from_headers(headers: &reqwest::header::HeaderMap, base: &str) -> Self {
+        let base = reqwest::Url::parse(base).ok();
+        let mut mirrors = Vec::new();
+        for value in headers.get_all(reqwest::header::LINK).iter().filter_map(|v| v.to_str().ok()) {
+            for (target, params) in parse_link_header(value) {
+                let param = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+                let is_duplicate = param("rel")
+                    .is_some_and(|rel| rel.split_ascii_whitespace().any(|r| r.eq_ignore_ascii_case("duplicate")));
+                if !is_duplicate {
+                    continue;
+                }
+                let url = match &base {
+                    Some(base) => base.join(&target).map(String::from).unwrap_or(target),
+                    None => target,
+                };
+                // `pref` marks mirrors to try before any unranked one.
+                let priority = param("pri")
+                    .and_then(|p| p.trim().parse::<u32>().ok())
+                    .unwrap_or(if param("pref").is_some() { 1 } else { 999 });
+                mirrors.push(MetalinkUrl {
+                    url,
+                    priority,
+                    location: param("geo").map(|g| g.trim().to_ascii_lowercase()),
+                });
+            }
+        }
+        Self { mirrors, digests: crate::checksum::response_digests(headers, false) }
+    }
+
+    /// Add what another response (e.g. a later redirect hop) announced.
+    pub fn merge(&mut self, other: HttpMetalink) {
+        for mirror in other.mirrors {
+            if !self.mirrors.iter().any(|m| m.url == mirror.url) {
+                self.mirrors.push(mirror);
+            }
+        }
+        for (algo, hash) in other.digests {
+            if !self.digests.iter().any(|(a, _)| *a == algo) {
+                self.digests.push((algo, hash));
+            }
+        }
+    }
+
+    /// Whether the server listed any mirror, i.e. multi-mirror mode applies.
+    pub fn has_mirrors(&self) -> bool {
+        !self.mirrors.is_empty()
+    }
+
+    /// Describe `url` as a Metalink file: the advertised mirrors first, then
+    /// `url` itself as the last resort.
+    pub fn into_file(self, url: &str, size: Option<u64>) -> MetalinkFile {
+        let name = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|u| u.path_segments()?.next_back().map(str::to_string))
+            .and_then(|n| urlencoding::decode(&n).ok().map(|n| n.into_owned()))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "download".to_string());
+        let last_resort = self.mirrors.iter().map(|m| m.priority).max().unwrap_or(0).saturating_add(1);
+        let mut urls = self.mirrors;
+        if !urls.iter().any(|m| m.url == url) {
+            urls.push(MetalinkUrl { url: url.to_string(), priority: last_resort, location: None });
+        }
+        urls.sort_by_key(|u| u.priority);
+        let find = |algo: ChecksumAlgorithm| self.digests.iter().find(|(a, _)| *a == algo).map(|(_, h)| h.clone());
+
+        MetalinkFile {
+            name,
+            size,
+            sha256: find(ChecksumAlgorithm::Sha256),
+            sha512: find(ChecksumAlgorithm::Sha512),
+            md5: find(ChecksumAlgorithm::Md5),
+            hashes: self.digests,
+            pieces: None,
+            urls,
+            metaurls: Vec::new(),
+            identity: None,
+            version: None,
+            description: None,
+            publisher: None,
+            languages: Vec::new(),
+            os: Vec::new(),
+            signature: None,
+        }
+    }
+}
+
+/// Split a `Link` header value (RFC 8288) into targets and lowercase-named
+/// parameters; valueless parameters such as `pref` get an empty value.
+fn parse_link_header(value: &str) -> Vec<(String, Vec<(String, String)>)> {
+    // Split on commas outside <…> and quoted strings.
+    let mut links = Vec::new();
+    let (mut start, mut in_angle, mut in_quote) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            '<' if !in_quote => in_angle = true,
+            '>' if !in_quote => in_angle = false,
+            '"' if !in_angle => in_quote = !in_quote,
+            ',' if !in_angle && !in_quote => {
+                links.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    links.push(&value[start..]);
+
+    links
+        .into_iter()
+        .filter_map(|link| {
+            let link = link.trim();
+            let (target, rest) = link.strip_prefix('<')?.split_once('>')?;
+            let params = rest
+                .split(';')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| match p.split_once('=') {
+                    Some((k, v)) => (k.trim().to_ascii_lowercase(), v.trim().trim_matches('"').to_string()),
+                    None => (p.to_ascii_lowercase(), String::new()),
+                })
+                .collect();
+            Some((target.trim().to_string(), params))
+        })
+        .collect()
 }
 
 // ============================================================================
@@ -182,12 +887,39 @@ pub fn download_metalink(
     quiet: bool,
     proxy: ProxyConfig,
     optimizer: Optimizer,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    download_metalink_with(source, output_dir, quiet, proxy, optimizer, &MetalinkConfig::default())
+}
+
+/// Like [`download_metalink`], but only downloads the files matching the
+/// OS and language in `prefs` and tries mirrors in `prefs.country` first.
+///
+/// Fails if no file matches.
+pub fn download_metalink_with(
+    source: &str,
+    output_dir: &str,
+    quiet: bool,
+    proxy: ProxyConfig,
+    optimizer: Optimizer,
+    prefs: &MetalinkConfig,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let xml = fetch_manifest(source, quiet, &proxy)?;
     let manifest = parse(&xml)?;
+    let files = manifest.select(prefs);
 
+    if files.is_empty() {
+        return Err(format!(
+            "No file in the Metalink matches os={} language={}",
+            prefs.os.as_deref().unwrap_or("any"),
+            prefs.language.as_deref().unwrap_or("any")
+        )
+        .into());
+    }
     if !quiet {
-        println!("Metalink: {} file(s) to download", manifest.files.len());
+        println!("Metalink: {} file(s) to download", files.len());
+        if files.len() < manifest.files.len() {
+            println!("  ({} skipped for another OS or language)", manifest.files.len() - files.len());
+        }
     }
 
     let out_dir = PathBuf::from(output_dir);
@@ -195,11 +927,28 @@ pub fn download_metalink(
         std::fs::create_dir_all(&out_dir)?;
     }
 
-    for file in &manifest.files {
-        download_one_file(file, &out_dir, quiet, &proxy, &optimizer)?;
-    }
+    let mut session = Session::new(out_dir, quiet, proxy, optimizer);
+    session.country = prefs.country.clone();
+    session.concurrency = prefs.concurrent_files.unwrap_or(DEFAULT_CONCURRENT_FILES);
+    session.source = Some(source.to_string());
+    let total = files.len();
+    let mut failed: Vec<(String, KgetError)> = session
+        .run(&files)
+        .into_iter()
+        .filter_map(|r| r.result.err().map(|e| (r.name, e)))
+        .collect();
 
-    Ok(())
+    if !quiet {
+        println!("Metalink: {} of {} file(s) downloaded", total - failed.len(), total);
+    }
+    match failed.len() {
+        0 => Ok(()),
+        1 => Err(failed.remove(0).1.into()),
+        n => {
+            let list: Vec<String> = failed.iter().map(|(name, e)| format!("{}: {}", name, e)).collect();
+            Err(format!("{} Metalink files failed: {}", n, list.join("; ")).into())
+        }
+    }
 }
 
 // ============================================================================
@@ -207,7 +956,7 @@ pub fn download_metalink(
 // ============================================================================
 
 /// Fetch the metalink manifest XML — from disk or over HTTP.
-fn fetch_manifest(
+pub(crate) fn fetch_manifest(
     source: &str,
     quiet: bool,
     proxy: &ProxyConfig,
@@ -233,7 +982,7 @@ fn fetch_manifest(
 }
 
 /// Build a blocking reqwest client with optional proxy support.
-fn build_http_client(
+pub(crate) fn build_http_client(
     proxy: &ProxyConfig,
 ) -> Result<reqwest::blocking::Client, Box<dyn Error + Send + Sync>> {
     let mut builder = reqwest::blocking::Client::builder()
@@ -259,110 +1008,264 @@ fn build_http_client(
     Ok(builder.build()?)
 }
 
-/// Download a single metalink file, trying each mirror in order.
-fn download_one_file(
-    file: &MetalinkFile,
-    output_dir: &Path,
-    quiet: bool,
-    proxy: &ProxyConfig,
-    optimizer: &Optimizer,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let safe_name = sanitise_filename(&file.name);
-    let dest = output_dir.join(&safe_name);
+/// Files of one manifest downloaded at the same time unless configured.
+pub(crate) const DEFAULT_CONCURRENT_FILES: usize = 3;
 
-    if !quiet {
-        println!(
-            "\nDownloading '{}' ({} mirror(s)){}",
-            safe_name,
-            file.urls.len(),
-            file.size
-                .map(|s| format!("  [{} bytes]", s))
-                .unwrap_or_default()
-        );
+/// Shared settings for downloading the files of one manifest, several at once.
+pub(crate) struct Session {
+    pub(crate) output_dir: PathBuf,
+    pub(crate) quiet: bool,
+    pub(crate) proxy: ProxyConfig,
+    pub(crate) optimizer: Optimizer,
+    /// Mirrors in this country are tried first.
+    pub(crate) country: Option<String>,
+    pub(crate) concurrency: usize,
+    pub(crate) cancel: Arc<AtomicBool>,
+    pub(crate) events: Option<mpsc::Sender<FileEvent>>,
+    /// Where the manifest came from, reported as the checksum source.
+    pub(crate) source: Option<String>,
+}
+
+impl Session {
+    pub(crate) fn new(output_dir: impl Into<PathBuf>, quiet: bool, proxy: ProxyConfig, optimizer: Optimizer) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            quiet,
+            proxy,
+            optimizer,
+            country: None,
+            concurrency: DEFAULT_CONCURRENT_FILES,
+            cancel: Arc::new(AtomicBool::new(false)),
+            events: None,
+            source: None,
+        }
     }
 
-    let mut last_error: Option<Box<dyn Error + Send + Sync>> = None;
+    /// Download `files` into the output directory with at most
+    /// `concurrency` in flight; one result per file, in manifest order.
+    ///
+    /// Workers are plain threads so each file's chunked download still gets
+    /// the whole rayon pool.
+    pub(crate) fn run(&self, files: &[&MetalinkFile]) -> Vec<MetalinkResult> {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<MetalinkResult>>> = Mutex::new(files.iter().map(|_| None).collect());
+        let workers = self.concurrency.clamp(1, files.len().max(1));
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(file) = files.get(index) else { break };
+                        let dest = self.output_dir.join(sanitise_filename(&file.name));
+                        let result = self.download_to(file, &dest);
+                        results.lock().expect("Metalink results mutex was poisoned")[index] = Some(result);
+                    }
+                });
+            }
+        });
+        results
+            .into_inner()
+            .expect("Metalink results mutex was poisoned")
+            .into_iter()
+            .flatten()
+            .collect()
+    }
 
-    for (idx, mirror) in file.urls.iter().enumerate() {
-        if !quiet {
-            println!("  Mirror {}/{}: {}", idx + 1, file.urls.len(), mirror.url);
+    /// Download one file entry to `dest`, trying each mirror in order and
+    /// verifying the strongest hash the entry lists.
+    pub(crate) fn download_to(&self, file: &MetalinkFile, dest: &Path) -> MetalinkResult {
+        let emit = |event| self.emit(&file.name, event);
+        let (mirror, result) = match self.fetch(file, dest, &emit) {
+            Ok((mirror, result)) => {
+                emit(DownloadEvent::Completed { path: result.path.clone(), sha256: result.checksums.sha256.clone() });
+                (mirror, Ok(result))
+            }
+            Err(e) => {
+                emit(DownloadEvent::Error(e.to_string()));
+                (None, Err(e))
+            }
+        };
+        MetalinkResult { name: file.name.clone(), mirror, result }
+    }
+
+    fn fetch(
+        &self,
+        file: &MetalinkFile,
+        dest: &Path,
+        emit: &dyn Fn(DownloadEvent),
+    ) -> Result<(Option<String>, DownloadResult), KgetError> {
+        let note = |msg: String| {
+            if !self.quiet {
+                println!("  {}: {}", file.name, msg);
+            }
+            emit(DownloadEvent::Status(msg));
+        };
+        let start = Instant::now();
+        let mirrors = file.mirrors(self.country.as_deref());
+        note(format!(
+            "{} mirror(s){}",
+            mirrors.len(),
+            file.size.map(|s| format!(", {} bytes", s)).unwrap_or_default()
+        ));
+
+        let digest = file.best_digest();
+        let store = self.optimizer.content_store();
+        let store_digest = match &digest {
+            Some((algo, hash)) if algo.is_cryptographic() => store.as_ref().map(|s| (s, algo, *hash)),
+            _ => None,
+        };
+        if let Some((store, algo, hash)) = store_digest
+            && let Some(kind) = store.materialize(algo, hash, dest)?
+        {
+            note(format!("Reused from content store ({})", kind));
+            return Ok((None, self.finish(dest, start, digest, true)));
         }
 
-        match download_from_mirror(&mirror.url, &dest, quiet, proxy, optimizer) {
-            Ok(()) => {
-                if !quiet {
-                    println!("  Download OK");
+        let mut last_error = None;
+        for (idx, mirror) in mirrors.iter().enumerate() {
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err(KgetError::Cancelled);
+            }
+            note(format!("Mirror {}/{}: {}", idx + 1, mirrors.len(), mirror.url));
+            if let Err(e) = self.fetch_mirror(&mirror.url, dest, file) {
+                if self.cancel.load(Ordering::Relaxed) {
+                    return Err(KgetError::Cancelled);
                 }
+                note(format!("Mirror failed: {}", e));
+                last_error = Some(e);
+                continue;
+            }
 
-                // Verify hash if the manifest provides one.
-                if let Some((hash_type, expected)) = file.best_hash() {
-                    if hash_type == "sha-256" {
-                        if !quiet {
-                            println!("  Verifying SHA-256...");
-                        }
-                        match verify_file_sha256(&dest, Some(expected), None) {
-                            Ok(_) => {
-                                if !quiet {
-                                    println!("  SHA-256 OK ✓");
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("  SHA-256 mismatch: {}", e);
-                                // Remove corrupted file and try next mirror.
-                                let _ = std::fs::remove_file(&dest);
-                                last_error = Some(e);
-                                continue;
-                            }
+            // Verify hash if the manifest provides one.
+            if let Some((algo, expected)) = &digest {
+                let label = algo.name().to_uppercase();
+                match compute_checksum(dest, algo) {
+                    Ok(got) if got == *expected => {
+                        note(format!("{} OK", label));
+                        if let Some((store, algo, hash)) = store_digest
+                            && let Err(e) = store.insert(dest, algo, hash)
+                        {
+                            note(format!("Could not add to content store: {}", e));
                         }
-                    } else if !quiet {
-                        println!(
-                            "  Skipping {} verification (only sha-256 supported inline)",
-                            hash_type
-                        );
+                    }
+                    result => {
+                        let e = match result {
+                            Ok(got) => KgetError::ChecksumMismatch {
+                                algorithm: algo.name().to_string(),
+                                expected: expected.to_string(),
+                                got,
+                            },
+                            Err(e) => e,
+                        };
+                        note(e.to_string());
+                        // Remove corrupted file and try next mirror.
+                        let _ = std::fs::remove_file(dest);
+                        last_error = Some(e);
+                        continue;
                     }
                 }
-
-                return Ok(());
-            }
-            Err(e) => {
-                if !quiet {
-                    eprintln!("  Mirror failed: {}", e);
-                }
-                last_error = Some(e);
             }
+            return Ok((Some(mirror.url.clone()), self.finish(dest, start, digest, false)));
+        }
+
+        Err(last_error.unwrap_or_else(|| KgetError::NotFound(format!("no mirror for {}", file.name))))
+    }
+
+    /// Download one mirror URL to `dest` with `AdvancedDownloader`.
+    fn fetch_mirror(&self, url: &str, dest: &Path, file: &MetalinkFile) -> Result<(), KgetError> {
+        let output_path = dest
+            .to_str()
+            .ok_or_else(|| KgetError::Other("Output path contains non-UTF-8 characters".to_string()))?
+            .to_string();
+
+        // Progress bars of concurrent files would draw over each other.
+        let quiet = self.quiet || self.concurrency > 1;
+        let mut dl = AdvancedDownloader::new(url.to_string(), output_path, quiet, self.proxy.clone(), self.optimizer.clone())?;
+        // We are already walking the mirror list; don't re-enter it.
+        dl.set_follow_mirror_links(false);
+        dl.set_cancel_token(self.cancel.clone());
+        if let Some(tx) = &self.events {
+            let (tx_progress, tx_status) = (tx.clone(), tx.clone());
+            let (name, size, started) = (file.name.clone(), file.size, Instant::now());
+            dl.set_progress_callback(move |p| {
+                let done = size.map_or(0.0, |s| s as f64 * p as f64);
+                let elapsed = started.elapsed().as_secs_f64();
+                let speed_bps = if elapsed > 0.0 { (done / elapsed) as u64 } else { 0 };
+                let eta_secs = size
+                    .filter(|_| speed_bps > 0)
+                    .map(|s| ((s as f64 - done) / speed_bps as f64) as u64);
+                let _ = tx_progress.send(FileEvent {
+                    file: name.clone(),
+                    event: DownloadEvent::Progress { percent: p as f64 * 100.0, speed_bps, eta_secs },
+                });
+            });
+            let name = file.name.clone();
+            dl.set_status_callback(move |msg| {
+                let _ = tx_status.send(FileEvent { file: name.clone(), event: DownloadEvent::Status(msg) });
+            });
+        }
+        Ok(dl.download()?)
+    }
+
+    fn finish(
+        &self,
+        dest: &Path,
+        start: Instant,
+        digest: Option<(ChecksumAlgorithm, &str)>,
+        from_store: bool,
+    ) -> DownloadResult {
+        let duration = start.elapsed();
+        let bytes_downloaded = std::fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+        let avg_speed_bps = if duration.as_secs() > 0 {
+            bytes_downloaded / duration.as_secs()
+        } else {
+            bytes_downloaded
+        };
+        let checksum_source = digest.as_ref().and(self.source.clone());
+        let mut checksums = ComputedChecksums::default();
+        if let Some((algo, hash)) = digest {
+            *checksums.slot(&algo) = Some(hash.to_string());
+        }
+        DownloadResult {
+            path: dest.display().to_string(),
+            bytes_downloaded,
+            avg_speed_bps,
+            duration,
+            connections_used: if from_store { 0 } else { self.optimizer.max_connections() },
+            checksums,
+            from_store,
+            signature: None,
+            checksum_source,
         }
     }
 
-    Err(last_error.unwrap_or_else(|| "All mirrors failed".into()))
+    fn emit(&self, file: &str, event: DownloadEvent) {
+        if let Some(tx) = &self.events {
+            let _ = tx.send(FileEvent { file: file.to_string(), event });
+        }
+    }
 }
 
-/// Attempt to download a single URL to `dest` using AdvancedDownloader.
-fn download_from_mirror(
-    url: &str,
+/// Download one Metalink file entry to `dest`, trying each mirror in order
+/// (those in `country` first) and verifying the strongest hash it lists.
+pub fn download_file(
+    file: &MetalinkFile,
     dest: &Path,
     quiet: bool,
     proxy: &ProxyConfig,
     optimizer: &Optimizer,
+    country: Option<&str>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    use crate::advanced_download::AdvancedDownloader;
-
-    let output_path = dest
-        .to_str()
-        .ok_or("Output path contains non-UTF-8 characters")?
-        .to_string();
-
-    let dl = AdvancedDownloader::new(
-        url.to_string(),
-        output_path,
-        quiet,
-        proxy.clone(),
-        optimizer.clone(),
-    )?;
-    dl.download()
+    let output_dir = dest.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut session = Session::new(output_dir, quiet, proxy.clone(), optimizer.clone());
+    session.country = country.map(str::to_string);
+    session.concurrency = 1;
+    session.download_to(file, dest).result?;
+    Ok(())
 }
 
 /// Remove characters that are unsafe in filenames across platforms.
-fn sanitise_filename(name: &str) -> String {
+pub(crate) fn sanitise_filename(name: &str) -> String {
     let cleaned: String = name
         .chars()
         .map(|c| match c {
@@ -21,13 +21,13 @@
 //! }
 //! ```
 
+use crate::cache::{CacheEntry, CacheStats, HttpCache, PruneReport, VerifyReport};
 use crate::config::OptimizationConfig;
+use crate::store::ContentStore;
 use flate2::write::{GzDecoder, GzEncoder};
-use lz4::block::{CompressionMode, compress};
 use std::error::Error;
-use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::time::Duration;
 
 /// Download optimizer for compression, caching, and speed limiting.
 ///
@@ -105,12 +105,20 @@ impl Optimizer {
                 encoder.write_all(data)?;
                 encoder.finish()?
             }
-            4..=6 => compress(data, Some(CompressionMode::FAST(0)), true)?,
+            4..=6 => {
+                let mut encoder = lz4::EncoderBuilder::new()
+                    .level(self.config.compression_level as u32)
+                    .build(Vec::new())?;
+                encoder.write_all(data)?;
+                let (out, result) = encoder.finish();
+                result?;
+                out
+            }
             7..=9 => {
                 let mut encoder = brotli::CompressorWriter::new(
                     Vec::new(),
-                    self.config.compression_level as usize,
                     4096,
+                    self.config.compression_level as u32,
                     22,
                 );
                 encoder.write_all(data)?;
@@ -121,9 +129,10 @@ impl Optimizer {
         Ok(compressed)
     }
 
-    /// Decompress data using the appropriate algorithm based on the file header
+    /// Decompress data produced by [`compress`](Self::compress).
     ///
-    /// Supports Gzip, Brotli, and LZ4
+    /// Gzip and LZ4 frames are recognised by their magic numbers; anything
+    /// else is treated as Brotli, which has no header.
     pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
         if !self.config.compression {
             return Ok(data.to_vec());
@@ -133,16 +142,24 @@ impl Optimizer {
             let mut decoder = GzDecoder::new(Vec::new());
             decoder.write_all(data)?;
             decompressed = decoder.finish()?;
-        } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
-            let mut decoder = brotli::Decompressor::new(data, 4096);
+        } else if data.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+            let mut decoder = lz4::Decoder::new(data)?;
             decoder.read_to_end(&mut decompressed)?;
         } else {
-            let mut decoder = lz4::Decoder::new(data)?;
+            let mut decoder = brotli::Decompressor::new(data, 4096);
             decoder.read_to_end(&mut decompressed)?;
         }
         Ok(decompressed)
     }
 
+    /// The HTTP response cache, if caching is enabled.
+    ///
+    /// [`download`](crate::download()) and [`DownloadBuilder`](crate::DownloadBuilder)
+    /// consult it automatically; see [`crate::cache`] for the freshness rules.
+    pub fn http_cache(&self) -> Option<HttpCache> {
+        self.config.cache_enabled.then(|| self.cache())
+    }
+
     /// Retrieve a file from the cache if it exists.
     ///
     /// # Arguments
@@ -155,59 +172,62 @@ impl Optimizer {
     /// - `Ok(None)` if caching is disabled or file doesn't exist
     /// - `Err` on I/O errors
     pub fn get_cached_file(&self, url: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
-        if !self.config.cache_enabled {
-            return Ok(None);
-        }
-
-        let _cache_dir = self.config.cache_dir.as_str();
-        let cache_path = self.get_cache_path(url)?;
-        if cache_path.exists() {
-            let mut file = File::open(cache_path)?;
-            let mut contents = Vec::new();
-            file.read_to_end(&mut contents)?;
-            return Ok(Some(contents));
+        match self.http_cache() {
+            Some(cache) => Ok(cache.read(url)?),
+            None => Ok(None),
         }
-        Ok(None)
     }
 
     /// Store a file in the cache.
     ///
-    /// Does nothing if caching is disabled.
+    /// Does nothing if caching is disabled. The entry carries no HTTP
+    /// freshness information, so downloads of `url` will not be served from it.
     pub fn cache_file(&self, url: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
-        if !self.config.cache_enabled {
-            return Ok(());
-        }
-        let cache_path = self.get_cache_path(url)?;
-        if let Some(parent) = cache_path.parent() {
-            fs::create_dir_all(parent)?;
+        if let Some(cache) = self.http_cache() {
+            cache.put(url, data)?;
         }
-        let mut file = File::create(cache_path)?;
-        file.write_all(data)?;
         Ok(())
     }
 
-    /// Generate the cache file path for a URL using a simple hash.
-    fn get_cache_path(&self, url: &str) -> Result<PathBuf, Box<dyn Error>> {
-        let mut cache_dir = PathBuf::from(if self.config.cache_dir.is_empty() {
-            "~/.cache/kget".to_string()
-        } else {
-            self.config.cache_dir.clone()
-        });
+    // ── Cache management ──
+    //
+    // These work on `cache_dir` whether or not `cache_enabled` is set, so a
+    // disabled cache can still be inspected and cleaned up.
 
-        if cache_dir.starts_with("~") {
-            if let Some(home) = dirs::home_dir() {
-                cache_dir = home.join(cache_dir.strip_prefix("~").unwrap());
-            }
-        }
+    /// All entries in the HTTP cache.
+    pub fn cache_entries(&self) -> Vec<CacheEntry> {
+        self.cache().entries()
+    }
 
-        // Simple hash function to generate a unique filename
-        let mut hash = 0u64;
-        for byte in url.bytes() {
-            hash = hash.wrapping_mul(31).wrapping_add(byte as u64);
-        }
+    /// The cache entry for `url`, if any.
+    pub fn cache_info(&self, url: &str) -> Option<CacheEntry> {
+        self.cache().lookup(url)
+    }
+
+    /// Cumulative hit/miss counters of the HTTP cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache().stats()
+    }
+
+    /// Check every cache entry against its recorded size and SHA-256,
+    /// deleting corrupted ones if `remove_corrupted` is set.
+    pub fn verify_cache(&self, remove_corrupted: bool) -> VerifyReport {
+        self.cache().verify(remove_corrupted)
+    }
 
-        cache_dir.push(format!("{:x}", hash));
-        Ok(cache_dir)
+    /// Remove entries unused for longer than `older_than`, then evict least
+    /// recently used entries down to `max_size` (default: `cache_max_size`).
+    pub fn prune_cache(&self, older_than: Option<Duration>, max_size: Option<u64>) -> PruneReport {
+        self.cache().prune(older_than, max_size)
+    }
+
+    /// Remove every cache entry.
+    pub fn clear_cache(&self) -> PruneReport {
+        self.cache().clear()
+    }
+
+    fn cache(&self) -> HttpCache {
+        HttpCache::from_config(&self.config)
     }
 
     /// Get the peer connection limit for torrent downloads.
@@ -220,6 +240,14 @@ impl Optimizer {
         self.config.max_connections.clamp(1, 32)
     }
 
+    /// The content-addressed store of verified downloads, if enabled.
+    pub fn content_store(&self) -> Option<ContentStore> {
+        if !self.config.store_enabled {
+            return None;
+        }
+        Some(ContentStore::from_config(&self.config))
+    }
+
     /// Check if compression is enabled.
     pub fn is_compression_enabled(&self) -> bool {
         self.config.compression
@@ -252,8 +280,11 @@ impl Default for Optimizer {
                 compression_level: 6,
                 cache_enabled: true,
                 cache_dir: "~/.cache/kget".to_string(),
+                cache_max_size: crate::cache::DEFAULT_MAX_SIZE,
                 speed_limit: None,
                 max_connections: 4,
+                store_enabled: false,
+                store_dir: crate::store::DEFAULT_STORE_DIR.to_string(),
             },
             speed_limit: None,
         }
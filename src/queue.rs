@@ -231,7 +231,7 @@ pub fn format_unix(secs: u64) -> String {
 }
 
 /// Convert days since Unix epoch (1970-01-01) to (year, month, day).
-fn civil_date(days: u64) -> (u64, u64, u64) {
+pub(crate) fn civil_date(days: u64) -> (u64, u64, u64) {
     // Shift epoch to 0000-03-01 to simplify leap-year arithmetic.
     let z = days as i64 + 719468;
     let era: i64 = if z >= 0 { z } else { z - 146096 } / 146097;
@@ -0,0 +1,604 @@
+//! S3-compatible object storage (`s3://bucket/key`).
+//!
+//! Requests are signed with AWS Signature Version 4 using credentials from
+//! [`S3Config`], the standard `AWS_*` environment variables or
+//! `~/.aws/credentials` (in that order); with no credentials at all, objects
+//! are fetched anonymously. Setting a custom endpoint switches to path-style
+//! addressing, so MinIO, Ceph RGW and other S3-compatible servers work too.
+//!
+//! Object GETs are presigned and handed to [`AdvancedDownloader`], so large
+//! objects are fetched with parallel ranged requests like any HTTP download.
+//! A URL that ends in `/` (or names only a bucket) is treated as a prefix:
+//! every object under it is listed with ListObjectsV2 and downloaded into the
+//! output directory, keeping the key layout.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use kget::s3::S3Downloader;
+//! use kget::{ProxyConfig, Optimizer};
+//!
+//! let downloader = S3Downloader::new(
+//!     "s3://my-bucket/releases/app.tar.gz".to_string(),
+//!     "app.tar.gz".to_string(),
+//!     false,
+//!     ProxyConfig::default(),
+//!     Optimizer::new(),
+//! );
+//!
+//! downloader.download().unwrap();
+//! ```
+
+pub mod sigv4;
+
+pub use sigv4::Credentials;
+
+use crate::advanced_download::AdvancedDownloader;
+use crate::config::{ProxyConfig, S3Config};
+use crate::optimization::Optimizer;
+use crate::store::{self, OutputMode};
+use reqwest::blocking::{Client, Response};
+use reqwest::Method;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns `true` if the URL uses the `s3://` scheme.
+pub fn is_s3_url(url: &str) -> bool {
+    url.get(..5).is_some_and(|s| s.eq_ignore_ascii_case("s3://"))
+}
+
+/// Returns `true` if the `s3://` URL names a prefix (bucket root or a key
+/// ending in `/`) rather than a single object.
+pub fn is_prefix_url(url: &str) -> bool {
+    parse_s3_url(url).is_ok_and(|(_, key)| key.is_empty() || key.ends_with('/'))
+}
+
+/// Split `s3://bucket/key` into `(bucket, key)`; the key is percent-decoded.
+pub fn parse_s3_url(url: &str) -> Result<(String, String), Box<dyn Error + Send + Sync>> {
+    if !is_s3_url(url) {
+        return Err(format!("Not an s3:// URL: {}", url).into());
+    }
+    let rest = &url[5..];
+    let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return Err(format!("Missing bucket name in '{}'", url).into());
+    }
+    let key = urlencoding::decode(key)
+        .map_err(|e| format!("Invalid object key in '{}': {}", url, e))?;
+    Ok((bucket.to_string(), key.into_owned()))
+}
+
+/// An object returned by a prefix listing.
+#[derive(Debug, Clone)]
+pub struct S3Object {
+    /// Full object key
+    pub key: String,
+    /// Object size in bytes
+    pub size: u64,
+}
+
+/// Where requests for one bucket are sent.
+struct Endpoint {
+    /// `scheme://host[:port]`
+    base: String,
+    /// Value of the `Host` header, as signed
+    host: String,
+    /// `/bucket` for path-style addressing, empty for virtual-hosted style
+    bucket_path: String,
+}
+
+impl Endpoint {
+    fn object_path(&self, key: &str) -> String {
+        format!("{}/{}", self.bucket_path, sigv4::uri_encode(key, true))
+    }
+
+    fn bucket_root(&self) -> String {
+        if self.bucket_path.is_empty() {
+            "/".to_string()
+        } else {
+            self.bucket_path.clone()
+        }
+    }
+}
+
+/// Downloads objects from S3-compatible storage.
+pub struct S3Downloader {
+    url: String,
+    output: String,
+    quiet: bool,
+    proxy: ProxyConfig,
+    optimizer: Optimizer,
+    config: S3Config,
+}
+
+impl S3Downloader {
+    /// Create a new downloader for an `s3://bucket/key` or `s3://bucket/prefix/` URL.
+    ///
+    /// For a prefix URL, `output` is the destination directory.
+    pub fn new(
+        url: String,
+        output: String,
+        quiet: bool,
+        proxy: ProxyConfig,
+        optimizer: Optimizer,
+    ) -> Self {
+        Self { url, output, quiet, proxy, optimizer, config: S3Config::default() }
+    }
+
+    /// Override region, endpoint and credentials (normally `Config::s3`).
+    pub fn set_config(&mut self, config: S3Config) {
+        self.config = config;
+    }
+
+    /// Download the object, or every object under the prefix.
+    pub fn download(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let (bucket, key) = parse_s3_url(&self.url)?;
+        let client = self.build_client()?;
+        let endpoint = self.endpoint(&bucket)?;
+
+        if key.is_empty() || key.ends_with('/') {
+            return self.download_prefix(&client, &endpoint, &bucket, &key);
+        }
+
+        if !self.quiet {
+            println!("Fetching s3://{}/{} ...", bucket, key);
+        }
+        self.download_object(&client, &endpoint, &key, Path::new(&self.output))
+    }
+
+    /// List every object whose key starts with the URL's key.
+    pub fn list(&self) -> Result<Vec<S3Object>, Box<dyn Error + Send + Sync>> {
+        let (bucket, prefix) = parse_s3_url(&self.url)?;
+        let client = self.build_client()?;
+        let endpoint = self.endpoint(&bucket)?;
+        self.list_objects(&client, &endpoint, &prefix)
+    }
+
+    fn download_prefix(
+        &self,
+        client: &Client,
+        endpoint: &Endpoint,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let objects: Vec<S3Object> = self
+            .list_objects(client, endpoint, prefix)?
+            .into_iter()
+            .filter(|o| !o.key.ends_with('/'))
+            .collect();
+        if objects.is_empty() {
+            return Err(format!("No objects found under s3://{}/{}", bucket, prefix).into());
+        }
+        if !self.quiet {
+            println!("Found {} object(s) under s3://{}/{}", objects.len(), bucket, prefix);
+        }
+
+        let output_dir = Path::new(&self.output);
+        let total = objects.len();
+        let mut failed = 0;
+        for (i, object) in objects.iter().enumerate() {
+            let Some(relative) = relative_path(&object.key, prefix) else {
+                eprintln!("[SKIP] {} — unsafe key for a local path", object.key);
+                failed += 1;
+                continue;
+            };
+            if !self.quiet {
+                println!("\n[{}/{}] {} ({} bytes)", i + 1, total, object.key, object.size);
+            }
+            let dest = output_dir.join(relative);
+            if let Err(e) = self.download_object(client, endpoint, &object.key, &dest) {
+                eprintln!("[FAIL] {} — {}", object.key, e);
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(format!("{} of {} object(s) failed to download", failed, total).into());
+        }
+        Ok(())
+    }
+
+    fn download_object(
+        &self,
+        client: &Client,
+        endpoint: &Endpoint,
+        key: &str,
+        dest: &Path,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = endpoint.object_path(key);
+
+        // A signed HEAD surfaces S3's own error (NoSuchKey, AccessDenied, …)
+        // before the chunk engine starts probing the presigned URL.
+        let head = self.send(client, Method::HEAD, endpoint, &path, &[])?;
+        let size = head
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.parse::<u64>().ok());
+
+        if let Some(parent) = dest.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Ranged requests cannot describe an empty object.
+        if size == Some(0) {
+            store::open_output(dest, OutputMode::Create)?;
+            return Ok(());
+        }
+
+        let url = match self.credentials() {
+            Some(creds) => sigv4::presign_get(
+                &endpoint.base,
+                &endpoint.host,
+                &path,
+                &creds,
+                &self.region(),
+                &sigv4::amz_date(unix_now()),
+                self.config.presign_expiry_secs,
+            ),
+            None => format!("{}{}", endpoint.base, path),
+        };
+
+        let downloader = AdvancedDownloader::new(
+            url,
+            dest.to_string_lossy().into_owned(),
+            self.quiet,
+            self.proxy.clone(),
+            self.optimizer.clone(),
+        )?;
+        downloader.download()
+    }
+
+    /// ListObjectsV2, following continuation tokens until the listing is complete.
+    fn list_objects(
+        &self,
+        client: &Client,
+        endpoint: &Endpoint,
+        prefix: &str,
+    ) -> Result<Vec<S3Object>, Box<dyn Error + Send + Sync>> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ];
+            if let Some(t) = &token {
+                query.push(("continuation-token".to_string(), t.clone()));
+            }
+
+            let body = self
+                .send(client, Method::GET, endpoint, &endpoint.bucket_root(), &query)?
+                .text()?;
+            let page = parse_list_response(&body)?;
+            objects.extend(page.objects);
+
+            match page.next_token {
+                Some(next) if page.truncated => token = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    /// Send a request, signing it when credentials are available.
+    fn send(
+        &self,
+        client: &Client,
+        method: Method,
+        endpoint: &Endpoint,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let query_string = sigv4::canonical_query(query);
+        let url = if query_string.is_empty() {
+            format!("{}{}", endpoint.base, path)
+        } else {
+            format!("{}{}?{}", endpoint.base, path, query_string)
+        };
+
+        let mut request = client.request(method.clone(), &url);
+        if let Some(creds) = self.credentials() {
+            let headers = sigv4::sign_headers(
+                method.as_str(),
+                &endpoint.host,
+                path,
+                query,
+                &[],
+                sigv4::EMPTY_PAYLOAD_SHA256,
+                &creds,
+                &self.region(),
+                &sigv4::amz_date(unix_now()),
+            );
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+        }
+
+        let response = request.send()?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            return Err(describe_error(status, &body).into());
+        }
+        Ok(response)
+    }
+
+    fn build_client(&self) -> Result<Client, Box<dyn Error + Send + Sync>> {
+        let mut builder = Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .user_agent(concat!("KGet/", env!("CARGO_PKG_VERSION")));
+
+        if self.proxy.enabled
+            && let Some(proxy_url) = &self.proxy.url
+        {
+            let p = match self.proxy.proxy_type {
+                crate::config::ProxyType::Http => reqwest::Proxy::http(proxy_url),
+                crate::config::ProxyType::Https => reqwest::Proxy::https(proxy_url),
+                crate::config::ProxyType::Socks5 => reqwest::Proxy::all(proxy_url),
+            };
+            if let Ok(mut p) = p {
+                if let (Some(u), Some(pw)) = (&self.proxy.username, &self.proxy.password) {
+                    p = p.basic_auth(u, pw);
+                }
+                builder = builder.proxy(p);
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+
+    fn endpoint(&self, bucket: &str) -> Result<Endpoint, Box<dyn Error + Send + Sync>> {
+        let custom = self
+            .config
+            .endpoint
+            .clone()
+            .or_else(|| env_var("AWS_ENDPOINT_URL_S3"))
+            .or_else(|| env_var("AWS_ENDPOINT_URL"));
+
+        let Some(custom) = custom else {
+            let region = self.region();
+            // Dotted bucket names break the wildcard TLS certificate.
+            if self.config.path_style || bucket.contains('.') {
+                let host = format!("s3.{}.amazonaws.com", region);
+                return Ok(Endpoint {
+                    base: format!("https://{}", host),
+                    host,
+                    bucket_path: format!("/{}", sigv4::uri_encode(bucket, false)),
+                });
+            }
+            let host = format!("{}.s3.{}.amazonaws.com", bucket, region);
+            return Ok(Endpoint { base: format!("https://{}", host), host, bucket_path: String::new() });
+        };
+
+        let parsed = url::Url::parse(&custom)
+            .map_err(|e| format!("Invalid S3 endpoint '{}': {}", custom, e))?;
+        let host_name = parsed
+            .host_str()
+            .ok_or_else(|| format!("S3 endpoint '{}' has no host", custom))?;
+        let host = match parsed.port() {
+            Some(port) => format!("{}:{}", host_name, port),
+            None => host_name.to_string(),
+        };
+        Ok(Endpoint {
+            base: format!("{}://{}", parsed.scheme(), host),
+            host,
+            bucket_path: format!("/{}", sigv4::uri_encode(bucket, false)),
+        })
+    }
+
+    fn region(&self) -> String {
+        self.config
+            .region
+            .clone()
+            .or_else(|| env_var("AWS_REGION"))
+            .or_else(|| env_var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string())
+    }
+
+    /// Config keys, then environment, then the shared credentials file.
+    fn credentials(&self) -> Option<Credentials> {
+        if let (Some(id), Some(secret)) = (&self.config.access_key_id, &self.config.secret_access_key) {
+            return Some(Credentials {
+                access_key_id: id.clone(),
+                secret_access_key: secret.clone(),
+                session_token: None,
+            });
+        }
+        if let (Some(id), Some(secret)) = (env_var("AWS_ACCESS_KEY_ID"), env_var("AWS_SECRET_ACCESS_KEY")) {
+            return Some(Credentials {
+                access_key_id: id,
+                secret_access_key: secret,
+                session_token: env_var("AWS_SESSION_TOKEN"),
+            });
+        }
+        shared_credentials()
+    }
+}
+
+// ────────────────────────────────────────────────────────────
+// Helpers
+// ────────────────────────────────────────────────────────────
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Read the `AWS_PROFILE` (or `default`) section of `~/.aws/credentials`.
+fn shared_credentials() -> Option<Credentials> {
+    let path = env_var("AWS_SHARED_CREDENTIALS_FILE")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".aws").join("credentials")))?;
+    let contents = fs::read_to_string(path).ok()?;
+    let profile = env_var("AWS_PROFILE").unwrap_or_else(|| "default".to_string());
+    parse_credentials_file(&contents, &profile)
+}
+
+/// Parse one profile out of an AWS shared-credentials INI file.
+fn parse_credentials_file(contents: &str, profile: &str) -> Option<Credentials> {
+    let mut in_profile = false;
+    let (mut id, mut secret, mut token) = (None, None, None);
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_profile = section.trim() == profile;
+            continue;
+        }
+        if !in_profile {
+            continue;
+        }
+        if let Some((k, v)) = line.split_once('=') {
+            let v = v.trim().to_string();
+            match k.trim() {
+                "aws_access_key_id" => id = Some(v),
+                "aws_secret_access_key" => secret = Some(v),
+                "aws_session_token" => token = Some(v),
+                _ => {}
+            }
+        }
+    }
+
+    Some(Credentials { access_key_id: id?, secret_access_key: secret?, session_token: token })
+}
+
+/// Map an object key under `prefix` to a safe relative local path.
+///
+/// Returns `None` for keys that would escape the output directory.
+fn relative_path(key: &str, prefix: &str) -> Option<PathBuf> {
+    let rest = key.strip_prefix(prefix).unwrap_or(key);
+    let mut out = PathBuf::new();
+    for part in rest.split('/') {
+        if part.is_empty() || part == "." {
+            continue;
+        }
+        // Rejects `..`, and drive prefixes or backslash paths on Windows.
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => out.push(part),
+            _ => return None,
+        }
+    }
+    if out.as_os_str().is_empty() { None } else { Some(out) }
+}
+
+struct ListPage {
+    objects: Vec<S3Object>,
+    truncated: bool,
+    next_token: Option<String>,
+}
+
+fn parse_list_response(xml: &str) -> Result<ListPage, Box<dyn Error + Send + Sync>> {
+    let doc = roxmltree::Document::parse(xml)
+        .map_err(|e| format!("Invalid ListObjectsV2 response: {}", e))?;
+    let root = doc.root_element();
+    let child_text = |node: roxmltree::Node, name: &str| -> Option<String> {
+        node.children()
+            .find(|c| c.is_element() && c.tag_name().name() == name)
+            .and_then(|c| c.text())
+            .map(str::to_string)
+    };
+
+    let objects = root
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "Contents")
+        .filter_map(|n| {
+            Some(S3Object {
+                key: child_text(n, "Key")?,
+                size: child_text(n, "Size").and_then(|s| s.parse().ok()).unwrap_or(0),
+            })
+        })
+        .collect();
+
+    Ok(ListPage {
+        objects,
+        truncated: child_text(root, "IsTruncated").is_some_and(|t| t == "true"),
+        next_token: child_text(root, "NextContinuationToken"),
+    })
+}
+
+/// Turn an S3 error response into a readable message.
+fn describe_error(status: reqwest::StatusCode, body: &str) -> String {
+    let detail = roxmltree::Document::parse(body).ok().and_then(|doc| {
+        let find = |name: &str| {
+            doc.descendants()
+                .find(|n| n.tag_name().name() == name)
+                .and_then(|n| n.text())
+                .map(str::to_string)
+        };
+        let code = find("Code")?;
+        Some(match find("Message") {
+            Some(msg) => format!("{}: {}", code, msg),
+            None => code,
+        })
+    });
+    match detail {
+        Some(detail) => format!("S3 request failed ({}): {}", status, detail),
+        None => format!("S3 request failed ({})", status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bucket_and_key() {
+        let (bucket, key) = parse_s3_url("s3://my-bucket/dir/file%20name.tar.gz").unwrap();
+        assert_eq!(bucket, "my-bucket");
+        assert_eq!(key, "dir/file name.tar.gz");
+        assert!(parse_s3_url("s3:///key").is_err());
+        assert!(is_prefix_url("s3://my-bucket"));
+        assert!(is_prefix_url("s3://my-bucket/logs/"));
+        assert!(!is_prefix_url("s3://my-bucket/logs/a.txt"));
+    }
+
+    #[test]
+    fn relative_paths_stay_inside_output_dir() {
+        assert_eq!(relative_path("logs/2024/a.txt", "logs/"), Some(PathBuf::from("2024/a.txt")));
+        assert_eq!(relative_path("a//b.txt", ""), Some(PathBuf::from("a/b.txt")));
+        assert_eq!(relative_path("logs/../../etc/passwd", "logs/"), None);
+        assert_eq!(relative_path("logs/", "logs/"), None);
+    }
+
+    #[test]
+    fn parses_list_objects_v2_page() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>bucket</Name><Prefix>logs/</Prefix><IsTruncated>true</IsTruncated>
+  <NextContinuationToken>abc==</NextContinuationToken>
+  <Contents><Key>logs/a.txt</Key><Size>12</Size></Contents>
+  <Contents><Key>logs/b.txt</Key><Size>0</Size></Contents>
+</ListBucketResult>"#;
+        let page = parse_list_response(xml).unwrap();
+        assert_eq!(page.objects.len(), 2);
+        assert_eq!(page.objects[0].key, "logs/a.txt");
+        assert_eq!(page.objects[0].size, 12);
+        assert!(page.truncated);
+        assert_eq!(page.next_token.as_deref(), Some("abc=="));
+    }
+
+    #[test]
+    fn parses_shared_credentials_profile() {
+        let ini = "[default]\naws_access_key_id = AKIDDEFAULT\naws_secret_access_key = s1\n\n\
+                   [ci]\naws_access_key_id=AKIDCI\naws_secret_access_key=s2\naws_session_token=tok\n";
+        let ci = parse_credentials_file(ini, "ci").unwrap();
+        assert_eq!(ci.access_key_id, "AKIDCI");
+        assert_eq!(ci.session_token.as_deref(), Some("tok"));
+        assert_eq!(parse_credentials_file(ini, "default").unwrap().secret_access_key, "s1");
+        assert!(parse_credentials_file(ini, "missing").is_none());
+    }
+}
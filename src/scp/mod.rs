@@ -0,0 +1,212 @@
+//! SCP (secure copy) download support.
+//!
+//! For servers and appliances that run SSH without the SFTP subsystem.
+//! Connections go through the same code as [`crate::sftp`]: `~/.ssh/config`
+//! hosts and `ProxyJump`, proxies, host-key verification against
+//! `~/.ssh/known_hosts` and the same authentication order.
+//!
+//! SCP transfers whole files only: there is no resume, no directory
+//! download and no parallel segments. Use `sftp://` where the server offers
+//! it.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use kget::scp::ScpDownloader;
+//! use kget::{Optimizer, ProxyConfig};
+//!
+//! let downloader = ScpDownloader::new(
+//!     "scp://admin@switch01/cfg/running-config".to_string(),
+//!     "running-config".to_string(),
+//!     false,
+//!     ProxyConfig::default(),
+//!     Optimizer::new(),
+//! );
+//! downloader.download().unwrap();
+//! ```
+
+use crate::config::{ProxyConfig, SftpConfig};
+use crate::download::throttle_download;
+use crate::optimization::Optimizer;
+use crate::progress::create_progress_bar;
+use crate::sftp::SftpDownloader;
+use crate::store::{self, OutputMode};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use url::Url;
+
+/// Returns `true` if the URL uses the `scp://` scheme.
+pub fn is_scp_url(url: &str) -> bool {
+    url.get(..6)
+        .is_some_and(|s| s.eq_ignore_ascii_case("scp://"))
+}
+
+/// SCP file downloader using SSH.
+///
+/// Takes `scp://[user[:pass]@]host[:port]/path` URLs. SSH settings
+/// ([`SftpConfig::key_path`], [`SftpConfig::default_port`]) are shared with
+/// SFTP.
+pub struct ScpDownloader {
+    url: String,
+    output: String,
+    quiet: bool,
+    optimizer: Optimizer,
+    /// Opens the SSH session (config lookup, proxy, host key, login)
+    ssh: SftpDownloader,
+    progress_callback: Option<Arc<dyn Fn(f32) + Send + Sync>>,
+    status_callback: Option<Arc<dyn Fn(String) + Send + Sync>>,
+}
+
+impl ScpDownloader {
+    /// Create a new SCP downloader.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - SCP URL (e.g., `scp://user@host/path/to/file`)
+    /// * `output` - Local path to save the file
+    /// * `quiet` - Suppress console output
+    /// * `proxy` - Proxy configuration (SOCKS5 or HTTP `CONNECT`) for the SSH connection
+    /// * `optimizer` - Optimizer instance (its `speed_limit` is honored)
+    pub fn new(
+        url: String,
+        output: String,
+        quiet: bool,
+        proxy: ProxyConfig,
+        optimizer: Optimizer,
+    ) -> Self {
+        let ssh = SftpDownloader::new(url.clone(), output.clone(), quiet, proxy, optimizer.clone());
+        Self {
+            url,
+            output,
+            quiet,
+            optimizer,
+            ssh,
+            progress_callback: None,
+            status_callback: None,
+        }
+    }
+
+    /// Apply SSH settings (default port, key file).
+    pub fn set_config(&mut self, config: SftpConfig) {
+        self.ssh.set_config(config);
+    }
+
+    /// Set a callback for download progress (0.0 to 1.0).
+    pub fn set_progress_callback(&mut self, callback: impl Fn(f32) + Send + Sync + 'static) {
+        self.progress_callback = Some(Arc::new(callback));
+    }
+
+    /// Set a callback for status messages.
+    pub fn set_status_callback(&mut self, callback: impl Fn(String) + Send + Sync + 'static) {
+        self.status_callback = Some(Arc::new(callback));
+    }
+
+    fn send_status(&self, msg: &str) {
+        if let Some(cb) = &self.status_callback {
+            cb(msg.to_string());
+        }
+        if !self.quiet {
+            println!("{}", msg);
+        }
+    }
+
+    /// Download the file via SCP.
+    pub fn download(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let parsed =
+            Url::parse(&self.url).map_err(|e| format!("Invalid SCP URL '{}': {}", self.url, e))?;
+
+        if !parsed.scheme().eq_ignore_ascii_case("scp") {
+            return Err(format!("Expected scp:// URL, got scheme '{}'", parsed.scheme()).into());
+        }
+
+        let remote_path = urlencoding::decode(parsed.path())?.into_owned();
+        if remote_path.is_empty() || remote_path.ends_with('/') {
+            return Err(
+                "SCP URL must name a single file (e.g., scp://user@host/path/to/file); \
+                        use sftp:// for directories"
+                    .into(),
+            );
+        }
+
+        let sess = self.ssh.connect(&parsed, true)?;
+
+        let (mut channel, stat) = sess
+            .scp_recv(Path::new(&remote_path))
+            .map_err(|e| format!("Cannot fetch '{}' over SCP: {}", remote_path, e))?;
+        let size = stat.size();
+        self.send_status(&format!("Receiving {} ({} bytes)", remote_path, size));
+
+        let progress = create_progress_bar(self.quiet, remote_path.clone(), Some(size), false);
+        let mut dest = store::open_output(Path::new(&self.output), OutputMode::Create)
+            .map_err(|e| format!("Cannot create local file '{}': {}", self.output, e))?;
+
+        // The server sends exactly `size` bytes, then a status byte that
+        // libssh2 consumes when the channel is closed.
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut received = 0u64;
+        let started_at = Instant::now();
+        while received < size {
+            let limit = (size - received).min(buffer.len() as u64) as usize;
+            let n = channel
+                .read(&mut buffer[..limit])
+                .map_err(|e| format!("SCP read error: {}", e))?;
+            if n == 0 {
+                return Err(format!(
+                    "SCP transfer of '{}' ended after {} of {} bytes",
+                    remote_path, received, size
+                )
+                .into());
+            }
+            dest.write_all(&buffer[..n])
+                .map_err(|e| format!("Write error to '{}': {}", self.output, e))?;
+            received += n as u64;
+            progress.set_position(received);
+            if let Some(cb) = &self.progress_callback {
+                cb(received as f32 / size.max(1) as f32);
+            }
+            throttle_download(received, started_at, self.optimizer.speed_limit);
+        }
+
+        channel.send_eof()?;
+        channel.wait_eof()?;
+        channel.close()?;
+        channel.wait_close()?;
+
+        progress.finish_with_message("Download complete");
+
+        if !self.quiet {
+            println!("Saved to '{}'", self.output);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_scp_url() {
+        assert!(is_scp_url("scp://admin@switch01/cfg/running-config"));
+        assert!(is_scp_url("SCP://host/file"));
+        assert!(!is_scp_url("sftp://host/file"));
+        assert!(!is_scp_url("scp:/"));
+    }
+
+    #[test]
+    fn test_rejects_directories() {
+        let downloader = ScpDownloader::new(
+            "scp://host/var/log/".to_string(),
+            "log".to_string(),
+            true,
+            ProxyConfig::default(),
+            Optimizer::new(),
+        );
+        let err = downloader.download().unwrap_err();
+        assert!(err.to_string().contains("single file"), "{err}");
+    }
+}
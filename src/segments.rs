@@ -0,0 +1,258 @@
+//! Segment splitting and progress records for segmented FTP and SFTP
+//! downloads.
+//!
+//! Segmented downloads preallocate the output file and fill it out of
+//! order, so its length says nothing about how much of it has arrived. The
+//! byte ranges written so far are kept in a side file next to the output
+//! (`<output>.kget-ftp`, `<output>.kget-sftp`): a later attempt fetches only
+//! the gaps, and the side file is removed once every range is in place.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Attempts per segment before a segmented download gives up.
+pub(crate) const MAX_RETRIES: usize = 3;
+
+/// Split `[start, total)` into at most `connections` segments of at least
+/// `min_segment` bytes (a smaller remainder stays in one segment).
+pub(crate) fn calculate_segments(
+    start: u64,
+    total: u64,
+    connections: Option<usize>,
+    min_segment: u64,
+) -> Vec<(u64, u64)> {
+    let remaining = total.saturating_sub(start);
+    let connections = connections.unwrap_or(1).max(1) as u64;
+    let count = connections.min(remaining / min_segment).max(1);
+    let segment_size = remaining.div_ceil(count).max(1);
+
+    let mut segments = Vec::new();
+    let mut offset = start;
+    while offset < total {
+        let end = (offset + segment_size).min(total);
+        segments.push((offset, end));
+        offset = end;
+    }
+    segments
+}
+
+/// Ranges of a preallocated output file that hold downloaded data.
+pub(crate) struct SegmentLog {
+    path: PathBuf,
+    total: u64,
+    done: Mutex<Vec<(u64, u64)>>,
+}
+
+impl SegmentLog {
+    /// Start a record for an output of `total` bytes whose first `done`
+    /// bytes are already downloaded. Call this before preallocating.
+    pub(crate) fn create(output: &Path, protocol: &str, total: u64, done: u64) -> io::Result<Self> {
+        let log = Self {
+            path: log_path(output, protocol),
+            total,
+            done: Mutex::new(if done > 0 {
+                vec![(0, done)]
+            } else {
+                Vec::new()
+            }),
+        };
+        log.save(&log.done.lock().unwrap())?;
+        Ok(log)
+    }
+
+    /// The record left by an earlier attempt at `output`, if any.
+    ///
+    /// A record that does not match the output (a different remote size, a
+    /// missing or resized file, an unreadable side file) is discarded along
+    /// with the output, whose contents cannot be trusted.
+    pub(crate) fn load(output: &Path, protocol: &str, total: u64) -> io::Result<Option<Self>> {
+        let path = log_path(output, protocol);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let output_len = fs::metadata(output).map(|m| m.len()).ok();
+        match parse(&text) {
+            Some((recorded, done)) if recorded == total && output_len == Some(total) => {
+                Ok(Some(Self {
+                    path,
+                    total,
+                    done: Mutex::new(done),
+                }))
+            }
+            _ => {
+                fs::remove_file(&path)?;
+                if output_len.is_some() {
+                    fs::remove_file(output)?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Size of the complete output.
+    pub(crate) fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Ranges still to download, in order.
+    pub(crate) fn missing(&self) -> Vec<(u64, u64)> {
+        let done = self.done.lock().unwrap();
+        let mut gaps = Vec::new();
+        let mut offset = 0;
+        for &(start, end) in done.iter() {
+            if start > offset {
+                gaps.push((offset, start));
+            }
+            offset = offset.max(end);
+        }
+        if offset < self.total {
+            gaps.push((offset, self.total));
+        }
+        gaps
+    }
+
+    /// Bytes already downloaded.
+    pub(crate) fn completed(&self) -> u64 {
+        let missing: u64 = self.missing().iter().map(|(start, end)| end - start).sum();
+        self.total - missing
+    }
+
+    /// Record that `[start, end)` has been written, and save the record.
+    pub(crate) fn mark(&self, start: u64, end: u64) -> io::Result<()> {
+        if start >= end {
+            return Ok(());
+        }
+        let mut done = self.done.lock().unwrap();
+        done.push((start, end));
+        done.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(done.len());
+        for &(start, end) in done.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *done = merged;
+        self.save(&done)
+    }
+
+    /// Remove the record once the output is complete.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write the record through a temporary file, so an interrupted save
+    /// never leaves a half-written one behind.
+    fn save(&self, done: &[(u64, u64)]) -> io::Result<()> {
+        let mut text = format!("{}\n", self.total);
+        for (start, end) in done {
+            text.push_str(&format!("{} {}\n", start, end));
+        }
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// `<output>.kget-<protocol>`
+fn log_path(output: &Path, protocol: &str) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(format!(".kget-{}", protocol));
+    PathBuf::from(path)
+}
+
+/// Total size on the first line, then one `start end` range per line.
+fn parse(text: &str) -> Option<(u64, Vec<(u64, u64)>)> {
+    let mut lines = text.lines();
+    let total = lines.next()?.trim().parse().ok()?;
+    let mut done = Vec::new();
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let (start, end) = line.trim().split_once(' ')?;
+        let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+        if start > end || end > total {
+            return None;
+        }
+        done.push((start, end));
+    }
+    done.sort_unstable();
+    Some((total, done))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_segments() {
+        const MB: u64 = 1024 * 1024;
+
+        assert_eq!(
+            calculate_segments(0, 100 * MB, None, 4 * MB),
+            vec![(0, 100 * MB)]
+        );
+        assert_eq!(
+            calculate_segments(0, 100 * MB, Some(4), 4 * MB),
+            vec![
+                (0, 25 * MB),
+                (25 * MB, 50 * MB),
+                (50 * MB, 75 * MB),
+                (75 * MB, 100 * MB)
+            ]
+        );
+        // Resumed: only the rest is split.
+        assert_eq!(
+            calculate_segments(92 * MB, 100 * MB, Some(4), 4 * MB),
+            vec![(92 * MB, 96 * MB), (96 * MB, 100 * MB)]
+        );
+        // Too small to be worth extra connections.
+        assert_eq!(calculate_segments(0, MB, Some(8), 4 * MB), vec![(0, MB)]);
+        assert_eq!(calculate_segments(0, 4 * MB, Some(8), MB).len(), 4);
+        // Nothing left to fetch.
+        assert!(calculate_segments(MB, MB, Some(4), MB).is_empty());
+    }
+
+    #[test]
+    fn test_log_tracks_gaps_across_attempts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output = dir.path().join("big.bin");
+        fs::write(&output, vec![0u8; 100]).unwrap();
+
+        let log = SegmentLog::create(&output, "ftp", 100, 10).unwrap();
+        log.mark(50, 70).unwrap();
+        log.mark(10, 30).unwrap();
+        assert_eq!(log.missing(), [(30, 50), (70, 100)]);
+        assert_eq!(log.completed(), 50);
+        drop(log);
+
+        let log = SegmentLog::load(&output, "ftp", 100).unwrap().unwrap();
+        assert_eq!(log.missing(), [(30, 50), (70, 100)]);
+        log.mark(30, 50).unwrap();
+        log.mark(70, 100).unwrap();
+        assert!(log.missing().is_empty());
+        log.finish().unwrap();
+        assert!(!dir.path().join("big.bin.kget-ftp").exists());
+    }
+
+    #[test]
+    fn test_stale_log_discards_output() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output = dir.path().join("big.bin");
+        fs::write(&output, vec![0u8; 100]).unwrap();
+        SegmentLog::create(&output, "sftp", 100, 40).unwrap();
+
+        // The remote file changed size: neither the record nor the
+        // preallocated output can be reused.
+        assert!(SegmentLog::load(&output, "sftp", 200).unwrap().is_none());
+        assert!(!output.exists());
+        assert!(!dir.path().join("big.bin.kget-sftp").exists());
+        assert!(SegmentLog::load(&output, "sftp", 100).unwrap().is_none());
+    }
+}
@@ -0,0 +1,275 @@
+//! The subset of OpenSSH's `~/.ssh/config` that SFTP connections honor:
+//! `Host` blocks with `HostName`, `User`, `Port`, `IdentityFile` and
+//! `ProxyJump`.
+//!
+//! As in OpenSSH, the first value found for an option wins (except
+//! `IdentityFile`, which accumulates), so specific `Host` blocks go before
+//! `Host *`. `Match` blocks other than `Match all` and `Include` are not
+//! supported; their options are ignored.
+
+use crate::utils::glob_match;
+use std::path::PathBuf;
+
+/// Options for one host, as read from `~/.ssh/config`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct HostConfig {
+    pub host_name: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    /// Raw `IdentityFile` values; see [`expand_path`]
+    pub identity_files: Vec<String>,
+    pub proxy_jump: Option<String>,
+}
+
+impl HostConfig {
+    /// Options for `alias` from `~/.ssh/config` (empty if there is none).
+    pub(crate) fn load(alias: &str) -> Self {
+        dirs::home_dir()
+            .and_then(|home| std::fs::read_to_string(home.join(".ssh/config")).ok())
+            .map(|text| Self::parse(&text, alias))
+            .unwrap_or_default()
+    }
+
+    /// Options from config `text` that apply to `alias`.
+    pub(crate) fn parse(text: &str, alias: &str) -> Self {
+        let alias = alias.to_ascii_lowercase();
+        let mut config = Self::default();
+        // Options before the first Host line apply to every host.
+        let mut active = true;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = split_option(line);
+            match key.to_ascii_lowercase().as_str() {
+                "host" => active = host_matches(value, &alias),
+                "match" => active = value.eq_ignore_ascii_case("all"),
+                _ if !active => {}
+                "hostname" => set_first(&mut config.host_name, value),
+                "user" => set_first(&mut config.user, value),
+                "port" if config.port.is_none() => config.port = value.parse().ok(),
+                "identityfile" => config.identity_files.push(value.to_string()),
+                "proxyjump" => set_first(&mut config.proxy_jump, value),
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+/// One hop of a `ProxyJump` chain (`[user@]host[:port]` or `ssh://` URI).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+/// Parse a comma-separated `ProxyJump` value, first hop first. `none`
+/// yields no hops.
+pub(crate) fn parse_jumps(spec: &str) -> Vec<JumpHost> {
+    if spec.trim().eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    spec.split(',')
+        .map(str::trim)
+        .filter(|hop| !hop.is_empty())
+        .map(|hop| {
+            let hop = hop.strip_prefix("ssh://").unwrap_or(hop);
+            let (user, rest) = match hop.rsplit_once('@') {
+                Some((user, rest)) => (Some(user.to_string()), rest),
+                None => (None, hop),
+            };
+            // [v6addr]:port, host:port or a bare host
+            let (host, port) = if let Some(bracketed) = rest.strip_prefix('[') {
+                let (host, tail) = bracketed.split_once(']').unwrap_or((bracketed, ""));
+                (host, tail.strip_prefix(':').and_then(|p| p.parse().ok()))
+            } else {
+                match rest.rsplit_once(':') {
+                    Some((host, port)) if !host.contains(':') => (host, port.parse().ok()),
+                    _ => (rest, None),
+                }
+            };
+            JumpHost {
+                user,
+                host: host.to_string(),
+                port,
+            }
+        })
+        .collect()
+}
+
+/// Expand `~` and the `%d` (home), `%h` (host), `%p` (port), `%r` (remote
+/// user), `%u` (local user) and `%%` tokens in an `IdentityFile` path.
+pub(crate) fn expand_path(value: &str, host: &str, port: u16, user: &str) -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_default();
+    let local_user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+
+    let mut expanded = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('d') => expanded.push_str(&home.to_string_lossy()),
+            Some('h') => expanded.push_str(host),
+            Some('p') => expanded.push_str(&port.to_string()),
+            Some('r') => expanded.push_str(user),
+            Some('u') => expanded.push_str(&local_user),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    match expanded.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None if expanded == "~" => home,
+        None => PathBuf::from(expanded),
+    }
+}
+
+/// Split `Key value` or `Key=value`, dropping quotes around the value.
+fn split_option(line: &str) -> (&str, &str) {
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let (key, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let value = rest.strip_prefix('=').unwrap_or(rest).trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    (key, value)
+}
+
+/// Whether a `Host` line's patterns select `alias` (already lowercase): any
+/// pattern matches and no `!pattern` does.
+fn host_matches(patterns: &str, alias: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix('!') {
+            Some(negated) if glob_match(negated, alias) => return false,
+            Some(_) => {}
+            None => matched |= glob_match(&pattern, alias),
+        }
+    }
+    matched
+}
+
+fn set_first(slot: &mut Option<String>, value: &str) {
+    if slot.is_none() {
+        *slot = Some(value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+# Global defaults come first
+IdentityFile ~/.ssh/id_global
+
+Host prod-db prod-web
+    HostName 10.0.4.%h.internal
+    User deploy
+    Port 2222
+    IdentityFile "~/.ssh/id_%r@%h"
+    ProxyJump bastion
+
+Host *.corp !legacy.corp
+    User=corp-user
+    ProxyJump jump1.corp,admin@jump2.corp:2200
+
+Host prod-*
+    User ignored-because-first-wins
+    Port 22
+
+Match host foo exec "true"
+    User never
+
+Host *
+    User fallback
+"#;
+
+    #[test]
+    fn test_parse_host_block() {
+        let config = HostConfig::parse(CONFIG, "Prod-DB");
+        assert_eq!(config.host_name.as_deref(), Some("10.0.4.%h.internal"));
+        assert_eq!(config.user.as_deref(), Some("deploy"));
+        assert_eq!(config.port, Some(2222));
+        assert_eq!(
+            config.identity_files,
+            ["~/.ssh/id_global", "~/.ssh/id_%r@%h"]
+        );
+        assert_eq!(config.proxy_jump.as_deref(), Some("bastion"));
+    }
+
+    #[test]
+    fn test_parse_wildcards_and_negation() {
+        let corp = HostConfig::parse(CONFIG, "files.corp");
+        assert_eq!(corp.user.as_deref(), Some("corp-user"));
+        assert_eq!(corp.host_name, None);
+
+        let legacy = HostConfig::parse(CONFIG, "legacy.corp");
+        assert_eq!(legacy.user.as_deref(), Some("fallback"));
+        assert_eq!(legacy.proxy_jump, None);
+
+        let other = HostConfig::parse(CONFIG, "example.com");
+        assert_eq!(other.user.as_deref(), Some("fallback"));
+        assert_eq!(other.port, None);
+    }
+
+    #[test]
+    fn test_parse_jumps() {
+        assert_eq!(
+            parse_jumps("jump1.corp,admin@jump2.corp:2200"),
+            vec![
+                JumpHost {
+                    user: None,
+                    host: "jump1.corp".to_string(),
+                    port: None
+                },
+                JumpHost {
+                    user: Some("admin".to_string()),
+                    host: "jump2.corp".to_string(),
+                    port: Some(2200)
+                },
+            ]
+        );
+        assert_eq!(
+            parse_jumps("ssh://ops@[2001:db8::1]:22"),
+            vec![JumpHost {
+                user: Some("ops".to_string()),
+                host: "2001:db8::1".to_string(),
+                port: Some(22)
+            }]
+        );
+        assert!(parse_jumps("none").is_empty());
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let home = dirs::home_dir().unwrap_or_default();
+        assert_eq!(
+            expand_path("~/.ssh/id_%r@%h-%p", "db1", 2222, "deploy"),
+            home.join(".ssh/id_deploy@db1-2222")
+        );
+        assert_eq!(
+            expand_path("/keys/100%%.pem", "h", 22, "u"),
+            PathBuf::from("/keys/100%.pem")
+        );
+    }
+}
@@ -0,0 +1,486 @@
+//! Detached signature verification for downloads.
+//!
+//! A checksum fetched from the same server as the file only detects
+//! corruption; a signature made with a key the user already trusts also
+//! detects a compromised mirror. Two formats are supported:
+//!
+//! - **OpenPGP** (`.asc`, `.sig`, `.gpg`) — checked with `gpg` against a
+//!   throwaway home directory that holds only the keys from the given
+//!   keyring, so the user's own trust database never matters
+//! - **minisign** (`.minisig`) — checked in-process against a `minisign.pub`
+//!   public key file
+//!
+//! A signature can cover the downloaded file itself or a checksum list such
+//! as `SHA256SUMS`. In the latter case the list is verified first and the
+//! file's entry (found with [`parse_sidecar`]) becomes the expected digest.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use kget::signature::SignatureCheck;
+//!
+//! let client = reqwest::blocking::Client::new();
+//! let url = "https://example.com/release.tar.gz";
+//! let check = SignatureCheck::new(None, "release-keys.asc");
+//! let pending = check.prepare(&client, url)?;
+//! // ... download `url` to release.tar.gz ...
+//! let info = pending.verify(std::path::Path::new("release.tar.gz"))?;
+//! println!("Good signature from {}", info.signer);
+//! # Ok::<(), kget::KgetError>(())
+//! ```
+
+use crate::checksum::{ChecksumAlgorithm, compute_checksum, parse_sidecar};
+use crate::config::ProxyConfig;
+use crate::error::KgetError;
+use crate::local;
+use crate::metalink;
+use crate::utils;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Signature sidecar extensions probed next to a file, in order.
+pub const SIGNATURE_EXTENSIONS: &[&str] = &[".minisig", ".asc", ".sig"];
+
+/// Signed checksum lists probed in the file's directory, in order.
+pub const SIGNED_MANIFESTS: &[&str] = &[
+    "SHA256SUMS.asc",
+    "SHA256SUMS.gpg",
+    "SHA256SUMS.sig",
+    "SHA256SUMS.minisig",
+    "SHA512SUMS.asc",
+    "SHA512SUMS.gpg",
+    "SHA512SUMS.sig",
+    "SHA512SUMS.minisig",
+];
+
+/// Signature format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKind {
+    /// Detached OpenPGP signature, armored or binary.
+    OpenPgp,
+    /// minisign signature.
+    Minisign,
+}
+
+impl SignatureKind {
+    /// Guess the format from a signature's URL or file name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.split(['?', '#']).next().unwrap_or(name).to_ascii_lowercase();
+        if name.ends_with(".minisig") {
+            Some(SignatureKind::Minisign)
+        } else if name.ends_with(".asc") || name.ends_with(".sig") || name.ends_with(".gpg") {
+            Some(SignatureKind::OpenPgp)
+        } else {
+            None
+        }
+    }
+
+    /// Detect the format from the signature's contents.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(b"untrusted comment:") {
+            SignatureKind::Minisign
+        } else {
+            SignatureKind::OpenPgp
+        }
+    }
+}
+
+impl fmt::Display for SignatureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SignatureKind::OpenPgp => "OpenPGP",
+            SignatureKind::Minisign => "minisign",
+        })
+    }
+}
+
+/// A successfully verified signature.
+#[derive(Debug, Clone)]
+pub struct SignatureInfo {
+    /// Signature format.
+    pub kind: SignatureKind,
+    /// Who made it: user ID and fingerprint for OpenPGP, key ID and trusted
+    /// comment for minisign.
+    pub signer: String,
+    /// URL the signature was fetched from.
+    pub url: String,
+    /// URL of the signed checksum list, when the signature covers one
+    /// instead of the file.
+    pub manifest: Option<String>,
+}
+
+/// Verify `signature` over the file at `data` with the keys in `keyring`.
+///
+/// For OpenPGP, `keyring` is an exported public key or keyring (armored or
+/// binary); for minisign it is a `minisign.pub` file.
+pub fn verify_detached(
+    data: &Path,
+    signature: &[u8],
+    kind: SignatureKind,
+    keyring: &Path,
+) -> Result<String, KgetError> {
+    match kind {
+        SignatureKind::OpenPgp => verify_openpgp(data, signature, keyring),
+        SignatureKind::Minisign => verify_minisign(data, signature, keyring),
+    }
+}
+
+/// Signature verification requested for a download.
+#[derive(Debug, Clone)]
+pub struct SignatureCheck {
+    /// Signature URL; `None` probes [`SIGNATURE_EXTENSIONS`] next to the file,
+    /// then [`SIGNED_MANIFESTS`] in its directory.
+    pub url: Option<String>,
+    /// Trusted keys.
+    pub keyring: PathBuf,
+}
+
+impl SignatureCheck {
+    pub fn new(url: Option<String>, keyring: impl Into<PathBuf>) -> Self {
+        Self { url, keyring: keyring.into() }
+    }
+
+    /// [`prepare`](Self::prepare) with a client configured for `proxy`.
+    pub fn prepare_with_proxy(
+        &self,
+        proxy: &ProxyConfig,
+        file_url: &str,
+    ) -> Result<PendingSignature, KgetError> {
+        let client = metalink::build_http_client(proxy).map_err(|e| KgetError::Network(e.to_string()))?;
+        self.prepare(&client, file_url)
+    }
+
+    /// Fetch the signature for `file_url` before the download.
+    ///
+    /// A signature over a checksum list is verified right away, so a bad
+    /// signature aborts before anything is downloaded.
+    pub fn prepare(
+        &self,
+        client: &reqwest::blocking::Client,
+        file_url: &str,
+    ) -> Result<PendingSignature, KgetError> {
+        if !self.keyring.is_file() {
+            return Err(KgetError::SignatureError(format!(
+                "Keyring not found: {}",
+                self.keyring.display()
+            )));
+        }
+
+        let (sig_url, signature) = match &self.url {
+            Some(url) => {
+                let data = fetch(client, url)?.ok_or_else(|| {
+                    KgetError::SignatureError(format!("Signature not found: {url}"))
+                })?;
+                (url.clone(), data)
+            }
+            None => candidate_urls(file_url)
+                .into_iter()
+                .find_map(|url| match fetch(client, &url) {
+                    Ok(Some(data)) => Some(Ok((url, data))),
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e)),
+                })
+                .transpose()?
+                .ok_or_else(|| {
+                    KgetError::SignatureError(format!("No signature found for {file_url}"))
+                })?,
+        };
+        let kind = SignatureKind::from_name(&sig_url).unwrap_or_else(|| SignatureKind::detect(&signature));
+
+        let mut pending = PendingSignature {
+            url: sig_url.clone(),
+            kind,
+            keyring: self.keyring.clone(),
+            signature,
+            manifest: None,
+        };
+
+        let Some(manifest_url) = signed_manifest_url(&sig_url, file_url) else {
+            return Ok(pending);
+        };
+        let manifest = fetch(client, &manifest_url)?.ok_or_else(|| {
+            KgetError::SignatureError(format!("Signed checksum list not found: {manifest_url}"))
+        })?;
+        let tmp = tempfile::NamedTempFile::new()?;
+        fs::write(tmp.path(), &manifest)?;
+        let signer = verify_detached(tmp.path(), &pending.signature, kind, &self.keyring)?;
+
+        let filename = utils::get_filename_from_url_or_default(file_url, "file");
+        let (algorithm, hash) = parse_sidecar(&String::from_utf8_lossy(&manifest), &filename)
+            .ok_or_else(|| {
+                KgetError::SignatureError(format!("No entry for '{filename}' in {manifest_url}"))
+            })?;
+        pending.manifest = Some(SignedManifest { url: manifest_url, algorithm, hash, signer });
+        Ok(pending)
+    }
+}
+
+/// A signed checksum list that was already verified.
+#[derive(Debug, Clone)]
+struct SignedManifest {
+    url: String,
+    algorithm: ChecksumAlgorithm,
+    hash: String,
+    signer: String,
+}
+
+/// A fetched signature waiting for the download to finish.
+#[derive(Debug, Clone)]
+pub struct PendingSignature {
+    /// URL the signature was fetched from.
+    pub url: String,
+    /// Signature format.
+    pub kind: SignatureKind,
+    keyring: PathBuf,
+    signature: Vec<u8>,
+    manifest: Option<SignedManifest>,
+}
+
+impl PendingSignature {
+    /// Digest the file must have, when the signature covers a checksum list.
+    pub fn expected_digest(&self) -> Option<(ChecksumAlgorithm, &str)> {
+        self.manifest.as_ref().map(|m| (m.algorithm.clone(), m.hash.as_str()))
+    }
+
+    /// Check the downloaded file: against the signature itself, or against
+    /// its digest in the signed checksum list.
+    pub fn verify(&self, path: &Path) -> Result<SignatureInfo, KgetError> {
+        self.verify_with_digests(path, &[])
+    }
+
+    /// Like [`verify`](Self::verify), reusing digests the caller already
+    /// computed for `path` instead of hashing it again.
+    pub fn verify_with_digests(
+        &self,
+        path: &Path,
+        computed: &[(ChecksumAlgorithm, &str)],
+    ) -> Result<SignatureInfo, KgetError> {
+        let (signer, manifest) = match &self.manifest {
+            Some(m) => {
+                let got = match computed.iter().find(|(algo, _)| *algo == m.algorithm) {
+                    Some((_, hex)) => hex.to_string(),
+                    None => compute_checksum(path, &m.algorithm)?,
+                };
+                if got != m.hash {
+                    return Err(KgetError::ChecksumMismatch {
+                        algorithm: m.algorithm.name().to_string(),
+                        expected: m.hash.clone(),
+                        got,
+                    });
+                }
+                (m.signer.clone(), Some(m.url.clone()))
+            }
+            None => (verify_detached(path, &self.signature, self.kind, &self.keyring)?, None),
+        };
+        Ok(SignatureInfo { kind: self.kind, signer, url: self.url.clone(), manifest })
+    }
+}
+
+/// Signature URLs tried for `file_url` when none was given.
+pub fn candidate_urls(file_url: &str) -> Vec<String> {
+    let base = file_url.split(['?', '#']).next().unwrap_or(file_url);
+    let mut urls: Vec<String> = SIGNATURE_EXTENSIONS.iter().map(|ext| format!("{base}{ext}")).collect();
+    if let Some((dir, _)) = base.rsplit_once('/') {
+        urls.extend(SIGNED_MANIFESTS.iter().map(|name| format!("{dir}/{name}")));
+    }
+    urls
+}
+
+/// The checksum list `sig_url` signs, or `None` if it signs `file_url` itself.
+fn signed_manifest_url(sig_url: &str, file_url: &str) -> Option<String> {
+    let sig_base = sig_url.split(['?', '#']).next().unwrap_or(sig_url);
+    let signed = [".minisig", ".asc", ".sig", ".gpg"]
+        .iter()
+        .find_map(|ext| sig_base.strip_suffix(ext))
+        .unwrap_or(sig_base);
+    let file_base = file_url.split(['?', '#']).next().unwrap_or(file_url);
+    let name = |u: &str| u.rsplit('/').next().unwrap_or(u).to_string();
+    if signed == file_base || name(signed) == name(file_base) {
+        None
+    } else {
+        Some(signed.to_string())
+    }
+}
+
+/// Fetch a small auxiliary file; `Ok(None)` if it does not exist.
+fn fetch(client: &reqwest::blocking::Client, url: &str) -> Result<Option<Vec<u8>>, KgetError> {
+    if !url.contains("://") && !local::is_data_url(url) {
+        return match fs::read(url) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+    if local::is_local_url(url) {
+        return match local::open(url) {
+            Ok(mut source) => {
+                let mut data = Vec::new();
+                source.reader.read_to_end(&mut data)?;
+                Ok(Some(data))
+            }
+            Err(_) => Ok(None),
+        };
+    }
+    let response = client.get(url).send()?;
+    if response.status().is_success() {
+        Ok(Some(response.bytes()?.to_vec()))
+    } else if response.status().is_client_error() {
+        Ok(None)
+    } else {
+        Err(KgetError::Network(format!("HTTP {} for {}", response.status(), url)))
+    }
+}
+
+fn verify_minisign(data: &Path, signature: &[u8], keyring: &Path) -> Result<String, KgetError> {
+    let fail = |e: minisign_verify::Error| KgetError::SignatureError(format!("minisign: {e}"));
+    let public_key = minisign_verify::PublicKey::from_file(keyring).map_err(fail)?;
+    let signature = minisign_verify::Signature::decode(&String::from_utf8_lossy(signature)).map_err(fail)?;
+
+    match public_key.verify_stream(&signature) {
+        Ok(mut verifier) => {
+            let mut file = File::open(data)?;
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = file.read(&mut buf)?;
+                if n == 0 { break; }
+                verifier.update(&buf[..n]);
+            }
+            verifier.finalize().map_err(fail)?;
+        }
+        // Signatures made by minisign < 0.8 sign the raw data.
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            public_key.verify(&fs::read(data)?, &signature, true).map_err(fail)?;
+        }
+        Err(e) => return Err(fail(e)),
+    }
+
+    let key = public_key.untrusted_comment().unwrap_or("minisign key");
+    Ok(format!("{} ({})", key.trim_start_matches("untrusted comment: "), signature.trusted_comment()))
+}
+
+fn verify_openpgp(data: &Path, signature: &[u8], keyring: &Path) -> Result<String, KgetError> {
+    let home = tempfile::tempdir()?;
+    let gpg = |args: &[&std::ffi::OsStr]| {
+        Command::new("gpg")
+            .arg("--batch")
+            .arg("--no-tty")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| {
+                KgetError::SignatureError(format!(
+                    "could not run gpg ({e}); install GnuPG to verify OpenPGP signatures"
+                ))
+            })
+    };
+
+    let import = gpg(&["--import".as_ref(), keyring.as_os_str()])?;
+    if !import.status.success() {
+        return Err(KgetError::SignatureError(format!(
+            "could not import {}: {}",
+            keyring.display(),
+            String::from_utf8_lossy(&import.stderr).trim()
+        )));
+    }
+
+    let sig_path = home.path().join("download.sig");
+    fs::write(&sig_path, signature)?;
+    let output = gpg(&[
+        "--status-fd".as_ref(),
+        "1".as_ref(),
+        "--verify".as_ref(),
+        sig_path.as_os_str(),
+        data.as_os_str(),
+    ])?;
+
+    // Only the status lines are authoritative; the human-readable output
+    // and trust warnings depend on the gpg version.
+    let status = String::from_utf8_lossy(&output.stdout);
+    let mut good = None;
+    let mut fingerprint = None;
+    for line in status.lines() {
+        let Some(rest) = line.strip_prefix("[GNUPG:] ") else { continue };
+        let mut words = rest.splitn(3, ' ');
+        match (words.next(), words.next(), words.next()) {
+            (Some("GOODSIG"), Some(_key_id), user) => good = Some(user.unwrap_or("").to_string()),
+            (Some("VALIDSIG"), Some(fpr), _) => fingerprint = Some(fpr.to_string()),
+            (Some(bad @ ("BADSIG" | "EXPKEYSIG" | "REVKEYSIG" | "EXPSIG" | "ERRSIG")), _, _) => {
+                let reason = match bad {
+                    "BADSIG" => "bad signature",
+                    "EXPKEYSIG" => "signing key has expired",
+                    "REVKEYSIG" => "signing key was revoked",
+                    "EXPSIG" => "signature has expired",
+                    _ => "no matching key in keyring",
+                };
+                return Err(KgetError::SignatureError(format!("OpenPGP: {reason}")));
+            }
+            _ => {}
+        }
+    }
+
+    match (output.status.success(), good, fingerprint) {
+        (true, Some(user), Some(fpr)) => Ok(format!("{user} [{fpr}]")),
+        _ => Err(KgetError::SignatureError(format!(
+            "OpenPGP: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINISIGN_KEY: &str = "untrusted comment: minisign public key E7620F1842B4E81F\n\
+                                RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\n";
+    const MINISIGN_SIG: &str = "untrusted comment: signature from minisign secret key\n\
+        RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=\n\
+        trusted comment: timestamp:1556193335\tfile:test\n\
+        y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==\n";
+
+    #[test]
+    fn minisign_accepts_good_and_rejects_tampered_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = dir.path().join("minisign.pub");
+        let data = dir.path().join("test");
+        fs::write(&key, MINISIGN_KEY).unwrap();
+
+        fs::write(&data, "test").unwrap();
+        let signer = verify_detached(&data, MINISIGN_SIG.as_bytes(), SignatureKind::Minisign, &key).unwrap();
+        assert!(signer.contains("E7620F1842B4E81F"));
+        assert!(signer.contains("file:test"));
+
+        fs::write(&data, "Test").unwrap();
+        let err = verify_detached(&data, MINISIGN_SIG.as_bytes(), SignatureKind::Minisign, &key).unwrap_err();
+        assert!(matches!(err, KgetError::SignatureError(_)));
+    }
+
+    #[test]
+    fn kind_from_name_and_contents() {
+        assert_eq!(SignatureKind::from_name("https://x/a.iso.minisig"), Some(SignatureKind::Minisign));
+        assert_eq!(SignatureKind::from_name("https://x/SHA256SUMS.gpg?sig=1"), Some(SignatureKind::OpenPgp));
+        assert_eq!(SignatureKind::from_name("https://x/a.iso"), None);
+        assert_eq!(SignatureKind::detect(MINISIGN_SIG.as_bytes()), SignatureKind::Minisign);
+        assert_eq!(SignatureKind::detect(b"-----BEGIN PGP SIGNATURE-----"), SignatureKind::OpenPgp);
+    }
+
+    #[test]
+    fn signature_target_is_file_or_manifest() {
+        let file = "https://example.com/rel/app.tar.gz";
+        assert_eq!(signed_manifest_url("https://example.com/rel/app.tar.gz.asc", file), None);
+        assert_eq!(signed_manifest_url("https://cdn.example.com/app.tar.gz.minisig", file), None);
+        assert_eq!(
+            signed_manifest_url("https://example.com/rel/SHA256SUMS.gpg", file).as_deref(),
+            Some("https://example.com/rel/SHA256SUMS")
+        );
+
+        let candidates = candidate_urls(file);
+        assert_eq!(candidates[0], "https://example.com/rel/app.tar.gz.minisig");
+        assert!(candidates.contains(&"https://example.com/rel/SHA256SUMS.asc".to_string()));
+    }
+}
@@ -0,0 +1,471 @@
+//! Content-addressed store of verified downloads.
+//!
+//! Every file whose checksum was verified after a download can be added to the
+//! store under `<root>/<algorithm>/<first two hex chars>/<hex digest>`. When a
+//! later download expects the same digest, the object is hardlinked (or
+//! reflinked, or as a last resort copied) into place instead of fetching it
+//! again.
+//!
+//! Objects are re-hashed before they are reused, so a store entry that was
+//! modified through one of its hardlinks is discarded rather than handed out.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use kget::checksum::ChecksumAlgorithm;
+//! use kget::store::ContentStore;
+//! use std::path::Path;
+//!
+//! let store = ContentStore::new(ContentStore::default_dir());
+//! let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+//! if let Some(kind) = store.materialize(&ChecksumAlgorithm::Sha256, hash, Path::new("empty.bin"))? {
+//!     println!("reused via {}", kind);
+//! }
+//! # Ok::<(), kget::KgetError>(())
+//! ```
+
+use crate::checksum::{ChecksumAlgorithm, compute_checksum};
+use crate::config::OptimizationConfig;
+use crate::error::KgetError;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// Default store location, relative to the home directory.
+pub const DEFAULT_STORE_DIR: &str = "~/.local/share/kget/store";
+
+/// How an object was placed at its destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// A hardlink sharing the store object's inode.
+    Hardlink,
+    /// A copy-on-write clone (`cp --reflink` / APFS clone).
+    Reflink,
+    /// A plain byte copy.
+    Copy,
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LinkKind::Hardlink => "hardlink",
+            LinkKind::Reflink => "reflink",
+            LinkKind::Copy => "copy",
+        })
+    }
+}
+
+/// Options for [`ContentStore::gc`].
+#[derive(Debug, Clone, Default)]
+pub struct GcOptions {
+    /// Remove objects whose modification time is older than this, even if
+    /// they are still linked from elsewhere.
+    pub older_than: Option<Duration>,
+    /// Remove every object.
+    pub all: bool,
+    /// Report what would be removed without deleting anything.
+    pub dry_run: bool,
+}
+
+/// Outcome of a [`ContentStore::gc`] run.
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    /// Objects removed (or that would be removed with `dry_run`).
+    pub removed: usize,
+    /// Bytes freed by the removed objects.
+    pub bytes_freed: u64,
+    /// Objects left in place.
+    pub kept: usize,
+}
+
+/// A content-addressed object store rooted at a directory.
+#[derive(Debug, Clone)]
+pub struct ContentStore {
+    root: PathBuf,
+}
+
+impl ContentStore {
+    /// Open (lazily — nothing is created until the first insert) a store at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The store at `config.store_dir` (whether or not `store_enabled` is set).
+    pub fn from_config(config: &OptimizationConfig) -> Self {
+        if config.store_dir.is_empty() {
+            Self::new(Self::default_dir())
+        } else {
+            Self::new(expand_home(&config.store_dir))
+        }
+    }
+
+    /// The default store directory with `~` expanded.
+    pub fn default_dir() -> PathBuf {
+        expand_home(DEFAULT_STORE_DIR)
+    }
+
+    /// Root directory of the store.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path an object with this digest would live at.
+    ///
+    /// Returns `None` if `hex` is not a plausible hex digest, so a hash taken
+    /// from a manifest can never escape the store directory.
+    pub fn object_path(&self, algorithm: &ChecksumAlgorithm, hex: &str) -> Option<PathBuf> {
+        let hex = hex.trim().to_ascii_lowercase();
+        if hex.len() < 8 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(self.root.join(algorithm.name()).join(&hex[..2]).join(&hex))
+    }
+
+    /// Whether an object with this digest is present (without verifying it).
+    pub fn contains(&self, algorithm: &ChecksumAlgorithm, hex: &str) -> bool {
+        self.object_path(algorithm, hex).is_some_and(|p| p.is_file())
+    }
+
+    /// Place the object for `hex` at `dest`, replacing any existing file.
+    ///
+    /// The object is re-hashed first; a corrupt object is deleted and `Ok(None)`
+    /// is returned, as it is when the digest is not in the store.
+    pub fn materialize(
+        &self,
+        algorithm: &ChecksumAlgorithm,
+        hex: &str,
+        dest: &Path,
+    ) -> Result<Option<LinkKind>, KgetError> {
+        let Some(object) = self.object_path(algorithm, hex) else {
+            return Ok(None);
+        };
+        if !object.is_file() {
+            return Ok(None);
+        }
+        if compute_checksum(&object, algorithm)? != hex.trim().to_ascii_lowercase() {
+            let _ = fs::remove_file(&object);
+            return Ok(None);
+        }
+        if same_file(&object, dest) {
+            return Ok(Some(LinkKind::Hardlink));
+        }
+
+        if let Some(parent) = dest.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        if dest.is_file() {
+            fs::remove_file(dest)?;
+        }
+        let kind = link_or_copy(&object, dest)?;
+        // Mark the object as recently used for `gc --older-than`.
+        touch(&object);
+        Ok(Some(kind))
+    }
+
+    /// Add the verified file at `path` under `hex`.
+    ///
+    /// Returns `Ok(false)` if the digest was already present. The caller is
+    /// responsible for having verified that `path` really has this digest.
+    pub fn insert(
+        &self,
+        path: &Path,
+        algorithm: &ChecksumAlgorithm,
+        hex: &str,
+    ) -> Result<bool, KgetError> {
+        let object = self
+            .object_path(algorithm, hex)
+            .ok_or_else(|| KgetError::Other(format!("Invalid {} digest: {}", algorithm.name(), hex)))?;
+        if object.is_file() {
+            return Ok(false);
+        }
+        let parent = object.parent().expect("object path has a parent");
+        fs::create_dir_all(parent)?;
+
+        // Link under a temporary name and rename, so a concurrent reader never
+        // sees a partially copied object.
+        let tmp = parent.join(format!(".tmp-{}-{}", std::process::id(), object_name(&object)));
+        let _ = fs::remove_file(&tmp);
+        link_or_copy(path, &tmp)?;
+        if let Err(e) = fs::rename(&tmp, &object) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(true)
+    }
+
+    /// Prune the store.
+    ///
+    /// By default only orphaned objects are removed: objects whose inode is no
+    /// longer linked from anywhere outside the store. Reflinked and copied
+    /// objects always count as orphaned, since the store cannot see their
+    /// users. On platforms without link counts nothing is orphaned, so use
+    /// `older_than` or `all` there.
+    pub fn gc(&self, options: &GcOptions) -> Result<GcReport, KgetError> {
+        let mut report = GcReport::default();
+        let objects = self.objects()?;
+
+        // Objects stored under several algorithms share one inode; count the
+        // store's own links per inode so they don't keep each other alive.
+        #[cfg(unix)]
+        let store_links = {
+            use std::os::unix::fs::MetadataExt;
+            let mut counts = std::collections::HashMap::<(u64, u64), u64>::new();
+            for (_, meta) in &objects {
+                *counts.entry((meta.dev(), meta.ino())).or_default() += 1;
+            }
+            counts
+        };
+
+        let now = SystemTime::now();
+        #[cfg(unix)]
+        let mut counted = std::collections::HashSet::<(u64, u64)>::new();
+        for (path, meta) in &objects {
+            if object_name(path).starts_with(".tmp-") {
+                // Leftover from an interrupted insert.
+                remove(path, options.dry_run, &mut report, meta.len());
+                continue;
+            }
+
+            let expired = options.older_than.is_some_and(|age| {
+                meta.modified()
+                    .ok()
+                    .and_then(|m| now.duration_since(m).ok())
+                    .is_some_and(|elapsed| elapsed > age)
+            });
+
+            // Space is only freed once the last link of an inode goes, and
+            // only if nothing outside the store still links to it.
+            #[cfg(unix)]
+            let (orphaned, freed) = {
+                use std::os::unix::fs::MetadataExt;
+                let key = (meta.dev(), meta.ino());
+                let orphaned = meta.nlink() <= store_links[&key];
+                let freed = if orphaned && counted.insert(key) { meta.len() } else { 0 };
+                (orphaned, freed)
+            };
+            #[cfg(not(unix))]
+            let (orphaned, freed) = (false, meta.len());
+
+            if options.all || expired || orphaned {
+                remove(path, options.dry_run, &mut report, freed);
+            } else {
+                report.kept += 1;
+            }
+        }
+
+        if !options.dry_run {
+            prune_empty_dirs(&self.root);
+        }
+        Ok(report)
+    }
+
+    /// All files under `<root>/<algorithm>/<prefix>/`.
+    fn objects(&self) -> Result<Vec<(PathBuf, fs::Metadata)>, KgetError> {
+        let mut out = Vec::new();
+        let Ok(algorithms) = fs::read_dir(&self.root) else {
+            return Ok(out);
+        };
+        for algorithm in algorithms.flatten() {
+            let Ok(prefixes) = fs::read_dir(algorithm.path()) else { continue };
+            for prefix in prefixes.flatten() {
+                let Ok(entries) = fs::read_dir(prefix.path()) else { continue };
+                for entry in entries.flatten() {
+                    let meta = entry.metadata()?;
+                    if meta.is_file() {
+                        out.push((entry.path(), meta));
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Remove `path` if it is a file shared with other hardlinks.
+///
+/// [`open_output`] calls this before truncating an output, so a hardlinked
+/// store object is not overwritten through the output path.
+pub fn detach(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(meta) = fs::symlink_metadata(path)
+            && meta.is_file()
+            && meta.nlink() > 1
+        {
+            fs::remove_file(path)?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// How [`open_output`] treats an existing output file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Start over: create or truncate.
+    Create,
+    /// Keep the contents and append to them (resuming a partial file).
+    Append,
+    /// Keep the contents and write at offsets; create the file if missing.
+    Update,
+}
+
+/// Open a download's output for writing.
+///
+/// Every writer goes through here, because the output may be a hardlink to
+/// a store object (see [`ContentStore::materialize`]). A shared file is
+/// unlinked before it is truncated ([`detach`]), and replaced by a private
+/// copy before it is written in place, so neither reaches the object.
+pub fn open_output(path: &Path, mode: OutputMode) -> io::Result<fs::File> {
+    match mode {
+        OutputMode::Create => {
+            detach(path)?;
+            fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+        }
+        OutputMode::Append => {
+            unshare(path)?;
+            fs::OpenOptions::new().append(true).open(path)
+        }
+        OutputMode::Update => {
+            unshare(path)?;
+            fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)
+        }
+    }
+}
+
+/// Replace `path` by a private copy if it is a file shared with other
+/// hardlinks, keeping its contents.
+fn unshare(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(meta) = fs::symlink_metadata(path)
+            && meta.is_file()
+            && meta.nlink() > 1
+        {
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".kget-unshare");
+            let tmp = PathBuf::from(tmp);
+            fs::copy(path, &tmp)?;
+            if let Err(e) = fs::rename(&tmp, path) {
+                let _ = fs::remove_file(&tmp);
+                return Err(e);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Expand a leading `~` to the home directory.
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    let p = PathBuf::from(path);
+    if let Ok(rest) = p.strip_prefix("~")
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(rest);
+    }
+    p
+}
+
+// ── Internals ──
+
+/// Hardlink `src` to `dest`, falling back to a reflink and then a copy.
+fn link_or_copy(src: &Path, dest: &Path) -> io::Result<LinkKind> {
+    if fs::hard_link(src, dest).is_ok() {
+        return Ok(LinkKind::Hardlink);
+    }
+    if reflink(src, dest) {
+        return Ok(LinkKind::Reflink);
+    }
+    fs::copy(src, dest)?;
+    Ok(LinkKind::Copy)
+}
+
+/// Clone `src` to `dest` with the system `cp`, if the filesystem supports it.
+fn reflink(src: &Path, dest: &Path) -> bool {
+    let flag = if cfg!(target_os = "macos") {
+        "-c"
+    } else if cfg!(target_os = "linux") {
+        "--reflink=always"
+    } else {
+        return false;
+    };
+    let ok = Command::new("cp")
+        .arg(flag)
+        .arg(src)
+        .arg(dest)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !ok {
+        let _ = fs::remove_file(dest);
+    }
+    ok
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let (Ok(ma), Ok(mb)) = (fs::metadata(a), fs::metadata(b)) {
+            return ma.dev() == mb.dev() && ma.ino() == mb.ino();
+        }
+        false
+    }
+    #[cfg(not(unix))]
+    {
+        a.canonicalize().ok().is_some_and(|ca| b.canonicalize().ok() == Some(ca))
+    }
+}
+
+fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+fn object_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn remove(path: &Path, dry_run: bool, report: &mut GcReport, freed: u64) {
+    if dry_run || fs::remove_file(path).is_ok() {
+        report.removed += 1;
+        report.bytes_freed += freed;
+    } else {
+        report.kept += 1;
+    }
+}
+
+/// Remove empty `<algorithm>/<prefix>` directories (never the root itself).
+fn prune_empty_dirs(root: &Path) {
+    let Ok(algorithms) = fs::read_dir(root) else { return };
+    for algorithm in algorithms.flatten() {
+        if let Ok(prefixes) = fs::read_dir(algorithm.path()) {
+            for prefix in prefixes.flatten() {
+                let _ = fs::remove_dir(prefix.path());
+            }
+        }
+        let _ = fs::remove_dir(algorithm.path());
+    }
+}
@@ -4,6 +4,7 @@
 //! - Console output management
 //! - URL filename extraction
 //! - Path resolution
+//! - Shell-style glob matching
 //!
 //! # Example
 //!
@@ -129,6 +130,125 @@ pub fn resolve_output_path(output_arg: Option<String>, url: &str, default_name:
     }
 }
 
+/// Returns `true` if the URL names a directory (trailing `/`) or a glob
+/// pattern in its last path segment rather than a single file.
+///
+/// FTP and SFTP download such URLs into a directory. A path without the
+/// trailing slash that turns out to be a directory is detected after
+/// connecting.
+pub fn is_tree_url(url: &str) -> bool {
+    let Ok(url) = url::Url::parse(url) else {
+        return false;
+    };
+    let path = url.path();
+    let last = path.rsplit('/').next().unwrap_or_default();
+    let last = urlencoding::decode(last).map_or_else(|_| last.into(), |s| s.into_owned());
+    path.ends_with('/') || is_glob_pattern(&last)
+}
+
+/// Split the path of a tree URL into the remote directory to walk and the
+/// glob pattern its last segment holds, if any.
+///
+/// `/pub/*.iso` gives `("/pub", Some("*.iso"))`, `/pub/` and `/pub` give
+/// `("/pub", None)`, and an empty directory becomes `/`.
+pub fn split_tree_path(path: &str) -> (&str, Option<&str>) {
+    let (dir, last) = path.rsplit_once('/').unwrap_or(("", path));
+    let (dir, pattern) = if is_glob_pattern(last) {
+        (dir, Some(last))
+    } else {
+        (path.trim_end_matches('/'), None)
+    };
+    (if dir.is_empty() { "/" } else { dir }, pattern)
+}
+
+/// Returns `true` if a remote directory entry `name` can be joined to a
+/// local directory without escaping it.
+pub fn is_safe_file_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
+/// Returns `true` if `s` contains shell glob characters (`*`, `?` or `[`).
+pub fn is_glob_pattern(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Match a file name against a shell-style glob.
+///
+/// Supports `*` (any run of characters), `?` (one character) and bracket
+/// classes such as `[abc]`, `[0-9]` or `[!.]`. An unterminated `[` matches
+/// itself.
+///
+/// # Example
+///
+/// ```rust
+/// use kget::utils::glob_match;
+///
+/// assert!(glob_match("*.iso", "debian-12.iso"));
+/// assert!(glob_match("v[0-9].?", "v1.x"));
+/// assert!(!glob_match("*.iso", "debian-12.iso.sig"));
+/// ```
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Position of the last `*` and the name index it currently swallows up to.
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() {
+            if p[pi] == '*' {
+                star = Some((pi, ni));
+                pi += 1;
+                continue;
+            }
+            let next = match p[pi] {
+                '?' => Some(pi + 1),
+                '[' => match_class(&p, pi, n[ni]),
+                c if c == n[ni] => Some(pi + 1),
+                _ => None,
+            };
+            if let Some(next) = next {
+                pi = next;
+                ni += 1;
+                continue;
+            }
+        }
+        let Some((star_pi, star_ni)) = star else {
+            return false;
+        };
+        pi = star_pi + 1;
+        ni = star_ni + 1;
+        star = Some((star_pi, star_ni + 1));
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Match `ch` against the bracket class opening at `p[start]`; returns the
+/// pattern index after the class when it matches.
+fn match_class(p: &[char], start: usize, ch: char) -> Option<usize> {
+    let mut i = start + 1;
+    let negated = matches!(p.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < p.len() && (first || p[i] != ']') {
+        first = false;
+        if p.get(i + 1) == Some(&'-') && p.get(i + 2).is_some_and(|&c| c != ']') {
+            matched |= p[i] <= ch && ch <= p[i + 2];
+            i += 3;
+        } else {
+            matched |= p[i] == ch;
+            i += 1;
+        }
+    }
+    if i >= p.len() {
+        return (ch == '[').then_some(start + 1);
+    }
+    (matched != negated).then_some(i + 1)
+}
+
 /// Returns `true` when the path's extension is a supported archive format.
 pub fn is_extractable(path: &std::path::Path) -> bool {
     let name = path
@@ -105,7 +105,7 @@ fn parse_webdav_url(url: &str) -> (String, Option<String>, Option<String>) {
 }
 
 /// Minimal RFC 4648 base64 encoder — avoids adding a `base64` crate.
-fn base64_encode(data: &[u8]) -> String {
+pub(crate) fn base64_encode(data: &[u8]) -> String {
     const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
     let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
     for chunk in data.chunks(3) {
@@ -112,7 +112,68 @@ fn test_cli_ftp_flag() {
         .arg("--help")
         .assert()
         .success()
-        .stdout(predicate::str::contains("--ftp"));
+        .stdout(predicate::str::contains("--ftp"))
+        .stdout(predicate::str::contains("--ftp-connections"))
+        .stdout(predicate::str::contains("--ftp-active"));
+}
+
+/// Run kget against a closed FTP port with `flag`, returning the saved `ftp` config section.
+fn saved_ftp_config(home: &std::path::Path, flag: &str) -> serde_json::Value {
+    saved_config(home, &[flag])["ftp"].clone()
+}
+
+/// Run a failing FTP download with `flags` and return the config it saved.
+fn saved_config(home: &std::path::Path, flags: &[&str]) -> serde_json::Value {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    kget()
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .args(["-q", "--ftp"])
+        .args(flags)
+        .arg(format!("ftp://127.0.0.1:{port}/file.bin"))
+        .timeout(std::time::Duration::from_secs(10))
+        .assert()
+        .failure();
+    let config = std::fs::read_to_string(home.join(".config/kget/config.json")).unwrap();
+    serde_json::from_str(&config).unwrap()
+}
+
+#[test]
+fn test_cli_no_ftp_tls_resets_saved_setting() {
+    let home = tempfile::TempDir::new().unwrap();
+    assert_eq!(saved_ftp_config(home.path(), "--ftp-tls")["explicit_tls"], true);
+    assert_eq!(saved_ftp_config(home.path(), "--no-ftp-tls")["explicit_tls"], false);
+}
+
+#[test]
+fn test_cli_ftp_passive_resets_saved_active_mode() {
+    let home = tempfile::TempDir::new().unwrap();
+    assert_eq!(saved_ftp_config(home.path(), "--ftp-active")["passive_mode"], false);
+    assert_eq!(saved_ftp_config(home.path(), "--ftp-passive")["passive_mode"], true);
+}
+
+#[test]
+fn test_cli_metalink_preferences_are_not_saved() {
+    let home = tempfile::TempDir::new().unwrap();
+    let config = saved_config(
+        home.path(),
+        &["--metalink-os", "linux", "--metalink-language", "en", "--metalink-country", "de"],
+    );
+    assert_eq!(config["metalink"]["os"], serde_json::Value::Null);
+    assert_eq!(config["metalink"]["language"], serde_json::Value::Null);
+    assert_eq!(config["metalink"]["country"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_cli_s3_endpoint_and_region_are_not_saved() {
+    let home = tempfile::TempDir::new().unwrap();
+    let config = saved_config(home.path(), &["--s3-endpoint", "http://localhost:9000", "--s3-region", "eu-west-1"]);
+    assert_eq!(config["s3"]["endpoint"], serde_json::Value::Null);
+    assert_eq!(config["s3"]["region"], serde_json::Value::Null);
 }
 
 #[test]
@@ -121,7 +182,11 @@ fn test_cli_sftp_flag() {
         .arg("--help")
         .assert()
         .success()
-        .stdout(predicate::str::contains("--sftp"));
+        .stdout(predicate::str::contains("--sftp"))
+        .stdout(predicate::str::contains("--sftp-connections"))
+        .stdout(predicate::str::contains("--sftp-key"))
+        .stdout(predicate::str::contains("--sftp-symlinks"))
+        .stdout(predicate::str::contains("--preserve"));
 }
 
 #[test]
@@ -190,6 +255,27 @@ fn test_cli_nonexistent_proxy() {
         .failure();
 }
 
+#[test]
+fn test_cli_scp_url_uses_ssh() {
+    // Nothing listens on this port: the scp:// URL must be dispatched to the
+    // SSH connection code rather than the HTTP downloader.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    // Fresh config, so no proxy saved by another test applies
+    let home = tempfile::TempDir::new().unwrap();
+    kget()
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join(".config"))
+        .args(["-q", &format!("scp://admin@127.0.0.1:{port}/cfg/running-config")])
+        .timeout(std::time::Duration::from_secs(10))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!("Cannot connect to 127.0.0.1:{port}")));
+}
+
 // ============================================================================
 // Feature Tests
 // ============================================================================
@@ -319,6 +405,207 @@ fn test_cli_quiet_produces_minimal_output() {
         .stdout(predicate::str::contains("Usage"));
 }
 
+// ============================================================================
+// Subcommand Tests
+// ============================================================================
+#[test]
+fn test_cli_store_gc_dry_run() {
+    let home = tempfile::TempDir::new().unwrap();
+    kget()
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join(".config"))
+        .args(["store", "gc", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would remove 0 object(s)"));
+}
+
+#[test]
+fn test_cli_cache_list_and_verify_empty_cache() {
+    let home = tempfile::TempDir::new().unwrap();
+    for (args, expected) in [
+        (&["cache", "list"][..], "0 entries"),
+        (&["cache", "verify"][..], "0 ok, 0 corrupted"),
+    ] {
+        kget()
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", home.path().join(".config"))
+            .env("XDG_CACHE_HOME", home.path().join(".cache"))
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(expected));
+    }
+}
+
+#[test]
+fn test_cli_cache_prune_rejects_bad_size() {
+    kget()
+        .args(["cache", "prune", "--max-size", "12Q"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size"));
+}
+
+#[test]
+fn test_cli_verifies_minisign_signature() {
+    // Test vector from the minisign-verify crate: a signature over "test".
+    const KEY: &str = "untrusted comment: minisign public key E7620F1842B4E81F\n\
+                       RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\n";
+    const SIG: &str = "untrusted comment: signature from minisign secret key\n\
+        RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=\n\
+        trusted comment: timestamp:1556193335\tfile:test\n\
+        y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==\n";
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    std::fs::create_dir(&out).unwrap();
+    std::fs::write(dir.path().join("minisign.pub"), KEY).unwrap();
+    std::fs::write(dir.path().join("test.minisig"), SIG).unwrap();
+
+    for (content, ok) in [("test", true), ("tampered", false)] {
+        std::fs::write(dir.path().join("test"), content).unwrap();
+        let url = format!("file://{}", dir.path().join("test").display());
+        let assert = kget()
+            .env("HOME", dir.path())
+            .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+            .env("XDG_CACHE_HOME", dir.path().join(".cache"))
+            .arg(&url)
+            .arg("-O")
+            .arg(&out)
+            .arg("--keyring")
+            .arg(dir.path().join("minisign.pub"))
+            .assert();
+        if ok {
+            assert.success().stdout(predicate::str::contains("Good minisign signature"));
+        } else {
+            assert.failure().stderr(predicate::str::contains("Signature verification failed"));
+        }
+    }
+}
+
+#[test]
+fn test_cli_checks_integrity_string() {
+    // sha512 of "hello world".
+    const SRI: &str = "sha512-MJ7MSJwS1utMxA9QyQLytNDtd+5RGnx6m808qG1M2G+YndNbxf9JlnDaNCVbRbDP2DDoH2Bdz33FVC6TrpzXbw==";
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    std::fs::create_dir(&out).unwrap();
+
+    for (content, ok) in [("hello world", true), ("tampered", false)] {
+        std::fs::write(dir.path().join("hello.txt"), content).unwrap();
+        let url = format!("file://{}", dir.path().join("hello.txt").display());
+        let assert = kget()
+            .env("HOME", dir.path())
+            .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+            .env("XDG_CACHE_HOME", dir.path().join(".cache"))
+            .arg(&url)
+            .arg("-O")
+            .arg(&out)
+            .args(["--integrity", SRI])
+            .assert();
+        if ok {
+            assert.success();
+        } else {
+            assert.failure().stderr(predicate::str::contains("sha512 mismatch"));
+        }
+    }
+
+    kget()
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+        .args(["file:///dev/null", "--integrity", "md5-XUFAKrxLKna5cZ2REBfFkg=="])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid integrity string"));
+}
+
+#[test]
+fn test_cli_hash_multiple_algorithms() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join("hello.txt");
+    std::fs::write(&file, "hello world").unwrap();
+    kget()
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+        .arg("hash")
+        .arg(&file)
+        .args(["--algo", "sha256,md5"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("SHA256 ("))
+        .stdout(predicate::str::contains(
+            ") = b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+        ))
+        .stdout(predicate::str::contains(") = 5eb63bbbe01eeed093cb22bb8f5acdc3"));
+
+    kget()
+        .args(["hash", "missing.bin", "--algo", "crc64"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown algorithm"));
+}
+
+#[test]
+fn test_cli_verify_manifest_json() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("hello.txt"), "hello world").unwrap();
+    std::fs::write(dir.path().join("stray.txt"), "not listed").unwrap();
+    std::fs::write(
+        dir.path().join("SHA256SUMS"),
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9  hello.txt\n",
+    )
+    .unwrap();
+
+    let output = kget()
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+        .args(["verify", "--json"])
+        .arg(dir.path().join("SHA256SUMS"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["passed"], true);
+    assert_eq!(report["ok"], serde_json::json!(["hello.txt"]));
+    assert_eq!(report["extra"], serde_json::json!(["stray.txt"]));
+
+    std::fs::write(dir.path().join("hello.txt"), "tampered").unwrap();
+    kget()
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+        .arg("verify")
+        .arg(dir.path().join("SHA256SUMS"))
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("hello.txt: FAILED"));
+}
+
+#[test]
+fn test_cli_metalink_create() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("hello.txt"), "hello world").unwrap();
+    let manifest = dir.path().join("hello.meta4");
+
+    kget()
+        .env("HOME", dir.path())
+        .env("XDG_CONFIG_HOME", dir.path().join(".config"))
+        .args(["metalink", "create", "--mirror", "https://a.example.com/files,1,de", "--mirror", "https://b.example.com/files"])
+        .arg(dir.path().join("hello.txt"))
+        .arg("-o")
+        .arg(&manifest)
+        .assert()
+        .success();
+
+    let xml = std::fs::read_to_string(&manifest).unwrap();
+    assert!(xml.contains(r#"<hash type="sha-256">b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9</hash>"#));
+    assert!(xml.contains(r#"<url location="de" priority="1">https://a.example.com/files/hello.txt</url>"#));
+    let doc = kget::metalink::parse(&xml).unwrap();
+    assert_eq!(doc.files[0].urls.len(), 2);
+    assert!(doc.files[0].pieces.is_some());
+}
+
 // ============================================================================
 // Concurrent Execution Tests
 // ============================================================================
@@ -340,3 +627,4 @@ fn test_cli_multiple_help_calls() {
         handle.join().unwrap();
     }
 }
+
@@ -74,6 +74,56 @@ mod utils_tests {
         );
         assert_eq!(result, "custom_name.zip");
     }
+
+    #[test]
+    fn test_glob_match() {
+        use kget::utils::{glob_match, is_glob_pattern};
+
+        assert!(glob_match("*.iso", "ubuntu-24.04.iso"));
+        assert!(!glob_match("*.iso", "ubuntu-24.04.iso.sha256"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(glob_match("v[0-9]*", "v2.1"));
+        assert!(!glob_match("v[!0-9]*", "v2.1"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(glob_match("[abc", "[abc"));
+        assert!(glob_match("*", ""));
+
+        assert!(is_glob_pattern("*.iso"));
+        assert!(!is_glob_pattern("plain.iso"));
+    }
+
+    #[test]
+    fn test_split_tree_path() {
+        use kget::utils::split_tree_path;
+
+        assert_eq!(split_tree_path("/pub/*.iso"), ("/pub", Some("*.iso")));
+        assert_eq!(split_tree_path("/pub/"), ("/pub", None));
+        assert_eq!(split_tree_path("/pub"), ("/pub", None));
+        assert_eq!(split_tree_path("/*.txt"), ("/", Some("*.txt")));
+        assert_eq!(split_tree_path("/"), ("/", None));
+        assert_eq!(split_tree_path(""), ("/", None));
+    }
+
+    #[test]
+    fn test_is_safe_file_name() {
+        use kget::utils::is_safe_file_name;
+
+        assert!(is_safe_file_name("file.iso"));
+        assert!(!is_safe_file_name(".."));
+        assert!(!is_safe_file_name("../etc/passwd"));
+        assert!(!is_safe_file_name("a\\b"));
+    }
+
+    #[test]
+    fn test_ftp_tree_urls() {
+        use kget::ftp::is_tree_url;
+
+        assert!(is_tree_url("ftp://host/pub/"));
+        assert!(is_tree_url("ftp://host/pub/*.iso"));
+        assert!(is_tree_url("ftp://host/pub/file%3F.iso"));
+        assert!(!is_tree_url("ftp://host/pub/file.iso"));
+    }
 }
 
 // ============================================================================
@@ -107,9 +157,12 @@ mod config_tests {
         // FTP defaults
         assert!(config.ftp.passive_mode);
         assert_eq!(config.ftp.default_port, 21);
+        assert!(!config.ftp.explicit_tls);
+        assert!(config.ftp.verify_certificates);
 
         // SFTP defaults
         assert_eq!(config.sftp.default_port, 22);
+        assert_eq!(config.sftp.connections, None);
     }
 
     #[test]
@@ -185,6 +238,23 @@ mod config_tests {
             Some("http://proxy.example.com:8080".to_string())
         );
     }
+
+    #[test]
+    fn test_config_partial_s3_section() {
+        let json = r#"{
+            "proxy": { "enabled": false, "url": null, "username": null, "password": null, "proxy_type": "Http" },
+            "optimization": { "compression": true, "compression_level": 6, "cache_enabled": true, "cache_dir": "~/.cache/kget", "speed_limit": null, "max_connections": 4 },
+            "torrent": { "enabled": false, "download_dir": null, "max_peers": 50, "max_seeds": 25, "port": null, "dht_enabled": true, "max_peer_connections": 50, "max_upload_slots": 4 },
+            "ftp": { "passive_mode": true, "default_port": 21 },
+            "sftp": { "default_port": 22, "key_path": null },
+            "s3": { "region": "eu-west-1" }
+        }"#;
+
+        let config: Config = serde_json::from_str(json).expect("hand-written s3 section should load");
+        assert_eq!(config.s3.region.as_deref(), Some("eu-west-1"));
+        assert!(!config.s3.path_style);
+        assert_eq!(config.s3.presign_expiry_secs, Config::default().s3.presign_expiry_secs);
+    }
 }
 
 // ============================================================================
@@ -415,6 +485,113 @@ mod download_options_tests {
     }
 }
 
+// ============================================================================
+// Local Source (file:// and data:) Tests
+// ============================================================================
+
+mod local_source_tests {
+    use kget::{DownloadOptions, Optimizer, ProxyConfig, download};
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+
+    fn options(output: &std::path::Path, sha256: Option<String>) -> DownloadOptions {
+        DownloadOptions {
+            quiet_mode: true,
+            output_path: Some(output.to_string_lossy().to_string()),
+            verify_iso: false,
+            expected_sha256: sha256,
+            extra_headers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_file_url_is_copied_and_verified() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("artifact.bin");
+        let body = b"local artifact contents";
+        std::fs::write(&source, body).unwrap();
+
+        let url = url::Url::from_file_path(&source).unwrap().to_string();
+        let dest = temp_dir.path().join("out").join("copy.bin");
+        let expected = hex::encode(Sha256::digest(body));
+
+        download(&url, ProxyConfig::default(), Optimizer::new(), options(&dest, Some(expected)), None)
+            .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+    }
+
+    #[test]
+    fn test_file_url_checksum_mismatch_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("artifact.bin");
+        std::fs::write(&source, b"contents").unwrap();
+
+        let url = url::Url::from_file_path(&source).unwrap().to_string();
+        let dest = temp_dir.path().join("copy.bin");
+        let result = download(
+            &url,
+            ProxyConfig::default(),
+            Optimizer::new(),
+            options(&dest, Some("0".repeat(64))),
+            None,
+        );
+        assert!(result.unwrap_err().to_string().contains("mismatch"));
+    }
+
+    #[test]
+    fn test_file_url_missing_source_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = url::Url::from_file_path(temp_dir.path().join("nope.bin")).unwrap().to_string();
+        let dest = temp_dir.path().join("copy.bin");
+        assert!(download(&url, ProxyConfig::default(), Optimizer::new(), options(&dest, None), None).is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_file_url_refuses_to_overwrite_itself() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("same.bin");
+        std::fs::write(&source, b"keep me").unwrap();
+
+        let url = url::Url::from_file_path(&source).unwrap().to_string();
+        assert!(download(&url, ProxyConfig::default(), Optimizer::new(), options(&source, None), None).is_err());
+        assert_eq!(std::fs::read(&source).unwrap(), b"keep me");
+    }
+
+    #[test]
+    fn test_data_url_is_decoded_to_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest = temp_dir.path().join("hello.txt");
+        download(
+            "data:text/plain;base64,SGVsbG8sIFdvcmxkIQ==",
+            ProxyConfig::default(),
+            Optimizer::new(),
+            options(&dest, None),
+            None,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"Hello, World!");
+    }
+
+    #[test]
+    fn test_batch_mixes_local_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("a.txt");
+        std::fs::write(&source, b"from disk").unwrap();
+        let out_dir = temp_dir.path().join("out");
+        std::fs::create_dir(&out_dir).unwrap();
+
+        let url = url::Url::from_file_path(&source).unwrap().to_string();
+        let results = kget::batch([url])
+            .output_dir(out_dir.to_string_lossy())
+            .quiet(true)
+            .download_all();
+
+        assert!(results[0].result.is_ok());
+        assert_eq!(std::fs::read(out_dir.join("a.txt")).unwrap(), b"from disk");
+    }
+}
+
 // ============================================================================
 // Optimizer Tests
 // ============================================================================
@@ -465,6 +642,384 @@ mod optimizer_tests {
     }
 }
 
+// ============================================================================
+// Content Store Tests
+// ============================================================================
+
+mod store_tests {
+    use kget::checksum::ChecksumAlgorithm;
+    use kget::store::{ContentStore, GcOptions};
+    use sha2::{Digest, Sha256};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    #[test]
+    fn test_insert_then_materialize_links_object() {
+        let dir = TempDir::new().unwrap();
+        let store = ContentStore::new(dir.path().join("store"));
+        let src = dir.path().join("a.bin");
+        fs::write(&src, b"stored payload").unwrap();
+        let hash = sha256_hex(b"stored payload");
+
+        assert!(store.insert(&src, &ChecksumAlgorithm::Sha256, &hash).unwrap());
+        assert!(!store.insert(&src, &ChecksumAlgorithm::Sha256, &hash).unwrap());
+        assert!(store.contains(&ChecksumAlgorithm::Sha256, &hash));
+
+        let dest = dir.path().join("nested").join("b.bin");
+        let kind = store.materialize(&ChecksumAlgorithm::Sha256, &hash, &dest).unwrap();
+        assert!(kind.is_some());
+        assert_eq!(fs::read(&dest).unwrap(), b"stored payload");
+    }
+
+    #[test]
+    fn test_materialize_miss_returns_none() {
+        let dir = TempDir::new().unwrap();
+        let store = ContentStore::new(dir.path());
+        let dest = dir.path().join("out.bin");
+        let hash = sha256_hex(b"never stored");
+        assert!(store.materialize(&ChecksumAlgorithm::Sha256, &hash, &dest).unwrap().is_none());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_corrupt_object_is_discarded() {
+        let dir = TempDir::new().unwrap();
+        let store = ContentStore::new(dir.path().join("store"));
+        let src = dir.path().join("a.bin");
+        fs::write(&src, b"original").unwrap();
+        let hash = sha256_hex(b"original");
+        store.insert(&src, &ChecksumAlgorithm::Sha256, &hash).unwrap();
+
+        // Rewriting in place goes through the hardlink into the store.
+        fs::write(store.object_path(&ChecksumAlgorithm::Sha256, &hash).unwrap(), b"tampered").unwrap();
+
+        let dest = dir.path().join("b.bin");
+        assert!(store.materialize(&ChecksumAlgorithm::Sha256, &hash, &dest).unwrap().is_none());
+        assert!(!store.contains(&ChecksumAlgorithm::Sha256, &hash));
+    }
+
+    #[test]
+    fn test_object_path_rejects_non_hex() {
+        let store = ContentStore::new("/tmp/kget-store");
+        assert!(store.object_path(&ChecksumAlgorithm::Sha256, "../../etc/passwd").is_none());
+        assert!(store.object_path(&ChecksumAlgorithm::Sha256, "abc").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_gc_removes_only_orphans() {
+        let dir = TempDir::new().unwrap();
+        let store = ContentStore::new(dir.path().join("store"));
+
+        let kept = dir.path().join("kept.bin");
+        fs::write(&kept, b"still linked").unwrap();
+        let kept_hash = sha256_hex(b"still linked");
+        store.insert(&kept, &ChecksumAlgorithm::Sha256, &kept_hash).unwrap();
+
+        let gone = dir.path().join("gone.bin");
+        fs::write(&gone, b"deleted by user").unwrap();
+        let gone_hash = sha256_hex(b"deleted by user");
+        store.insert(&gone, &ChecksumAlgorithm::Sha256, &gone_hash).unwrap();
+        fs::remove_file(&gone).unwrap();
+
+        let dry = store.gc(&GcOptions { dry_run: true, ..Default::default() }).unwrap();
+        assert_eq!(dry.removed, 1);
+        assert!(store.contains(&ChecksumAlgorithm::Sha256, &gone_hash));
+
+        let report = store.gc(&GcOptions::default()).unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.kept, 1);
+        assert_eq!(report.bytes_freed, b"deleted by user".len() as u64);
+        assert!(store.contains(&ChecksumAlgorithm::Sha256, &kept_hash));
+        assert!(!store.contains(&ChecksumAlgorithm::Sha256, &gone_hash));
+
+        let all = store.gc(&GcOptions { all: true, ..Default::default() }).unwrap();
+        assert_eq!(all.removed, 1);
+        assert!(fs::read_dir(store.root()).unwrap().next().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_output_never_writes_through_to_store() {
+        use kget::store::{OutputMode, open_output};
+        use std::io::Write;
+
+        let dir = TempDir::new().unwrap();
+        let store = ContentStore::new(dir.path().join("store"));
+        let src = dir.path().join("a.bin");
+        fs::write(&src, b"shared object").unwrap();
+        let hash = sha256_hex(b"shared object");
+        store.insert(&src, &ChecksumAlgorithm::Sha256, &hash).unwrap();
+        let object = store.object_path(&ChecksumAlgorithm::Sha256, &hash).unwrap();
+
+        // Appending (resume) keeps the contents in a private copy.
+        open_output(&src, OutputMode::Append).unwrap().write_all(b" + more").unwrap();
+        assert_eq!(fs::read(&src).unwrap(), b"shared object + more");
+
+        let dest = dir.path().join("b.bin");
+        store.materialize(&ChecksumAlgorithm::Sha256, &hash, &dest).unwrap();
+        open_output(&dest, OutputMode::Create).unwrap().write_all(b"new").unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"new");
+
+        store.materialize(&ChecksumAlgorithm::Sha256, &hash, &dest).unwrap();
+        let file = open_output(&dest, OutputMode::Update).unwrap();
+        file.set_len(4).unwrap();
+
+        assert_eq!(fs::read(&object).unwrap(), b"shared object");
+        assert!(store.contains(&ChecksumAlgorithm::Sha256, &hash));
+    }
+}
+
+// ============================================================================
+// Metalink Parsing Tests
+// ============================================================================
+
+mod metalink_tests {
+    use kget::checksum::ChecksumAlgorithm;
+    use kget::metalink;
+
+    #[test]
+    fn test_hash_types_map_onto_checksum_algorithms() {
+        let doc = metalink::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <metalink xmlns="urn:ietf:params:xml:ns:metalink">
+              <file name="a.iso">
+                <hash type="crc32c">e3069283</hash>
+                <hash type="sha3-256">3A985DA74FE225B2045C172D6BD390BD855F086E3E9D525B46BFE24511431532</hash>
+                <hash type="sha-384">cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7</hash>
+                <hash type="whirlpool">ffff</hash>
+                <url>https://example.com/a.iso</url>
+              </file>
+            </metalink>"#,
+        )
+        .unwrap();
+
+        let file = &doc.files[0];
+        let algos: Vec<_> = file.hashes.iter().map(|(a, _)| a.clone()).collect();
+        assert_eq!(
+            algos,
+            [ChecksumAlgorithm::Crc32c, ChecksumAlgorithm::Sha3_256, ChecksumAlgorithm::Sha384]
+        );
+        assert!(file.best_hash().is_none());
+        let (algo, hash) = file.best_digest().unwrap();
+        assert_eq!(algo, ChecksumAlgorithm::Sha384);
+        assert!(hash.starts_with("cb00753f"));
+    }
+
+    const METALINK3: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <metalink version="3.0" xmlns="http://www.metalinker.org/" pubdate="Mon, 01 Jan 2024 00:00:00 GMT">
+          <publisher><name>Example Project</name></publisher>
+          <files>
+            <file name="tool-linux.tar.gz">
+              <version>1.2</version>
+              <os>Linux-x86</os>
+              <language>en-US</language>
+              <verification>
+                <hash type="sha256">E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855</hash>
+                <signature type="pgp" file="tool-linux.tar.gz.asc">-----BEGIN PGP SIGNATURE-----</signature>
+              </verification>
+              <resources>
+                <url type="http" location="us" preference="50">http://us.example.com/tool-linux.tar.gz</url>
+                <url type="http" location="de" preference="100">http://de.example.com/tool-linux.tar.gz</url>
+                <url type="bittorrent" preference="90">http://example.com/tool-linux.torrent</url>
+              </resources>
+            </file>
+            <file name="tool-win.zip">
+              <os>Windows-x86</os>
+              <language>pt-BR</language>
+              <resources>
+                <url type="http">http://example.com/tool-win.zip</url>
+              </resources>
+            </file>
+          </files>
+        </metalink>"#;
+
+    #[test]
+    fn test_metalink3_is_parsed() {
+        let doc = metalink::parse(METALINK3).unwrap();
+        assert_eq!(doc.version, metalink::MetalinkVersion::V3);
+        assert_eq!(doc.files.len(), 2);
+
+        let file = &doc.files[0];
+        assert_eq!(file.version.as_deref(), Some("1.2"));
+        assert_eq!(file.publisher.as_deref(), Some("Example Project"));
+        assert_eq!(file.os, ["Linux-x86"]);
+        assert_eq!(file.languages, ["en-US"]);
+        assert_eq!(
+            file.sha256.as_deref(),
+            Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert_eq!(file.signature.as_ref().unwrap().mediatype, "application/pgp-signature");
+        // preference="100" outranks preference="50"; the torrent becomes a metaurl.
+        assert_eq!(file.urls[0].url, "http://de.example.com/tool-linux.tar.gz");
+        assert_eq!(file.urls[1].location.as_deref(), Some("us"));
+        assert_eq!(file.metaurls.len(), 1);
+        assert_eq!(file.metaurls[0].mediatype, "torrent");
+    }
+
+    #[test]
+    fn test_metalink4_metadata_is_parsed() {
+        let doc = metalink::parse(
+            r#"<metalink xmlns="urn:ietf:params:xml:ns:metalink">
+              <generator>MirrorBrain/2.19</generator>
+              <origin dynamic="true">https://example.com/app.meta4</origin>
+              <file name="app.iso">
+                <publisher name="Example" url="https://example.com"/>
+                <description>Installer</description>
+                <os>LINUX</os>
+                <language>en</language>
+                <language>fr</language>
+                <signature mediatype="application/pgp-signature">SIG</signature>
+                <metaurl mediatype="torrent" priority="2">https://example.com/app.torrent</metaurl>
+                <url location="fr" priority="2">https://fr.example.com/app.iso</url>
+                <url location="DE" priority="1">https://de.example.com/app.iso</url>
+              </file>
+            </metalink>"#,
+        )
+        .unwrap();
+
+        assert_eq!(doc.version, metalink::MetalinkVersion::V4);
+        assert_eq!(doc.generator.as_deref(), Some("MirrorBrain/2.19"));
+        assert_eq!(doc.origin.as_deref(), Some("https://example.com/app.meta4"));
+        let file = &doc.files[0];
+        assert_eq!(file.publisher.as_deref(), Some("Example"));
+        assert_eq!(file.description.as_deref(), Some("Installer"));
+        assert_eq!(file.languages, ["en", "fr"]);
+        assert_eq!(file.signature.as_ref().unwrap().body, "SIG");
+        assert_eq!(file.metaurls[0].priority, 2);
+        assert_eq!(file.urls[0].location.as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn test_metalink_selection_and_country_preference() {
+        let doc = metalink::parse(METALINK3).unwrap();
+        let prefs = |os: Option<&str>, language: Option<&str>| kget::config::MetalinkConfig {
+            os: os.map(str::to_string),
+            language: language.map(str::to_string),
+            ..Default::default()
+        };
+
+        assert_eq!(doc.select(&prefs(None, None)).len(), 2);
+        let linux = doc.select(&prefs(Some("linux"), None));
+        assert_eq!(linux.len(), 1);
+        assert_eq!(linux[0].name, "tool-linux.tar.gz");
+        assert_eq!(doc.select(&prefs(None, Some("pt")))[0].name, "tool-win.zip");
+        assert!(doc.select(&prefs(Some("windows"), Some("en"))).is_empty());
+        assert!(doc.select(&prefs(Some("lin"), None)).is_empty());
+
+        let file = &doc.files[0];
+        let urls = |country| file.mirrors(country).iter().map(|u| u.url.clone()).collect::<Vec<_>>();
+        assert_eq!(urls(None)[0], "http://de.example.com/tool-linux.tar.gz");
+        assert_eq!(
+            urls(Some("US")),
+            ["http://us.example.com/tool-linux.tar.gz", "http://de.example.com/tool-linux.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn test_link_header_mirrors_are_parsed() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.append(
+            "link",
+            r#"<http://de.example.com/f.iso>; rel=duplicate; pri=1; geo=DE, </alt/f.iso>; rel="duplicate"; pref, <http://example.com/f.meta4>; rel=describedby; type="application/metalink4+xml""#
+                .parse()
+                .unwrap(),
+        );
+        headers.append("link", "<http://us.example.com/f,1.iso>; rel=\"duplicate other\"; pri=7".parse().unwrap());
+        headers.insert("digest", "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=".parse().unwrap());
+
+        let links = metalink::HttpMetalink::from_headers(&headers, "http://example.com/pub/f.iso");
+        assert!(links.has_mirrors());
+        let mirrors: Vec<_> = links.mirrors.iter().map(|m| (m.url.as_str(), m.priority, m.location.as_deref())).collect();
+        assert_eq!(
+            mirrors,
+            [
+                ("http://de.example.com/f.iso", 1, Some("de")),
+                ("http://example.com/alt/f.iso", 1, None),
+                ("http://us.example.com/f,1.iso", 7, None),
+            ]
+        );
+
+        let file = links.into_file("http://example.com/pub/f.iso", Some(11));
+        assert_eq!(file.name, "f.iso");
+        assert_eq!(file.urls.last().unwrap().url, "http://example.com/pub/f.iso");
+        assert_eq!(file.urls.last().unwrap().priority, 8);
+        assert_eq!(
+            file.best_digest(),
+            Some((ChecksumAlgorithm::Sha256, "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"))
+        );
+    }
+
+    #[test]
+    fn test_generated_metalink_round_trips() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("release 1.0.bin");
+        let data: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+        std::fs::write(
+            dir.path().join("release 1.0.bin.asc"),
+            "-----BEGIN PGP SIGNATURE-----\nabc<&>\n-----END PGP SIGNATURE-----\n",
+        )
+        .unwrap();
+
+        let options = metalink::GenerateOptions {
+            mirrors: vec![
+                metalink::MetalinkMirror {
+                    base_url: "https://us.example.com/pub/".to_string(),
+                    priority: Some(2),
+                    location: Some("US".to_string()),
+                },
+                metalink::MetalinkMirror {
+                    base_url: "https://de.example.com/pub".to_string(),
+                    priority: Some(1),
+                    location: None,
+                },
+            ],
+            algorithms: vec![ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Sha512],
+            piece_length: Some(1024),
+            signature: true,
+            origin: Some("https://example.com/release.meta4".to_string()),
+        };
+        let generated = metalink::generate(std::slice::from_ref(&path), &options).unwrap();
+        let doc = metalink::parse(&generated.to_xml()).unwrap();
+
+        assert_eq!(doc.version, metalink::MetalinkVersion::V4);
+        assert_eq!(doc.origin.as_deref(), Some("https://example.com/release.meta4"));
+        assert!(doc.generator.as_deref().unwrap().starts_with("KGet/"));
+        let file = &doc.files[0];
+        assert_eq!(file.name, "release 1.0.bin");
+        assert_eq!(file.size, Some(2500));
+        assert_eq!(file.hashes, generated.files[0].hashes);
+        assert_eq!(
+            file.sha256.as_deref(),
+            Some(kget::checksum::compute_checksum(&path, &ChecksumAlgorithm::Sha256).unwrap().as_str())
+        );
+        assert!(file.sha512.is_some());
+
+        let pieces = file.pieces.as_ref().unwrap();
+        assert_eq!((pieces.length, pieces.hash_type.as_str(), pieces.hashes.len()), (1024, "sha-256", 3));
+        let tail = dir.path().join("tail");
+        std::fs::write(&tail, &data[2048..]).unwrap();
+        assert_eq!(pieces.hashes[2], kget::checksum::compute_checksum(&tail, &ChecksumAlgorithm::Sha256).unwrap());
+
+        let urls: Vec<_> = file.mirrors(None).iter().map(|u| (u.url.as_str(), u.priority, u.location.as_deref())).collect();
+        assert_eq!(
+            urls,
+            [
+                ("https://de.example.com/pub/release%201.0.bin", 1, None),
+                ("https://us.example.com/pub/release%201.0.bin", 2, Some("us")),
+            ]
+        );
+        let signature = file.signature.as_ref().unwrap();
+        assert_eq!(signature.mediatype, "application/pgp-signature");
+        assert!(signature.body.contains("abc<&>"));
+    }
+}
+
 // ============================================================================
 // App Contract Tests
 // ============================================================================